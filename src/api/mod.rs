@@ -0,0 +1,787 @@
+use std::net::IpAddr;
+
+use actix_web::{
+    App, delete, FromRequest, get, HttpRequest, HttpResponse, HttpServer, post, put, web,
+};
+use actix_web::dev::Payload;
+use actix_web::http::header::{CACHE_CONTROL, ETAG, HeaderName, IF_MATCH};
+use actix_web::http::StatusCode;
+use anyhow::{anyhow, bail};
+use futures::future::{ready, Ready};
+use futures::stream;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::warn;
+
+use crate::caldav;
+use crate::common::exception::InternalError;
+use crate::common::utils::parse_time;
+use crate::core::audit::{get_audit_between, get_event_audit};
+use crate::core::calendar::{delete_calendar, find_calendar, get_calendars, save_calendar};
+use crate::core::change_feed::get_changes_after;
+use crate::core::event::{delete_event, get_event, update_event};
+use crate::core::history::{get_history, redo, undo};
+use crate::core::live::{LiveMessage, LiveSink, LiveStream, open_stream};
+use crate::core::processor::{start_evictor, wake_stored_users};
+use crate::core::query::query_events_at_revision;
+use crate::core::scheduler::{dismiss_reminder, ReminderScheduler, snooze_reminder};
+use crate::core::transaction::commit;
+use crate::core::subscription::{
+    find_subscription, get_subscriptions, refresh_subscription, start_refresher,
+    subscribe_feed, unsubscribe_feed,
+};
+use crate::core::view::{create_view, delete_view, export_view, find_view, get_views, query_view};
+use crate::model::calendar::Calendar;
+use crate::model::category::ConflictBehavior;
+use crate::model::subscription::Subscription;
+use crate::model::transaction::Transaction;
+use crate::model::user::UserContext;
+use crate::notification::{LogSink, SinkConfig};
+use crate::persistent::PersistentModel;
+
+mod test;
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+// set by the authenticating proxy in front of a team server, requests without it act
+// for the default user. anyone could send it, so it is only accepted from the addresses
+// of trusted proxies and rejected from everyone else
+pub const USER_HEADER: &str = "x-forwarded-user";
+
+// the proxies allowed to set the user header, none unless the server is told about them
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(pub Vec<IpAddr>);
+
+#[derive(Deserialize)]
+struct EventsQuery {
+    q: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChangesQuery {
+    after: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct AuditQuery {
+    start: String,
+    end: String,
+}
+
+// one staged write of a transaction, like {"insert": event} or {"delete": "id"}
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum StagedWrite {
+    Insert(PersistentModel),
+    Update(PersistentModel),
+    Delete(String),
+}
+
+// the alarm of an event, none for a standalone reminder
+#[derive(Deserialize)]
+struct NotificationQuery {
+    alarm: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Snooze {
+    until: String,
+}
+
+#[derive(Deserialize)]
+struct NewView {
+    name: String,
+    query: String,
+}
+
+#[derive(Deserialize)]
+struct CalendarSettings {
+    name: String,
+    #[serde(default)]
+    color: String,
+    #[serde(default = "shown")]
+    visible: bool,
+    #[serde(default)]
+    conflict: ConflictBehavior,
+    #[serde(default)]
+    isolated: bool,
+}
+
+#[derive(Deserialize)]
+struct NewSubscription {
+    name: String,
+    source: String,
+    refresh_minutes: Option<i64>,
+    #[serde(default)]
+    count_conflicts: bool,
+}
+
+// a subscription without the feed it holds, which can be large
+#[derive(Serialize)]
+struct SubscriptionSummary<'a> {
+    id: u128,
+    name: &'a str,
+    source: &'a str,
+    refresh_minutes: i64,
+    count_conflicts: bool,
+    refreshed_time: Option<i64>,
+    error: Option<&'a str>,
+}
+
+fn shown() -> bool {
+    true
+}
+
+impl CalendarSettings {
+    fn apply(self, calendar: &mut Calendar) {
+        calendar.name = self.name;
+        calendar.color = self.color;
+        calendar.visible = self.visible;
+        calendar.conflict = self.conflict;
+        calendar.isolated = self.isolated;
+    }
+}
+
+impl FromRequest for UserContext {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let user = match request.headers().get(HeaderName::from_static(USER_HEADER)) {
+            Some(value) if is_trusted_proxy(request) => {
+                UserContext::new(value.to_str().unwrap_or_default())
+            }
+            Some(_) => Err(anyhow!(InternalError::UntrustedUserHeaderError)),
+            None => Ok(UserContext::default_user()),
+        };
+        ready(user.map_err(|error| {
+            let reason = error.to_string();
+            actix_web::error::InternalError::from_response(reason, error_response(error)).into()
+        }))
+    }
+}
+
+impl<'a> From<&'a Subscription> for SubscriptionSummary<'a> {
+    fn from(subscription: &'a Subscription) -> Self {
+        SubscriptionSummary {
+            id: subscription.get_id(),
+            name: subscription.name.as_str(),
+            source: subscription.source.as_str(),
+            refresh_minutes: subscription.refresh_minutes,
+            count_conflicts: subscription.count_conflicts,
+            refreshed_time: subscription.refreshed_time,
+            error: subscription.error.as_deref(),
+        }
+    }
+}
+
+pub fn configure(config: &mut web::ServiceConfig) {
+    config
+        .service(get_events)
+        .service(get_one_event)
+        .service(put_event)
+        .service(remove_event)
+        .service(post_transaction)
+        .service(list_views)
+        .service(post_view)
+        .service(remove_view)
+        .service(get_view_events)
+        .service(get_view_feed)
+        .service(get_view_stream)
+        .service(list_calendars)
+        .service(post_calendar)
+        .service(put_calendar)
+        .service(remove_calendar)
+        .service(list_subscriptions)
+        .service(post_subscription)
+        .service(remove_subscription)
+        .service(post_subscription_refresh)
+        .service(list_history)
+        .service(post_undo)
+        .service(post_redo)
+        .service(get_event_changes)
+        .service(get_changes)
+        .service(post_snooze)
+        .service(post_dismiss)
+        .service(list_changes)
+        .service(get_stream);
+}
+
+fn is_trusted_proxy(request: &HttpRequest) -> bool {
+    let proxies = request.app_data::<web::Data<TrustedProxies>>();
+    request
+        .peer_addr()
+        .zip(proxies)
+        .is_some_and(|(peer, proxies)| proxies.0.contains(&peer.ip()))
+}
+
+// reminders fire and feeds are refreshed while serving, so live streams get their alerts.
+// the configured sinks are added to them, those of a channel replace them for its alarms.
+// users are loaded by their first request and dropped again once idle
+pub async fn start_server(
+    address: &str,
+    proxies: TrustedProxies,
+    sinks: Vec<SinkConfig>,
+) -> std::io::Result<()> {
+    wake_stored_users();
+    let mut scheduler = ReminderScheduler::new().with_sink(LogSink).with_sink(LiveSink);
+    for config in sinks {
+        scheduler = match config.channel {
+            Some(channel) => scheduler.with_channel_sink(channel.as_str(), config.sink),
+            None => scheduler.with_sink(config.sink),
+        };
+    }
+    let scheduler = scheduler.start();
+    let refresher = start_refresher();
+    let evictor = start_evictor();
+    let result = HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::new(proxies.clone()))
+            .configure(configure)
+            .configure(caldav::configure)
+    })
+    .bind(address)?
+    .run()
+    .await;
+    scheduler.abort();
+    refresher.abort();
+    evictor.abort();
+    result
+}
+
+// events in their stored form, q is a query like kind:event tag:billable
+#[get("/events")]
+async fn get_events(user: UserContext, query: web::Query<EventsQuery>) -> HttpResponse {
+    match query_events_at_revision(&user, query.q.as_deref().unwrap_or("")).await {
+        Ok((revision, events)) => HttpResponse::Ok().insert_header((ETAG, etag(revision))).json(
+            events
+                .iter()
+                .map(|event| event.convert_to(None))
+                .collect::<Vec<PersistentModel>>(),
+        ),
+        Err(error) => error_response(error),
+    }
+}
+
+// the ETag is the revision of the event, to send back as If-Match when changing it
+#[get("/events/{id}")]
+async fn get_one_event(user: UserContext, path: web::Path<String>) -> HttpResponse {
+    let event = async { get_event(&user, parse_id(path.as_str())?).await }.await;
+    match event {
+        Ok(event) => event_response(event),
+        Err(error) => error_response(error),
+    }
+}
+
+// without If-Match the revision in the body is expected
+#[put("/events/{id}")]
+async fn put_event(
+    user: UserContext,
+    request: HttpRequest,
+    path: web::Path<String>,
+    event: web::Json<PersistentModel>,
+) -> HttpResponse {
+    let mut event = event.into_inner();
+    let updated = async {
+        event.id = parse_id(path.as_str())?;
+        let expected = required_revision(&request, event.revision)?;
+        update_event(&user, event, Some(expected)).await
+    }
+    .await;
+    match updated {
+        Ok(event) => event_response(event),
+        Err(error) => error_response(error),
+    }
+}
+
+#[delete("/events/{id}")]
+async fn remove_event(
+    user: UserContext,
+    request: HttpRequest,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let deleted = async {
+        let expected = required_revision(&request, 0)?;
+        delete_event(&user, parse_id(path.as_str())?, Some(expected)).await
+    }
+    .await;
+    match deleted {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(error) => error_response(error),
+    }
+}
+
+// the writes are committed in order, all of them or none. a staged event that can not be
+// read rejects the whole transaction before anything is written
+#[post("/transactions")]
+async fn post_transaction(user: UserContext, writes: web::Json<Vec<StagedWrite>>) -> HttpResponse {
+    let committed = async {
+        let mut transaction = Transaction::new();
+        for write in writes.into_inner() {
+            match write {
+                StagedWrite::Insert(event) => transaction.insert(event.convert_to()?),
+                StagedWrite::Update(event) => transaction.update(event.convert_to()?),
+                StagedWrite::Delete(id) => transaction.delete(parse_id(id.as_str())?),
+            };
+        }
+        commit(&user, transaction).await
+    }
+    .await;
+    match committed {
+        Ok(result) => HttpResponse::Ok().json(result),
+        Err(error) => error_response(error),
+    }
+}
+
+// paths cannot deserialize u128 ids directly, an id that does not parse matches no event
+fn parse_id(id: &str) -> anyhow::Result<u128> {
+    id.parse().map_err(|_| anyhow!(InternalError::EventNotFoundError))
+}
+
+fn parse_alarm_id(id: &str) -> anyhow::Result<u128> {
+    id.parse().map_err(|_| anyhow!(InternalError::AlarmNotFoundError))
+}
+
+fn event_response(event: PersistentModel) -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header((ETAG, etag(event.revision)))
+        .json(event)
+}
+
+pub(crate) fn etag(revision: u64) -> String {
+    format!("\"{}\"", revision)
+}
+
+// None when there is no If-Match or it is *
+pub(crate) fn expected_revision(request: &HttpRequest) -> anyhow::Result<Option<u64>> {
+    let value = match request.headers().get(IF_MATCH) {
+        Some(value) => value.to_str().unwrap_or_default().trim(),
+        None => return Ok(None),
+    };
+    if value == "*" {
+        return Ok(None);
+    }
+    let revision = value.trim_start_matches("W/").trim_matches('"').parse::<u64>();
+    match revision {
+        Ok(revision) => Ok(Some(revision)),
+        Err(_) => bail!(InternalError::InvalidRevisionError {
+            revision: value.to_string()
+        }),
+    }
+}
+
+// the revision a change is based on, from If-Match or else the given one. a change
+// without it would overwrite whatever others stored meanwhile
+fn required_revision(request: &HttpRequest, otherwise: u64) -> anyhow::Result<u64> {
+    let revision = match request.headers().get(IF_MATCH) {
+        Some(_) => expected_revision(request)?.unwrap_or(0),
+        None => otherwise,
+    };
+    if revision == 0 {
+        bail!(InternalError::PreconditionRequiredError)
+    }
+    Ok(revision)
+}
+
+#[get("/views")]
+async fn list_views(user: UserContext) -> HttpResponse {
+    match get_views(&user).await {
+        Ok(views) => HttpResponse::Ok().json(views),
+        Err(error) => error_response(error),
+    }
+}
+
+#[post("/views")]
+async fn post_view(user: UserContext, view: web::Json<NewView>) -> HttpResponse {
+    let view = view.into_inner();
+    match create_view(&user, view.name, view.query).await {
+        Ok(view) => HttpResponse::Created().json(view),
+        Err(error) => error_response(error),
+    }
+}
+
+// views are addressed by id or by name in paths
+#[delete("/views/{view}")]
+async fn remove_view(user: UserContext, path: web::Path<String>) -> HttpResponse {
+    let result = match find_view(&user, path.into_inner()).await {
+        Ok(view) => delete_view(&user, view.get_id()).await,
+        Err(error) => Err(error),
+    };
+    match result {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(error) => error_response(error),
+    }
+}
+
+#[get("/views/{view}/events")]
+async fn get_view_events(
+    user: UserContext,
+    path: web::Path<String>,
+    query: web::Query<EventsQuery>,
+) -> HttpResponse {
+    let events = match find_view(&user, path.into_inner()).await {
+        Ok(view) => query_view(&user, view.get_id(), query.q.as_deref().unwrap_or("")).await,
+        Err(error) => Err(error),
+    };
+    match events {
+        Ok(events) => HttpResponse::Ok().json(
+            events
+                .iter()
+                .map(|event| event.convert_to(None))
+                .collect::<Vec<PersistentModel>>(),
+        ),
+        Err(error) => error_response(error),
+    }
+}
+
+#[get("/views/{view}/feed.ics")]
+async fn get_view_feed(user: UserContext, path: web::Path<String>) -> HttpResponse {
+    let feed = match find_view(&user, path.into_inner()).await {
+        Ok(view) => export_view(&user, view.get_id()).await,
+        Err(error) => Err(error),
+    };
+    match feed {
+        Ok(feed) => HttpResponse::Ok()
+            .content_type("text/calendar; charset=utf-8")
+            .body(feed),
+        Err(error) => error_response(error),
+    }
+}
+
+#[get("/calendars")]
+async fn list_calendars(user: UserContext) -> HttpResponse {
+    match get_calendars(&user).await {
+        Ok(calendars) => HttpResponse::Ok().json(calendars),
+        Err(error) => error_response(error),
+    }
+}
+
+#[post("/calendars")]
+async fn post_calendar(user: UserContext, settings: web::Json<CalendarSettings>) -> HttpResponse {
+    let mut calendar = Calendar::new("", "");
+    settings.into_inner().apply(&mut calendar);
+    match save_calendar(&user, calendar).await {
+        Ok(calendar) => HttpResponse::Created().json(calendar),
+        Err(error) => error_response(error),
+    }
+}
+
+// calendars are addressed by id or by name in paths, settings left out take their default
+#[put("/calendars/{calendar}")]
+async fn put_calendar(
+    user: UserContext,
+    path: web::Path<String>,
+    settings: web::Json<CalendarSettings>,
+) -> HttpResponse {
+    let saved = match find_calendar(&user, path.into_inner()).await {
+        Ok(mut calendar) => {
+            settings.into_inner().apply(&mut calendar);
+            save_calendar(&user, calendar).await
+        }
+        Err(error) => Err(error),
+    };
+    match saved {
+        Ok(calendar) => HttpResponse::Ok().json(calendar),
+        Err(error) => error_response(error),
+    }
+}
+
+#[delete("/calendars/{calendar}")]
+async fn remove_calendar(user: UserContext, path: web::Path<String>) -> HttpResponse {
+    let result = match find_calendar(&user, path.into_inner()).await {
+        Ok(calendar) => delete_calendar(&user, calendar.get_id()).await,
+        Err(error) => Err(error),
+    };
+    match result {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(error) => error_response(error),
+    }
+}
+
+#[get("/subscriptions")]
+async fn list_subscriptions(user: UserContext) -> HttpResponse {
+    match get_subscriptions(&user).await {
+        Ok(subscriptions) => HttpResponse::Ok().json(
+            subscriptions
+                .iter()
+                .map(SubscriptionSummary::from)
+                .collect::<Vec<SubscriptionSummary>>(),
+        ),
+        Err(error) => error_response(error),
+    }
+}
+
+// the feed is fetched right away, a source that can not be read is not subscribed. only
+// urls of public hosts are read, local files are left to the command line
+#[post("/subscriptions")]
+async fn post_subscription(
+    user: UserContext,
+    subscription: web::Json<NewSubscription>,
+) -> HttpResponse {
+    let subscription = subscription.into_inner();
+    let subscribed = subscribe_feed(
+        &user,
+        subscription.name,
+        subscription.source,
+        subscription.refresh_minutes,
+        subscription.count_conflicts,
+        false,
+    )
+    .await;
+    match subscribed {
+        Ok(subscription) => HttpResponse::Created().json(SubscriptionSummary::from(&subscription)),
+        Err(error) => error_response(error),
+    }
+}
+
+// subscriptions are addressed by id or by name in paths
+#[delete("/subscriptions/{subscription}")]
+async fn remove_subscription(user: UserContext, path: web::Path<String>) -> HttpResponse {
+    let result = match find_subscription(&user, path.into_inner()).await {
+        Ok(subscription) => unsubscribe_feed(&user, subscription.get_id()).await,
+        Err(error) => Err(error),
+    };
+    match result {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(error) => error_response(error),
+    }
+}
+
+#[post("/subscriptions/{subscription}/refresh")]
+async fn post_subscription_refresh(user: UserContext, path: web::Path<String>) -> HttpResponse {
+    let refreshed = match find_subscription(&user, path.into_inner()).await {
+        Ok(subscription) => refresh_subscription(&user, subscription.get_id()).await,
+        Err(error) => Err(error),
+    };
+    match refreshed {
+        Ok(events) => HttpResponse::Ok().json(json!({ "events": events })),
+        Err(error) => error_response(error),
+    }
+}
+
+#[get("/history")]
+async fn list_history(user: UserContext) -> HttpResponse {
+    match get_history(&user).await {
+        Ok((undo, redo)) => HttpResponse::Ok().json(json!({ "undo": undo, "redo": redo })),
+        Err(error) => error_response(error),
+    }
+}
+
+// both return the step that was undone or redone
+#[post("/history/undo")]
+async fn post_undo(user: UserContext) -> HttpResponse {
+    match undo(&user).await {
+        Ok(step) => HttpResponse::Ok().json(step),
+        Err(error) => error_response(error),
+    }
+}
+
+#[post("/history/redo")]
+async fn post_redo(user: UserContext) -> HttpResponse {
+    match redo(&user).await {
+        Ok(step) => HttpResponse::Ok().json(step),
+        Err(error) => error_response(error),
+    }
+}
+
+// field level changes of one event, oldest first
+#[get("/events/{id}/audit")]
+async fn get_event_changes(user: UserContext, path: web::Path<String>) -> HttpResponse {
+    let entries = async { get_event_audit(&user, parse_id(path.as_str())?).await }.await;
+    match entries {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(error) => error_response(error),
+    }
+}
+
+// start and end are RFC 3339 times, end excluded
+#[get("/audit")]
+async fn get_changes(user: UserContext, query: web::Query<AuditQuery>) -> HttpResponse {
+    let entries = async {
+        let start = parse_time(query.start.as_str())?;
+        let end = parse_time(query.end.as_str())?;
+        get_audit_between(&user, start, end).await
+    }
+    .await;
+    match entries {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(error) => error_response(error),
+    }
+}
+
+// until is an RFC 3339 time, the notification fires again then even if it was dismissed
+#[post("/notifications/{id}/snooze")]
+async fn post_snooze(
+    user: UserContext,
+    path: web::Path<String>,
+    query: web::Query<NotificationQuery>,
+    snooze: web::Json<Snooze>,
+) -> HttpResponse {
+    let snoozed = async {
+        let alarm_id = query.alarm.as_deref().map(parse_alarm_id).transpose()?;
+        let until = parse_time(snooze.until.as_str())?;
+        snooze_reminder(&user, parse_id(path.as_str())?, alarm_id, until).await
+    }
+    .await;
+    match snoozed {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(error) => error_response(error),
+    }
+}
+
+#[post("/notifications/{id}/dismiss")]
+async fn post_dismiss(
+    user: UserContext,
+    path: web::Path<String>,
+    query: web::Query<NotificationQuery>,
+) -> HttpResponse {
+    let dismissed = async {
+        let alarm_id = query.alarm.as_deref().map(parse_alarm_id).transpose()?;
+        dismiss_reminder(&user, parse_id(path.as_str())?, alarm_id).await
+    }
+    .await;
+    match dismissed {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(error) => error_response(error),
+    }
+}
+
+// event changes after the cursor, 410 once they are no longer kept
+#[get("/changes")]
+async fn list_changes(user: UserContext, query: web::Query<ChangesQuery>) -> HttpResponse {
+    match get_changes_after(&user, query.after.unwrap_or(0)).await {
+        Ok(changes) => HttpResponse::Ok().json(changes),
+        Err(error) => error_response(error),
+    }
+}
+
+// server-sent events of the changes and alerts of the user, without hidden or isolated
+// calendars, resumed after the cursor in the after parameter or the Last-Event-ID header
+// of a reconnecting client
+#[get("/stream")]
+async fn get_stream(
+    user: UserContext,
+    request: HttpRequest,
+    query: web::Query<ChangesQuery>,
+) -> HttpResponse {
+    let stream =
+        async { open_stream(&user, None, resume_cursor(&request, &query)?).await }.await;
+    match stream {
+        Ok(stream) => stream_response(stream),
+        Err(error) => error_response(error),
+    }
+}
+
+// like /stream but only for events of the view
+#[get("/views/{view}/stream")]
+async fn get_view_stream(
+    user: UserContext,
+    request: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<ChangesQuery>,
+) -> HttpResponse {
+    let stream = async {
+        let view = find_view(&user, path.into_inner()).await?;
+        open_stream(&user, Some(view.get_id()), resume_cursor(&request, &query)?).await
+    }
+    .await;
+    match stream {
+        Ok(stream) => stream_response(stream),
+        Err(error) => error_response(error),
+    }
+}
+
+fn resume_cursor(request: &HttpRequest, query: &ChangesQuery) -> anyhow::Result<Option<u64>> {
+    if query.after.is_some() {
+        return Ok(query.after);
+    }
+    match request.headers().get(HeaderName::from_static("last-event-id")) {
+        None => Ok(None),
+        Some(value) => {
+            let value = value.to_str().unwrap_or_default();
+            match value.parse() {
+                Ok(cursor) => Ok(Some(cursor)),
+                Err(_) => bail!(InternalError::InvalidCursorError {
+                    cursor: value.to_string()
+                }),
+            }
+        }
+    }
+}
+
+fn stream_response(stream: LiveStream) -> HttpResponse {
+    let body = stream::unfold(stream, |mut stream| async move {
+        match stream.next().await {
+            Ok(message) => Some((Ok::<_, actix_web::Error>(sse_frame(&message).into()), stream)),
+            Err(error) => {
+                warn!("live stream ended: {}", error);
+                None
+            }
+        }
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((CACHE_CONTROL, "no-cache"))
+        .streaming(body)
+}
+
+// changes carry their cursor as the event id, so browsers resume from it on reconnect
+fn sse_frame(message: &LiveMessage) -> String {
+    match message {
+        LiveMessage::Change(change) => format!(
+            "id: {}\nevent: change\ndata: {}\n\n",
+            change.cursor,
+            serde_json::to_string(change).unwrap_or_default()
+        ),
+        LiveMessage::Alert(alert) => format!(
+            "event: alert\ndata: {}\n\n",
+            serde_json::to_string(alert).unwrap_or_default()
+        ),
+    }
+}
+
+pub(crate) fn error_response(error: anyhow::Error) -> HttpResponse {
+    let status = match error.downcast_ref::<InternalError>() {
+        Some(InternalError::QueryParseError { .. })
+        | Some(InternalError::InvalidStartEndTimeError { .. })
+        | Some(InternalError::InvalidTimeZoneError { .. })
+        | Some(InternalError::InvalidTimeError { .. })
+        | Some(InternalError::InvalidRevisionError { .. })
+        | Some(InternalError::InvalidCursorError { .. })
+        | Some(InternalError::InvalidIcsError { .. })
+        | Some(InternalError::InvalidKindError { .. })
+        | Some(InternalError::InvalidConflictPolicyError { .. })
+        | Some(InternalError::InvalidFocusLinkError { .. })
+        | Some(InternalError::InvalidRepeatStrategyError { .. })
+        | Some(InternalError::InvalidBreakPolicyError { .. })
+        | Some(InternalError::InvalidQuarterError { .. })
+        | Some(InternalError::InvalidTrackingGroupError { .. })
+        | Some(InternalError::InvalidFeedSourceError)
+        | Some(InternalError::InvalidUserError { .. }) => StatusCode::BAD_REQUEST,
+        Some(InternalError::EventNotFoundError)
+        | Some(InternalError::ObjectiveNotFoundError)
+        | Some(InternalError::KeyResultNotFoundError)
+        | Some(InternalError::TaskNotFoundError)
+        | Some(InternalError::AlarmNotFoundError)
+        | Some(InternalError::CategoryNotFoundError)
+        | Some(InternalError::ViewNotFoundError)
+        | Some(InternalError::CalendarNotFoundError)
+        | Some(InternalError::SubscriptionNotFoundError) => StatusCode::NOT_FOUND,
+        Some(InternalError::ReadOnlyEventError { .. })
+        | Some(InternalError::UntrustedUserHeaderError) => StatusCode::FORBIDDEN,
+        Some(InternalError::ConflictEventError { .. })
+        | Some(InternalError::SkippedEventError { .. })
+        | Some(InternalError::ViewAlreadyExistError { .. })
+        | Some(InternalError::CalendarAlreadyExistError { .. })
+        | Some(InternalError::SubscriptionAlreadyExistError { .. })
+        | Some(InternalError::NothingToUndoError)
+        | Some(InternalError::NothingToRedoError)
+        | Some(InternalError::EventsAlreadyExistError { .. }) => StatusCode::CONFLICT,
+        Some(InternalError::RevisionConflictError { .. }) => StatusCode::PRECONDITION_FAILED,
+        Some(InternalError::PreconditionRequiredError) => StatusCode::PRECONDITION_REQUIRED,
+        Some(InternalError::ChangeCursorExpiredError { .. }) => StatusCode::GONE,
+        Some(InternalError::RemoteCalendarError { .. }) => StatusCode::BAD_GATEWAY,
+        Some(InternalError::BusyCache) => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    HttpResponse::build(status).json(json!({ "error": error.to_string() }))
+}
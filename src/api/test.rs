@@ -0,0 +1,422 @@
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+    use std::net::SocketAddr;
+    use std::pin::pin;
+
+    use actix_web::{App, test, web};
+    use actix_web::body::MessageBody;
+    use actix_web::http::header::{CONTENT_TYPE, ETAG, IF_MATCH};
+    use actix_web::http::StatusCode;
+    use chrono::{DateTime, Utc};
+    use serde_json::{json, Value};
+
+    use crate::api::{configure, TrustedProxies};
+    use crate::core::processor::{dynamic_process, static_process};
+    use crate::model::{EventCommonTrait, ItemCommonTrait};
+    use crate::model::event::Event;
+    use crate::model::reminder::Reminder;
+    use crate::model::user::UserContext;
+    use crate::persistent::PersistentModel;
+
+    #[actix_web::test]
+    async fn get_events_filters_by_query() {
+        let mut event = Event::init(None);
+        event.set_title("api query zephyrine");
+        let now = DateTime::from(Utc::now());
+        event.set_duration(now, now);
+        let user = UserContext::default_user();
+        dynamic_process(&user, move |mut cache| cache.insert_events(vec![Box::new(event)]))
+            .await
+            .unwrap();
+        let app = test::init_service(App::new().configure(configure)).await;
+
+        let request = test::TestRequest::get()
+            .uri("/events?q=kind:event%20zephyr")
+            .to_request();
+        let events: Vec<Value> = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["title"], "api query zephyrine");
+        assert_eq!(events[0]["kind"], "Event");
+
+        let request = test::TestRequest::get().uri("/events?q=kind:task").to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn saved_view_is_queryable_and_exported_as_feed() {
+        let mut event = Event::init(None);
+        event.set_title("api view marjoram");
+        let now = DateTime::from(Utc::now());
+        event.set_duration(now, now);
+        let user = UserContext::default_user();
+        dynamic_process(&user, move |mut cache| cache.insert_events(vec![Box::new(event)]))
+            .await
+            .unwrap();
+        let app = test::init_service(App::new().configure(configure)).await;
+
+        let request = test::TestRequest::post()
+            .uri("/views")
+            .set_json(json!({ "name": "marjoram", "query": "marjoram" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::CREATED);
+        let request = test::TestRequest::post()
+            .uri("/views")
+            .set_json(json!({ "name": "marjoram", "query": "marjoram" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::CONFLICT);
+
+        let request = test::TestRequest::get()
+            .uri("/views/marjoram/events?q=kind:event")
+            .to_request();
+        let events: Vec<Value> = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(events.len(), 1);
+        let request = test::TestRequest::get().uri("/views/marjoram/feed.ics").to_request();
+        let feed = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+        assert!(feed.contains("X-WR-CALNAME:marjoram\r\n"));
+        assert!(feed.contains("SUMMARY:api view marjoram\r\n"));
+
+        let request = test::TestRequest::delete().uri("/views/marjoram").to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::NO_CONTENT);
+        let request = test::TestRequest::get().uri("/views/marjoram/feed.ics").to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn stale_if_match_is_rejected() {
+        let mut event = Event::init(None);
+        event.set_title("api revision tarragon");
+        let now = DateTime::from(Utc::now()) + chrono::Duration::days(600);
+        event.set_duration(now, now + chrono::Duration::hours(1));
+        let id = event.get_id();
+        let user = UserContext::default_user();
+        dynamic_process(&user, move |mut cache| cache.insert_events(vec![Box::new(event)]))
+            .await
+            .unwrap();
+        let app = test::init_service(App::new().configure(configure)).await;
+
+        let request = test::TestRequest::get().uri(format!("/events/{}", id).as_str()).to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.headers().get(ETAG).unwrap(), "\"1\"");
+        // ids do not fit a json Value, so the body is edited as text
+        let body = String::from_utf8(test::read_body(response).await.to_vec())
+            .unwrap()
+            .replace("api revision tarragon", "api revision tarragon renamed");
+
+        let request = test::TestRequest::put()
+            .uri(format!("/events/{}", id).as_str())
+            .insert_header((IF_MATCH, "\"1\""))
+            .insert_header((CONTENT_TYPE, "application/json"))
+            .set_payload(body.clone())
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(ETAG).unwrap(), "\"2\"");
+
+        // a second client still holding revision 1
+        let request = test::TestRequest::put()
+            .uri(format!("/events/{}", id).as_str())
+            .insert_header((IF_MATCH, "\"1\""))
+            .insert_header((CONTENT_TYPE, "application/json"))
+            .set_payload(body.clone())
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::PRECONDITION_FAILED);
+        let request = test::TestRequest::delete()
+            .uri(format!("/events/{}", id).as_str())
+            .insert_header((IF_MATCH, "\"1\""))
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::PRECONDITION_FAILED);
+        let request = test::TestRequest::delete()
+            .uri(format!("/events/{}", id).as_str())
+            .insert_header((IF_MATCH, "\"2\""))
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::NO_CONTENT);
+    }
+
+    fn put_model(model: &PersistentModel, revision: u64) -> test::TestRequest {
+        test::TestRequest::put()
+            .uri(format!("/events/{}", model.id).as_str())
+            .insert_header((IF_MATCH, format!("\"{}\"", revision)))
+            .insert_header((CONTENT_TYPE, "application/json"))
+            .set_payload(serde_json::to_string(model).unwrap())
+    }
+
+    #[actix_web::test]
+    async fn malformed_events_are_rejected() {
+        let start = DateTime::from(Utc::now()) + chrono::Duration::days(602);
+        let mut event = Event::init(None);
+        event.set_title("api malformed rue");
+        event.set_duration(start, start + chrono::Duration::hours(1));
+        let model = event.convert_to(None);
+        let app = test::init_service(App::new().configure(configure)).await;
+
+        let mut bogus = model.clone();
+        bogus.start_time_timezone = "bogus".to_string();
+        let response = test::call_service(&app, put_model(&bogus, 1).to_request()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let mut ancient = model.clone();
+        ancient.start_time = -1_000_000_000_000;
+        let response = test::call_service(&app, put_model(&ancient, 1).to_request()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let mut unreadable = model.clone();
+        unreadable.end_time = i64::MAX;
+        let response = test::call_service(&app, put_model(&unreadable, 1).to_request()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // the revision a client sends for a new event is not kept
+        let mut inflated = model.clone();
+        inflated.revision = u64::MAX;
+        let response = test::call_service(&app, put_model(&inflated, u64::MAX).to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(ETAG).unwrap(), "\"1\"");
+        let response = test::call_service(&app, put_model(&model, 1).to_request()).await;
+        assert_eq!(response.headers().get(ETAG).unwrap(), "\"2\"");
+    }
+
+    #[actix_web::test]
+    async fn changes_without_a_precondition_are_rejected() {
+        let mut event = Event::init(None);
+        event.set_title("api precondition sorrel");
+        let now = DateTime::from(Utc::now()) + chrono::Duration::days(601);
+        event.set_duration(now, now + chrono::Duration::hours(1));
+        let id = event.get_id();
+        let user = UserContext::default_user();
+        dynamic_process(&user, move |mut cache| cache.insert_events(vec![Box::new(event)]))
+            .await
+            .unwrap();
+        let app = test::init_service(App::new().configure(configure)).await;
+
+        let request = test::TestRequest::get().uri(format!("/events/{}", id).as_str()).to_request();
+        let response = test::call_service(&app, request).await;
+        let body = String::from_utf8(test::read_body(response).await.to_vec()).unwrap();
+        let unread = body.replace("\"revision\":1", "\"revision\":0");
+        assert_ne!(body, unread);
+
+        for if_match in [None, Some("\"0\""), Some("*")] {
+            let mut request = test::TestRequest::put()
+                .uri(format!("/events/{}", id).as_str())
+                .insert_header((CONTENT_TYPE, "application/json"))
+                .set_payload(unread.clone());
+            if let Some(if_match) = if_match {
+                request = request.insert_header((IF_MATCH, if_match));
+            }
+            let response = test::call_service(&app, request.to_request()).await;
+            assert_eq!(response.status(), StatusCode::PRECONDITION_REQUIRED);
+        }
+        let request = test::TestRequest::delete()
+            .uri(format!("/events/{}", id).as_str())
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::PRECONDITION_REQUIRED);
+
+        // the revision in the body is enough
+        let request = test::TestRequest::put()
+            .uri(format!("/events/{}", id).as_str())
+            .insert_header((CONTENT_TYPE, "application/json"))
+            .set_payload(body)
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(ETAG).unwrap(), "\"2\"");
+    }
+
+    #[actix_web::test]
+    async fn stream_sends_changes_as_server_sent_events() {
+        let user = UserContext::default_user();
+        let cursor = static_process(&user, |cache| cache.get_change_cursor()).await.unwrap();
+        let mut event = Event::init(None);
+        event.set_title("api stream fennel");
+        let now = DateTime::from(Utc::now());
+        event.set_duration(now, now);
+        dynamic_process(&user, move |mut cache| cache.insert_events(vec![Box::new(event)]))
+            .await
+            .unwrap();
+        let app = test::init_service(App::new().configure(configure)).await;
+
+        let request = test::TestRequest::get()
+            .uri("/stream")
+            .insert_header(("Last-Event-ID", cursor.to_string()))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.headers().get(CONTENT_TYPE).unwrap(), "text/event-stream");
+        let mut body = pin!(response.into_body());
+        let frame = poll_fn(|cx| body.as_mut().poll_next(cx)).await.unwrap().unwrap();
+        let frame = String::from_utf8(frame.to_vec()).unwrap();
+        // other tests may write in between, so the first change is not always this one
+        let (id, rest) = frame.strip_prefix("id: ").unwrap().split_once('\n').unwrap();
+        assert!(id.parse::<u64>().unwrap() > cursor);
+        assert!(rest.starts_with("event: change\ndata: {"));
+
+        let request = test::TestRequest::get()
+            .uri("/stream")
+            .insert_header(("Last-Event-ID", "soon"))
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::BAD_REQUEST);
+        let request = test::TestRequest::get().uri("/views/none/stream").to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn requests_act_for_the_forwarded_user() {
+        let user = UserContext::new("api-basil").unwrap();
+        let mut event = Event::init(None);
+        event.set_title("api user basil");
+        let now = DateTime::from(Utc::now());
+        event.set_duration(now, now);
+        dynamic_process(&user, move |mut cache| cache.insert_events(vec![Box::new(event)]))
+            .await
+            .unwrap();
+        let proxy: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let proxies = TrustedProxies(vec![proxy.ip()]);
+        let app = test::init_service(
+            App::new().app_data(web::Data::new(proxies)).configure(configure),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/events?q=basil")
+            .peer_addr(proxy)
+            .insert_header(("X-Forwarded-User", "api-basil"))
+            .to_request();
+        let events: Vec<Value> = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(events.len(), 1);
+        let request = test::TestRequest::get().uri("/events?q=basil").to_request();
+        let events: Vec<Value> = test::call_and_read_body_json(&app, request).await;
+        assert!(events.is_empty());
+        let request = test::TestRequest::get()
+            .uri("/events")
+            .peer_addr(proxy)
+            .insert_header(("X-Forwarded-User", "../api-basil"))
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::BAD_REQUEST);
+
+        // anyone else naming a user is turned away
+        let request = test::TestRequest::get()
+            .uri("/events?q=basil")
+            .peer_addr("10.0.0.7:4000".parse().unwrap())
+            .insert_header(("X-Forwarded-User", "api-basil"))
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::FORBIDDEN);
+        let app = test::init_service(App::new().configure(configure)).await;
+        let request = test::TestRequest::get()
+            .uri("/events?q=basil")
+            .peer_addr(proxy)
+            .insert_header(("X-Forwarded-User", "api-basil"))
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn transactions_are_committed_whole_or_not_at_all() {
+        let start = DateTime::from(Utc::now()) + chrono::Duration::days(400);
+        let mut first = Event::init(None);
+        first.set_title("api transaction quince");
+        first.set_duration(start, start + chrono::Duration::hours(1));
+        let mut second = Event::init(None);
+        second.set_title("api transaction quince overlapping");
+        second.set_duration(start, start + chrono::Duration::hours(1));
+        let first_id = first.get_id();
+        // ids do not fit a json Value, so the body is built as text
+        let first = serde_json::to_string(&first.convert_to(None)).unwrap();
+        let second = serde_json::to_string(&second.convert_to(None)).unwrap();
+        let app = test::init_service(App::new().configure(configure)).await;
+
+        let request = test::TestRequest::post()
+            .uri("/transactions")
+            .insert_header((CONTENT_TYPE, "application/json"))
+            .set_payload(format!("[{{\"insert\": {}}}, {{\"insert\": {}}}]", first, second))
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::CONFLICT);
+        let request = test::TestRequest::get().uri("/events?q=quince").to_request();
+        let events: Vec<Value> = test::call_and_read_body_json(&app, request).await;
+        assert!(events.is_empty());
+
+        let request = test::TestRequest::post()
+            .uri("/transactions")
+            .insert_header((CONTENT_TYPE, "application/json"))
+            .set_payload(format!("[{{\"insert\": {}}}, {{\"delete\": \"{}\"}}]", first, first_id))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = String::from_utf8(test::read_body(response).await.to_vec()).unwrap();
+        assert!(body.contains(format!("\"inserted\":[{}]", first_id).as_str()));
+        assert!(body.contains(format!("\"deleted\":[{}]", first_id).as_str()));
+        let request = test::TestRequest::get().uri("/events?q=quince").to_request();
+        let events: Vec<Value> = test::call_and_read_body_json(&app, request).await;
+        assert!(events.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn transactions_with_unreadable_times_are_rejected() {
+        let start = DateTime::from(Utc::now()) + chrono::Duration::days(401);
+        let mut event = Event::init(None);
+        event.set_title("api transaction sumac");
+        event.set_duration(start, start + chrono::Duration::hours(1));
+        let model = event.convert_to(None);
+        let app = test::init_service(App::new().configure(configure)).await;
+
+        let mut bogus = model.clone();
+        bogus.end_time_timezone = "bogus".to_string();
+        let mut unreadable = model.clone();
+        unreadable.start_time = i64::MIN;
+        let mut late = model.clone();
+        late.end_time = (start + chrono::Duration::days(365 * 200)).timestamp_millis();
+        for staged in [bogus, unreadable, late] {
+            let writes = format!(
+                "[{{\"insert\": {}}}, {{\"update\": {}}}]",
+                serde_json::to_string(&model).unwrap(),
+                serde_json::to_string(&staged).unwrap()
+            );
+            let request = test::TestRequest::post()
+                .uri("/transactions")
+                .insert_header((CONTENT_TYPE, "application/json"))
+                .set_payload(writes)
+                .to_request();
+            assert_eq!(test::call_service(&app, request).await.status(), StatusCode::BAD_REQUEST);
+        }
+        let request = test::TestRequest::get().uri("/events?q=sumac").to_request();
+        let events: Vec<Value> = test::call_and_read_body_json(&app, request).await;
+        assert!(events.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn reminders_are_snoozed_and_dismissed() {
+        let now = DateTime::from(Utc::now());
+        let mut reminder = Reminder::init(None);
+        reminder.set_duration(now, now);
+        let id = reminder.get_id();
+        let user = UserContext::default_user();
+        dynamic_process(&user, move |mut cache| cache.insert_events(vec![Box::new(reminder)]))
+            .await
+            .unwrap();
+        let app = test::init_service(App::new().configure(configure)).await;
+
+        let until = (now + chrono::Duration::minutes(5)).to_rfc3339();
+        let request = test::TestRequest::post()
+            .uri(format!("/notifications/{}/snooze", id).as_str())
+            .set_json(json!({ "until": until }))
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::NO_CONTENT);
+        let record = static_process(&user, move |cache| cache.get_notification_record(id, None));
+        let record = record.await.unwrap().unwrap();
+        assert_eq!(record.snoozed_until, Some(now.timestamp_millis() + 5 * 60 * 1000));
+
+        let request = test::TestRequest::post()
+            .uri(format!("/notifications/{}/dismiss", id).as_str())
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::NO_CONTENT);
+        let record = static_process(&user, move |cache| cache.get_notification_record(id, None));
+        assert!(record.await.unwrap().unwrap().dismissed);
+
+        let request = test::TestRequest::post()
+            .uri(format!("/notifications/{}/snooze", id).as_str())
+            .set_json(json!({ "until": "soon" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::BAD_REQUEST);
+        let request = test::TestRequest::post()
+            .uri(format!("/notifications/{}/dismiss?alarm=7", id).as_str())
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::{DateTime, FixedOffset, Utc};
+
+use crate::cache::Cache;
+use crate::model::audit::{AuditEntry, AUDIT_LOG_LIMIT};
+use crate::persistent::PersistentModel;
+
+impl Cache {
+    pub(crate) fn audit_event(
+        &mut self,
+        id: u128,
+        before: Option<&PersistentModel>,
+        after: Option<&PersistentModel>,
+    ) {
+        let time = Utc::now().timestamp_millis();
+        if let Some(entry) = AuditEntry::new(id, time, self.actor.as_str(), before, after) {
+            self.audit_log.push(entry);
+        }
+    }
+
+    pub fn set_actor(&mut self, actor: &str) {
+        self.actor = actor.to_string();
+    }
+
+    pub fn get_actor(&self) -> &str {
+        self.actor.as_str()
+    }
+
+    // oldest first, also for deleted events
+    pub fn get_event_audit(&self, event_id: u128) -> Vec<AuditEntry> {
+        self.audit_log
+            .iter()
+            .filter(|entry| entry.event_id == event_id)
+            .cloned()
+            .collect()
+    }
+
+    // changes made from start until before end, oldest first
+    pub fn get_audit_between(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+    ) -> Vec<AuditEntry> {
+        self.audit_log
+            .iter()
+            .filter(|entry| {
+                entry.time >= start.timestamp_millis() && entry.time < end.timestamp_millis()
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn get_audit_log(&self) -> Vec<AuditEntry> {
+        self.audit_log.clone()
+    }
+
+    pub fn restore_audit_log(&mut self, entries: Vec<AuditEntry>) {
+        self.audit_log = entries;
+        self.trim_audit_log();
+    }
+
+    // only between writes, rollbacks rely on the length of the log while writing
+    pub(crate) fn trim_audit_log(&mut self) {
+        let excess = self.audit_log.len().saturating_sub(AUDIT_LOG_LIMIT);
+        self.audit_log.drain(..excess);
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use chrono::{Days, Duration, FixedOffset, NaiveDate};
+
+use crate::cache::Cache;
+use crate::common::utils::get_local_day_start;
+use crate::model::{EventCommonTrait, ItemCommonTrait, Kind};
+use crate::model::break_block::Break;
+use crate::model::break_policy::{BreakPolicy, BreakReport, TimeBlock};
+
+impl Cache {
+    // day is a local date in the given timezone, not the utc date of the cache index
+    pub fn analyse_breaks(
+        &self,
+        day: NaiveDate,
+        timezone: FixedOffset,
+        policy: &BreakPolicy,
+    ) -> BreakReport {
+        let day_start = get_local_day_start(day, timezone);
+        let day_end = day_start + Duration::days(1);
+        let mut work = vec![];
+        let mut breaks = vec![];
+        let mut occupied = vec![];
+        let mut seen = HashSet::new();
+        let mut pointer_date = day_start.naive_utc().date();
+        while pointer_date.le(&day_end.naive_utc().date()) {
+            for event in self.events_by_date.get(&pointer_date).into_iter().flatten() {
+                if !seen.insert(event.get_id())
+                    || event.get_end_time().le(&day_start)
+                    || event.get_start_time().ge(&day_end)
+                {
+                    continue;
+                }
+                let block: TimeBlock = (event.get_start_time(), event.get_end_time());
+                match event.get_kind() {
+                    Kind::Event => work.push(block),
+                    Kind::Break => breaks.push(block),
+                    _ => {}
+                }
+                occupied.push(block);
+            }
+            pointer_date = pointer_date.checked_add_days(Days::new(1)).unwrap();
+        }
+        policy.analyse(day_start, &work, &breaks, &occupied)
+    }
+
+    // planned breaks only use free gaps, so inserting them can not conflict
+    pub fn insert_planned_breaks(
+        &mut self,
+        day: NaiveDate,
+        timezone: FixedOffset,
+        policy: &BreakPolicy,
+    ) -> Result<BreakReport> {
+        let report = self.analyse_breaks(day, timezone, policy);
+        let breaks = report
+            .planned
+            .iter()
+            .map(|planned| {
+                let mut rest = Break::init(None);
+                rest.set_title(planned.title.as_str());
+                rest.set_duration(planned.start, planned.end);
+                Box::new(rest) as Box<dyn EventCommonTrait>
+            })
+            .collect::<Vec<Box<dyn EventCommonTrait>>>();
+        self.insert_events(breaks)?;
+        Ok(report)
+    }
+}
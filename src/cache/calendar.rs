@@ -0,0 +1,136 @@
+use anyhow::bail;
+use anyhow::Result;
+
+use crate::cache::Cache;
+use crate::common::exception::InternalError;
+use crate::model::EventCommonTrait;
+use crate::model::calendar::{Calendar, MAIN_CALENDAR};
+use crate::model::category::ConflictBehavior;
+use crate::model::history::Change;
+
+impl Cache {
+    pub fn add_or_update_calendars(&mut self, calendars: Vec<Calendar>) -> Result<()> {
+        for calendar in &calendars {
+            let duplicated = calendar.name.eq_ignore_ascii_case(MAIN_CALENDAR)
+                || self.calendars.values().any(|c| {
+                    c.name.eq_ignore_ascii_case(calendar.name.as_str())
+                        && c.get_id() != calendar.get_id()
+                });
+            if duplicated {
+                bail!(InternalError::CalendarAlreadyExistError {
+                    name: calendar.name.clone()
+                });
+            }
+        }
+        for calendar in calendars {
+            let id = calendar.get_id();
+            let before = self.calendars.insert(id, calendar.clone());
+            self.record_change(Change::Calendar {
+                id,
+                before,
+                after: Some(calendar),
+            });
+        }
+        self.touch();
+        Ok(())
+    }
+
+    pub fn get_calendar(&self, id: u128) -> Result<Calendar> {
+        match self.calendars.get(&id) {
+            Some(calendar) => Ok(calendar.clone()),
+            None => bail!(InternalError::CalendarNotFoundError),
+        }
+    }
+
+    pub fn get_calendar_by_name(&self, name: &str) -> Result<Calendar> {
+        match self.calendars.values().find(|c| c.name.eq_ignore_ascii_case(name)) {
+            Some(calendar) => Ok(calendar.clone()),
+            None => bail!(InternalError::CalendarNotFoundError),
+        }
+    }
+
+    pub fn get_all_calendars(&self) -> Vec<Calendar> {
+        let mut calendars = self.calendars.values().cloned().collect::<Vec<Calendar>>();
+        calendars.sort_by(|a, b| a.name.cmp(&b.name));
+        calendars
+    }
+
+    // events keep referencing the id and follow the rules of the main calendar until a
+    // calendar with it comes back
+    pub fn delete_calendar(&mut self, id: u128) -> Result<()> {
+        let before = match self.calendars.remove(&id) {
+            Some(calendar) => calendar,
+            None => bail!(InternalError::CalendarNotFoundError),
+        };
+        self.record_change(Change::Calendar {
+            id,
+            before: Some(before),
+            after: None,
+        });
+        self.touch();
+        Ok(())
+    }
+
+    // None moves the event back to the main calendar
+    pub fn set_event_calendar(&mut self, event_id: u128, calendar: Option<u128>) -> Result<()> {
+        if let Some(calendar) = calendar {
+            self.get_calendar(calendar)?;
+        }
+        let mut event = self.clone_event(event_id)?;
+        event.set_calendar(calendar);
+        self.insert_single_event(event)
+    }
+
+    pub fn get_calendar_name(&self, event: &dyn EventCommonTrait) -> String {
+        event
+            .get_calendar()
+            .and_then(|id| self.calendars.get(&id))
+            .map_or(MAIN_CALENDAR.to_string(), |c| c.name.clone())
+    }
+
+    // events of hidden calendars are left out unless named, e.g. by calendar:team
+    pub(crate) fn is_shown(&self, event: &dyn EventCommonTrait, named: &[&str]) -> bool {
+        match event.get_calendar().and_then(|id| self.calendars.get(&id)) {
+            Some(calendar) if !calendar.visible => named
+                .iter()
+                .any(|name| calendar.name.eq_ignore_ascii_case(name)),
+            _ => true,
+        }
+    }
+
+    // streams also leave out the events of isolated calendars unless named, clients only
+    // hear of what they see in the calendar
+    pub(crate) fn is_streamed(&self, event: &dyn EventCommonTrait, named: &[&str]) -> bool {
+        match event.get_calendar().and_then(|id| self.calendars.get(&id)) {
+            Some(calendar) if calendar.isolated => named
+                .iter()
+                .any(|name| calendar.name.eq_ignore_ascii_case(name)),
+            _ => self.is_shown(event, named),
+        }
+    }
+
+    pub(crate) fn get_calendar_conflict(
+        &self,
+        event: &dyn EventCommonTrait,
+    ) -> Option<ConflictBehavior> {
+        event
+            .get_calendar()
+            .and_then(|id| self.calendars.get(&id))
+            .map(|c| c.conflict)
+    }
+
+    // an isolated calendar keeps its overlaps to itself, in both directions
+    pub(crate) fn calendars_conflict(
+        &self,
+        first: &dyn EventCommonTrait,
+        second: &dyn EventCommonTrait,
+    ) -> bool {
+        let isolated = |event: &dyn EventCommonTrait| {
+            event
+                .get_calendar()
+                .and_then(|id| self.calendars.get(&id))
+                .is_some_and(|c| c.isolated)
+        };
+        first.get_calendar() == second.get_calendar() || !(isolated(first) || isolated(second))
+    }
+}
@@ -0,0 +1,81 @@
+use anyhow::bail;
+use anyhow::Result;
+
+use crate::cache::Cache;
+use crate::common::exception::InternalError;
+use crate::model::{Category, EventCommonTrait};
+use crate::model::category::{ConflictBehavior, UserCategory};
+use crate::model::history::Change;
+
+impl Cache {
+    // a tightened conflict rule only applies to the next change of the events it covers,
+    // stored ones keep their place and still load
+    pub fn add_or_update_categories(&mut self, categories: Vec<UserCategory>) {
+        for category in categories {
+            let id = category.get_id();
+            let before = self.categories.insert(id, category.clone());
+            self.record_change(Change::Category {
+                id,
+                before,
+                after: Some(category),
+            });
+        }
+        self.touch();
+    }
+
+    pub fn get_category(&self, id: u128) -> Result<UserCategory> {
+        match self.categories.get(&id) {
+            Some(category) => Ok(category.clone()),
+            None => bail!(InternalError::CategoryNotFoundError),
+        }
+    }
+
+    pub fn get_category_by_name(&self, name: &str) -> Result<UserCategory> {
+        match self.categories.values().find(|c| c.name == name) {
+            Some(category) => Ok(category.clone()),
+            None => bail!(InternalError::CategoryNotFoundError),
+        }
+    }
+
+    pub fn get_all_categories(&self) -> Vec<UserCategory> {
+        let mut categories = self.categories.values().cloned().collect::<Vec<UserCategory>>();
+        categories.sort_by(|a, b| a.name.cmp(&b.name));
+        categories
+    }
+
+    // events keep referencing the id, it is shown as is until a category with it comes back
+    pub fn delete_category(&mut self, id: u128) -> Result<()> {
+        let before = match self.categories.remove(&id) {
+            Some(category) => category,
+            None => bail!(InternalError::CategoryNotFoundError),
+        };
+        self.record_change(Change::Category {
+            id,
+            before: Some(before),
+            after: None,
+        });
+        self.touch();
+        Ok(())
+    }
+
+    // user defined categories show their name, anything else its stored value
+    pub fn get_category_name(&self, category: &Category) -> String {
+        match category {
+            Category::Custom(id) => self
+                .categories
+                .get(id)
+                .map_or(category.to_string(), |c| c.name.clone()),
+            _ => category.to_string(),
+        }
+    }
+
+    pub(crate) fn get_category_conflict(
+        &self,
+        event: &dyn EventCommonTrait,
+    ) -> Option<ConflictBehavior> {
+        match event.get_categories() {
+            Category::Custom(id) => self.categories.get(&id).map(|c| c.conflict),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use anyhow::bail;
+use anyhow::Result;
+use chrono::Utc;
+
+use crate::cache::Cache;
+use crate::common::exception::InternalError;
+use crate::model::audit::AuditAction;
+use crate::model::change_feed::{ChangeEvent, CHANGE_LOG_LIMIT};
+use crate::persistent::PersistentModel;
+
+impl Cache {
+    pub(crate) fn feed_event(
+        &mut self,
+        id: u128,
+        before: Option<&PersistentModel>,
+        after: Option<&PersistentModel>,
+    ) {
+        let (action, revision) = match (before, after) {
+            (None, Some(after)) => (AuditAction::Created, after.revision),
+            (Some(_), Some(after)) => (AuditAction::Updated, after.revision),
+            (Some(before), None) => (AuditAction::Deleted, before.revision),
+            (None, None) => return,
+        };
+        self.change_cursor += 1;
+        self.changes.push_back(ChangeEvent {
+            cursor: self.change_cursor,
+            event_id: id,
+            action,
+            revision,
+            time: Utc::now().timestamp_millis(),
+        });
+        while self.changes.len() > CHANGE_LOG_LIMIT {
+            self.changes.pop_front();
+        }
+    }
+
+    // cursor of the latest change, subscribing from it skips everything before
+    pub fn get_change_cursor(&self) -> u64 {
+        self.change_cursor
+    }
+
+    // changes after the cursor, oldest first, fails once some of them were dropped
+    pub fn get_changes_after(&self, cursor: u64) -> Result<Vec<ChangeEvent>> {
+        if let Some(oldest) = self.changes.front() {
+            if cursor.saturating_add(1) < oldest.cursor {
+                bail!(InternalError::ChangeCursorExpiredError { cursor })
+            }
+        }
+        Ok(self
+            .changes
+            .iter()
+            .filter(|change| change.cursor > cursor)
+            .cloned()
+            .collect())
+    }
+
+    pub fn get_change_log(&self) -> Vec<ChangeEvent> {
+        self.changes.iter().cloned().collect()
+    }
+
+    pub fn restore_change_log(&mut self, changes: Vec<ChangeEvent>) {
+        self.change_cursor = changes.last().map_or(0, |change| change.cursor);
+        self.changes = changes.into();
+    }
+
+    // drops the changes after the cursor, for writes that were rolled back
+    pub(crate) fn forget_changes_after(&mut self, cursor: u64) {
+        self.changes.retain(|change| change.cursor <= cursor);
+        self.change_cursor = cursor;
+    }
+}
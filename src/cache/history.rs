@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::bail;
+use anyhow::Result;
+
+use crate::cache::Cache;
+use crate::common::exception::InternalError;
+use crate::model::history::{Change, History, HistoryStep};
+use crate::persistent::PersistentModel;
+
+impl Cache {
+    pub(crate) fn snapshot_event(&self, id: u128) -> Option<Box<PersistentModel>> {
+        self.events_by_id.get(&id).map(|event| {
+            Box::new(event.convert_to(
+                event
+                    .get_generator_instance()
+                    .and_then(|instance| self.instance.get(&instance).cloned()),
+            ))
+        })
+    }
+
+    pub(crate) fn record_event(&mut self, id: u128, before: Option<Box<PersistentModel>>) {
+        let after = self.snapshot_event(id);
+        self.audit_event(id, before.as_deref(), after.as_deref());
+        self.feed_event(id, before.as_deref(), after.as_deref());
+        self.history.record(Change::Event { id, before, after });
+    }
+
+    pub(crate) fn record_change(&mut self, change: Change) {
+        self.history.record(change);
+    }
+
+    // closes the changes made since the last call as one undoable step
+    pub fn commit_history_step(&mut self) {
+        self.history.commit();
+        self.trim_audit_log();
+    }
+
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history.commit();
+        self.history.set_limit(limit);
+    }
+
+    // steps that can be undone, the next one to undo first
+    pub fn get_undo_steps(&self) -> Vec<HistoryStep> {
+        self.get_history().undo.into_iter().rev().collect()
+    }
+
+    // steps that can be redone, the next one to redo first
+
+    pub fn get_redo_steps(&self) -> Vec<HistoryStep> {
+        self.get_history().redo.into_iter().rev().collect()
+    }
+
+    // history with the pending changes closed as a step, as it is persisted
+    pub fn get_history(&self) -> History {
+        let mut history = self.history.clone();
+        history.commit();
+        history
+    }
+
+    pub fn restore_history(&mut self, history: History) {
+        self.history = history;
+    }
+
+    // a step stays in the history until it was replayed completely
+    pub fn undo(&mut self) -> Result<HistoryStep> {
+        self.history.commit();
+        let step = match self.history.undo.back() {
+            Some(step) => step.clone(),
+            None => bail!(InternalError::NothingToUndoError),
+        };
+        self.replay(step.changes.iter().rev(), true)?;
+        self.history.undo.pop_back();
+        self.history.redo.push(step.clone());
+        Ok(step)
+    }
+
+    pub fn redo(&mut self) -> Result<HistoryStep> {
+        self.history.commit();
+        let step = match self.history.redo.last() {
+            Some(step) => step.clone(),
+            None => bail!(InternalError::NothingToRedoError),
+        };
+        self.replay(step.changes.iter(), false)?;
+        self.history.redo.pop();
+        self.history.undo.push_back(step.clone());
+        Ok(step)
+    }
+
+    // restored events are checked for conflicts like new ones, when a change fails the
+    // ones already replayed are put back so nothing of the step stays applied
+    pub(crate) fn replay<'a>(
+        &mut self,
+        changes: impl Iterator<Item = &'a Change>,
+        undo: bool,
+    ) -> Result<()> {
+        let audited = self.audit_log.len();
+        let cursor = self.change_cursor;
+        self.history.replaying = true;
+        let mut applied = vec![];
+        let mut result = Ok(());
+        for change in changes {
+            if let Err(error) = self.apply_change(change, undo, true) {
+                result = Err(error);
+                break;
+            }
+            applied.push(change);
+        }
+        if let Err(error) = result {
+            let rolled_back = self.put_back(applied.into_iter().rev(), !undo);
+            self.audit_log.truncate(audited);
+            self.forget_changes_after(cursor);
+            result = Err(rollback_error(error, rolled_back));
+        }
+        self.history.replaying = false;
+        self.touch();
+        result
+    }
+
+    // replays changes without checks, only to return to a state that existed before
+    pub(crate) fn put_back<'a>(
+        &mut self,
+        changes: impl Iterator<Item = &'a Change>,
+        undo: bool,
+    ) -> Result<()> {
+        let replaying = self.history.replaying;
+        self.history.replaying = true;
+        let result = changes
+            .map(|change| self.apply_change(change, undo, false))
+            .collect::<Result<Vec<()>>>();
+        self.history.replaying = replaying;
+        self.touch();
+        result.map(|_| ())
+    }
+
+    // puts back the state before the change for undo, after it for redo
+    fn apply_change(&mut self, change: &Change, undo: bool, checked: bool) -> Result<()> {
+        match change {
+            Change::Event { id, before, after } => {
+                let current = self.snapshot_event(*id);
+                let restored = match if undo { before } else { after } {
+                    Some(model) => {
+                        // revisions only move forward, a restored state is a new one
+                        let mut event = model.convert_to()?;
+                        let revision = current.as_ref().map_or(model.revision, |c| c.revision);
+                        event.set_revision(revision + 1);
+                        let event = Arc::new(event);
+                        if checked && self.has_conflict(&event) {
+                            bail!(InternalError::ConflictEventError {
+                                start_time: event.get_start_time(),
+                                end_time: event.get_end_time()
+                            })
+                        }
+                        Some(event)
+                    }
+                    None => None,
+                };
+                self.unstore_event(*id);
+                if let Some(event) = restored {
+                    self.store_event(event);
+                }
+                // undo and redo change events like any other edit
+                let replayed = self.snapshot_event(*id);
+                self.audit_event(*id, current.as_deref(), replayed.as_deref());
+                self.feed_event(*id, current.as_deref(), replayed.as_deref());
+            }
+            Change::Instance { id, before, after } => {
+                restore(&mut self.instance, *id, if undo { before } else { after })
+            }
+            Change::Objective { id, before, after } => {
+                restore(&mut self.objectives, *id, if undo { before } else { after })
+            }
+            Change::KeyResult { id, before, after } => {
+                restore(&mut self.key_results, *id, if undo { before } else { after })
+            }
+            Change::Task { id, before, after } => {
+                let task = if undo { before } else { after };
+                let task = task.as_ref().map(|t| t.convert_to()).transpose()?;
+                restore(&mut self.tasks, *id, &task)
+            }
+            Change::Category { id, before, after } => {
+                restore(&mut self.categories, *id, if undo { before } else { after })
+            }
+            Change::View { id, before, after } => {
+                restore(&mut self.views, *id, if undo { before } else { after })
+            }
+            Change::Calendar { id, before, after } => {
+                restore(&mut self.calendars, *id, if undo { before } else { after })
+            }
+        }
+        Ok(())
+    }
+}
+
+// the error that stopped the writes, with the one of putting them back when that failed
+// too, the original error stays the one to downcast to
+pub(crate) fn rollback_error(error: anyhow::Error, rolled_back: Result<()>) -> anyhow::Error {
+    match rolled_back {
+        Ok(()) => error,
+        Err(rollback) => error.context(format!("rolling back failed too: {:#}", rollback)),
+    }
+}
+
+fn restore<T: Clone>(items: &mut HashMap<u128, T>, id: u128, state: &Option<T>) {
+    match state {
+        Some(item) => {
+            items.insert(id, item.clone());
+        }
+        None => {
+            items.remove(&id);
+        }
+    }
+}
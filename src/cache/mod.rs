@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::ops::{Add, Sub};
 use std::sync::Arc;
 
@@ -7,12 +7,42 @@ use anyhow::Result;
 use chrono::{Datelike, Days, NaiveDate, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::cache::search::SearchIndex;
 use crate::common::exception::InternalError;
 use crate::common::utils::{check_conflict, MAX_EVENT_TIMESTAMP, MIN_EVENT_TIMESTAMP};
-use crate::model::EventCommonTrait;
+use crate::model::{Category, EventCommonTrait};
+use crate::model::audit::{AuditEntry, DEFAULT_ACTOR};
+use crate::model::calendar::Calendar;
+use crate::model::category::{ConflictBehavior, UserCategory};
+use crate::model::change_feed::ChangeEvent;
 use crate::model::generator_instance::GeneratorInstance;
+use crate::model::history::{Change, History};
+use crate::model::notification::NotificationRecord;
+use crate::model::objective::{KeyResult, Objective};
+use crate::model::subscription::Subscription;
+use crate::model::sync::SyncState;
+use crate::model::task::Task;
+use crate::model::view::SavedView;
 
+mod audit;
+mod break_planner;
+mod calendar;
+mod history;
+mod category;
+mod change_feed;
+mod notification;
+mod okr;
+mod query;
+mod search;
+mod series;
+mod subscription;
+mod sync;
+mod tag;
+mod task;
 mod test;
+mod tracking;
+mod transaction;
+mod view;
 
 pub struct Cache {
     // cache only care about conflict, event valid and other self check not here
@@ -21,11 +51,30 @@ pub struct Cache {
     events_by_date: HashMap<NaiveDate, Vec<Arc<Box<dyn EventCommonTrait>>>>,
     events_by_id: HashMap<u128, Arc<Box<dyn EventCommonTrait>>>,
     instance: HashMap<u128, GeneratorInstance>,
+    objectives: HashMap<u128, Objective>,
+    key_results: HashMap<u128, KeyResult>,
+    tasks: HashMap<u128, Task>,
+    notifications: HashMap<(u128, Option<u128>), NotificationRecord>,
+    categories: HashMap<u128, UserCategory>,
+    calendars: HashMap<u128, Calendar>,
+    events_by_tag: HashMap<String, HashSet<u128>>,
+    search_index: SearchIndex,
+    views: HashMap<u128, SavedView>,
+    history: History,
+    audit_log: Vec<AuditEntry>,
+    actor: String, // recorded as the author of audited changes
+    changes: VecDeque<ChangeEvent>,
+    change_cursor: u64,
+    sync_states: HashMap<String, SyncState>, // by collection url
+    subscriptions: HashMap<u128, Subscription>,
+    feed_events: HashMap<u128, Vec<Arc<Box<dyn EventCommonTrait>>>>, // by subscription
+    feed_event_index: HashMap<u128, u128>, // subscription by feed event
 }
 
 #[derive(Deserialize, Serialize)]
 struct Properties {
     last_modified: i64,
+    revision: u64, // of the whole cache, bumped by every change
 }
 
 impl Cache {
@@ -39,86 +88,255 @@ impl Cache {
         Cache {
             properties: Properties {
                 last_modified: Utc::now().timestamp_millis(),
+                revision: 0,
             },
             events_all: vec![],
             events_by_date: init_hashmap,
             events_by_id: Default::default(),
             instance: Default::default(),
+            objectives: Default::default(),
+            key_results: Default::default(),
+            tasks: Default::default(),
+            notifications: Default::default(),
+            categories: Default::default(),
+            calendars: Default::default(),
+            events_by_tag: Default::default(),
+            search_index: Default::default(),
+            views: Default::default(),
+            history: Default::default(),
+            audit_log: vec![],
+            actor: DEFAULT_ACTOR.to_string(),
+            changes: VecDeque::new(),
+            change_cursor: 0,
+            sync_states: HashMap::new(),
+            subscriptions: HashMap::new(),
+            feed_events: HashMap::new(),
+            feed_event_index: HashMap::new(),
         }
     }
 
-    pub fn insert_events(&mut self, events: Vec<Box<dyn EventCommonTrait>>) -> Result<()> {
-        for event in events {
-            let event = Arc::new(event);
-            let mut date_event_vec = Vec::new();
-            date_event_vec.push(event.clone());
-            let start_date = event.get_start_time().naive_utc().date();
-            let mut pointer_date = start_date.clone();
-            let end_date = event.get_end_time().naive_utc().date();
-            while pointer_date.le(&end_date) {
-                for e in self.events_by_date.get(&pointer_date).unwrap() {
-                    date_event_vec.push(e.clone());
+    // either all events are inserted or none, returns the ids of the events skipped by
+    // the conflict rules of their series, category or calendar
+    pub fn insert_events(&mut self, events: Vec<Box<dyn EventCommonTrait>>) -> Result<Vec<u128>> {
+        self.atomically(|cache| {
+            let mut skipped = vec![];
+            for event in events {
+                let id = event.get_id();
+                if !cache.insert_event(event)? {
+                    skipped.push(id);
                 }
-                pointer_date = pointer_date.checked_add_days(Days::new(1)).unwrap();
             }
-            // check conflict
-            let conflict_events = check_conflict(&date_event_vec);
-            if !conflict_events.is_empty() {
-                let ignore_conflict = event.get_generator_instance().map_or(false, |id| {
-                    self.instance.get(&id).map_or(false, |instance| {
-                        instance
-                            .repeat
-                            .as_ref()
-                            .map_or(false, |repeat| !repeat.throw_error_when_conflict)
-                    })
-                });
-                if ignore_conflict {
-                    continue;
-                }
-                bail!(InternalError::ConflictEventError {
+            Ok(skipped)
+        })
+    }
+
+    // a single event that was asked for, being skipped is an error here
+    pub fn insert_single_event(&mut self, event: Box<dyn EventCommonTrait>) -> Result<()> {
+        let event_id = event.get_id();
+        if !self.insert_events(vec![event])?.is_empty() {
+            bail!(InternalError::SkippedEventError { event_id })
+        }
+        Ok(())
+    }
+
+    // events read back from storage were checked when they were stored, rules tightened
+    // since only apply to their next change
+    pub fn restore_events(&mut self, events: Vec<Box<dyn EventCommonTrait>>) -> Result<()> {
+        for mut event in events {
+            if !is_indexable(event.as_ref()) {
+                bail!(InternalError::InvalidStartEndTimeError {
                     start_time: event.get_start_time(),
                     end_time: event.get_end_time()
-                });
+                })
+            }
+            event.set_revision(event.get_revision().max(1));
+            self.unstore_event(event.get_id());
+            self.store_event(Arc::new(event));
+        }
+        self.touch();
+        Ok(())
+    }
+
+    // false when the event was skipped instead of stored. an event read at some revision
+    // must still be at it when stored again
+    pub(crate) fn insert_event(&mut self, mut event: Box<dyn EventCommonTrait>) -> Result<bool> {
+        if self.get_event_feed(event.get_id()).is_some() {
+            bail!(InternalError::ReadOnlyEventError {
+                event_id: event.get_id()
+            })
+        }
+        if !is_indexable(event.as_ref()) {
+            bail!(InternalError::InvalidStartEndTimeError {
+                start_time: event.get_start_time(),
+                end_time: event.get_end_time()
+            })
+        }
+        let current = self.events_by_id.get(&event.get_id()).map(|e| e.get_revision());
+        match current {
+            Some(current) if event.get_revision() != current => {
+                bail!(InternalError::RevisionConflictError {
+                    event_id: event.get_id(),
+                    expected: event.get_revision(),
+                    current
+                })
             }
-            if self.events_by_id.get(&event.get_id()).is_some() {
-                // clean for update first
-                self.delete_event(event.get_id())
-                    .expect("Unexpected run here when create for update");
+            Some(current) => event.set_revision(current + 1),
+            // the revision a client sends for a new event is not kept
+            None => event.set_revision(1),
+        }
+        // events without a color take the one of their user defined category
+        if let Category::Custom(id) = event.get_categories() {
+            match self.categories.get(&id) {
+                Some(category) if event.get_color().is_empty() => {
+                    event.set_color(category.color.as_str())
+                }
+                _ => {}
             }
-            self.events_all.push(event.clone());
-            let mut pointer_date = start_date.clone();
-            while pointer_date.le(&end_date) {
-                self.events_by_date
-                    .get_mut(&pointer_date)
-                    .unwrap()
-                    .push(event.clone());
-                pointer_date = pointer_date.checked_add_days(Days::new(1)).unwrap();
+        }
+        // and then the one of their calendar
+        if let Some(calendar) = event.get_calendar().and_then(|id| self.calendars.get(&id)) {
+            if event.get_color().is_empty() {
+                event.set_color(calendar.color.as_str())
             }
-            self.events_by_id.insert(event.get_id(), event.clone());
         }
-        self.properties.last_modified = Utc::now().timestamp_millis();
+        let category_conflict = self.get_category_conflict(event.as_ref());
+        let calendar_conflict = self.get_calendar_conflict(event.as_ref());
+        let event = Arc::new(event);
+        if self.has_conflict(&event) {
+            let ignore_conflict = event.get_generator_instance().map_or(false, |id| {
+                self.instance.get(&id).map_or(false, |instance| {
+                    instance
+                        .repeat
+                        .as_ref()
+                        .map_or(false, |repeat| !repeat.throw_error_when_conflict)
+                })
+            }) || category_conflict == Some(ConflictBehavior::Skip)
+                || calendar_conflict == Some(ConflictBehavior::Skip);
+            if ignore_conflict {
+                return Ok(false);
+            }
+            bail!(InternalError::ConflictEventError {
+                start_time: event.get_start_time(),
+                end_time: event.get_end_time()
+            });
+        }
+        let before = self.snapshot_event(event.get_id());
+        // clean for update first
+        self.unstore_event(event.get_id());
+        self.store_event(event.clone());
+        self.record_event(event.get_id(), before);
+        self.touch();
+        Ok(true)
+    }
+
+    // whether the event overlaps a stored one it may not overlap, a stored state of the
+    // event itself does not count
+    fn has_conflict(&self, event: &Arc<Box<dyn EventCommonTrait>>) -> bool {
+        let category_conflict = self.get_category_conflict(event.as_ref().as_ref());
+        let calendar_conflict = self.get_calendar_conflict(event.as_ref().as_ref());
+        let mut date_event_vec = Vec::new();
+        date_event_vec.push(event.clone());
+        let start_date = event.get_start_time().naive_utc().date();
+        let mut pointer_date = start_date.clone();
+        let end_date = event.get_end_time().naive_utc().date();
+        while pointer_date.le(&end_date) {
+            for e in self.events_by_date.get(&pointer_date).unwrap() {
+                date_event_vec.push(e.clone());
+            }
+            pointer_date = pointer_date.checked_add_days(Days::new(1)).unwrap();
+        }
+        date_event_vec.extend(self.get_conflicting_feed_events(start_date, end_date));
+        // check conflict
+        // events of a category or calendar allowing conflicts may overlap anything, in
+        // both directions
+        check_conflict(&date_event_vec)
+            .into_iter()
+            .any(|(first, second)| {
+                let second = second.as_ref().as_ref();
+                first.get_id() == event.get_id()
+                    && category_conflict != Some(ConflictBehavior::Allow)
+                    && self.get_category_conflict(second) != Some(ConflictBehavior::Allow)
+                    && calendar_conflict != Some(ConflictBehavior::Allow)
+                    && self.get_calendar_conflict(second) != Some(ConflictBehavior::Allow)
+                    && self.calendars_conflict(event.as_ref().as_ref(), second)
+            })
+    }
+
+    pub fn check_revision(&self, event_id: u128, expected: u64) -> Result<()> {
+        let current = match self.events_by_id.get(&event_id) {
+            Some(event) => event.get_revision(),
+            None => bail!(InternalError::EventNotFoundError),
+        };
+        if current != expected {
+            bail!(InternalError::RevisionConflictError {
+                event_id,
+                expected,
+                current
+            })
+        }
         Ok(())
     }
 
+    pub fn get_revision(&self) -> u64 {
+        self.properties.revision
+    }
+
+    pub fn get_last_modified(&self) -> i64 {
+        self.properties.last_modified
+    }
+
+    pub(crate) fn set_revision(&mut self, revision: u64) {
+        self.properties.revision = revision;
+    }
+
+    fn touch(&mut self) {
+        self.properties.last_modified = Utc::now().timestamp_millis();
+        self.properties.revision += 1;
+    }
+
     pub fn delete_event(&mut self, event_id: u128) -> Result<()> {
-        let search_result = self.events_by_id.get(&event_id);
-        if search_result.is_none() {
+        if self.get_event_feed(event_id).is_some() {
+            bail!(InternalError::ReadOnlyEventError { event_id })
+        }
+        let before = self.snapshot_event(event_id);
+        if self.unstore_event(event_id).is_none() {
             bail!(InternalError::EventNotFoundError)
         }
+        self.record_event(event_id, before);
+        self.touch();
+        Ok(())
+    }
+
+    // adds the event to every index without any check, its days must be indexable
+    fn store_event(&mut self, event: Arc<Box<dyn EventCommonTrait>>) {
+        self.events_all.push(event.clone());
+        let mut pointer_date = event.get_start_time().naive_utc().date();
+        while pointer_date.le(&event.get_end_time().naive_utc().date()) {
+            self.events_by_date
+                .get_mut(&pointer_date)
+                .unwrap()
+                .push(event.clone());
+            pointer_date = pointer_date.checked_add_days(Days::new(1)).unwrap();
+        }
+        self.events_by_id.insert(event.get_id(), event.clone());
+        self.index_tags(event.as_ref().as_ref());
+        self.search_index.add(event.as_ref().as_ref());
+    }
+
+    fn unstore_event(&mut self, event_id: u128) -> Option<Arc<Box<dyn EventCommonTrait>>> {
+        let event = self.events_by_id.remove(&event_id)?;
         self.events_all.retain(|event| event.get_id() != event_id);
-        let start_date = search_result.unwrap().get_start_time().naive_utc().date();
-        let end_date = search_result.unwrap().get_end_time().naive_utc().date();
-        let mut pointer_date = start_date.clone();
-        while pointer_date.le(&end_date) {
+        let mut pointer_date = event.get_start_time().naive_utc().date();
+        while pointer_date.le(&event.get_end_time().naive_utc().date()) {
             self.events_by_date
                 .get_mut(&pointer_date)
                 .unwrap()
                 .retain(|event| event.get_id() != event_id);
             pointer_date = pointer_date.checked_add_days(Days::new(1)).unwrap();
         }
-        self.events_by_id.remove(&event_id);
-        self.properties.last_modified = Utc::now().timestamp_millis();
-        Ok(())
+        self.unindex_tags(event.as_ref().as_ref());
+        self.search_index.remove(event_id);
+        Some(event)
     }
 
     pub fn get_events_by_day<E: EventCommonTrait>(&self, day: NaiveDate) -> Vec<Arc<Box<&E>>> {
@@ -126,14 +344,7 @@ impl Cache {
             .get(&day)
             .unwrap()
             .iter()
-            .filter_map(|e| {
-                let e = e.as_ref().downcast_ref::<E>();
-                if e.is_none() {
-                    return None;
-                } else {
-                    Some(Arc::new(Box::new(e.unwrap())))
-                }
-            })
+            .filter_map(|e| e.as_ref().downcast_ref::<E>().map(|e| Arc::new(Box::new(e))))
             .collect()
     }
     pub fn get_events_by_id<E: EventCommonTrait>(&self, id: u128) -> Result<Arc<Box<&E>>> {
@@ -152,14 +363,7 @@ impl Cache {
     pub fn get_all_events<E: EventCommonTrait>(&self) -> Vec<Arc<Box<&E>>> {
         self.events_all
             .iter()
-            .filter_map(|e| {
-                let e = e.as_ref().downcast_ref::<E>();
-                if e.is_none() {
-                    return None;
-                } else {
-                    Some(Arc::new(Box::new(e.unwrap())))
-                }
-            })
+            .filter_map(|e| e.as_ref().downcast_ref::<E>().map(|e| Arc::new(Box::new(e))))
             .collect()
     }
     pub fn get_all_raw_events(&self) -> Vec<Arc<Box<dyn EventCommonTrait>>> {
@@ -172,10 +376,23 @@ impl Cache {
 
     pub fn add_or_update_instances(&mut self, instances: Vec<GeneratorInstance>) {
         for instance in instances {
-            self.instance.insert(instance.get_id(), instance);
+            let id = instance.get_id();
+            let before = self.instance.insert(id, instance.clone());
+            self.history.record(Change::Instance {
+                id,
+                before,
+                after: Some(instance),
+            });
         }
     }
     pub fn get_all_instances(&self) -> Vec<GeneratorInstance> {
         self.instance.values().map(|i| i.clone()).collect()
     }
 }
+
+// the days index only holds the days between the first and the last event day
+fn is_indexable(event: &dyn EventCommonTrait) -> bool {
+    event.get_start_time().naive_utc().ge(&MIN_EVENT_TIMESTAMP)
+        && event.get_end_time().naive_utc().le(&MAX_EVENT_TIMESTAMP)
+        && event.get_start_time().le(&event.get_end_time())
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use anyhow::bail;
+use anyhow::Result;
+use chrono::{DateTime, Days, FixedOffset};
+
+use crate::cache::Cache;
+use crate::common::exception::InternalError;
+use crate::model::{EventCommonTrait, ItemCommonTrait, Kind};
+use crate::model::alarm::Alarm;
+use crate::model::event::Event;
+use crate::model::notification::{Notification, NotificationRecord};
+
+impl Cache {
+    // reminders and event alarms firing within [since, now] that never fired, plus expired snoozes
+    pub fn get_due_notifications(
+        &self,
+        since: DateTime<FixedOffset>,
+        now: DateTime<FixedOffset>,
+    ) -> Vec<Notification> {
+        let mut due = HashMap::new();
+        let mut pointer_date = since.naive_utc().date();
+        while pointer_date.le(&now.naive_utc().date()) {
+            if let Some(events) = self.events_by_date.get(&pointer_date) {
+                for event in events {
+                    if matches!(event.get_kind(), Kind::Reminder)
+                        && event.get_start_time().ge(&since)
+                        && event.get_start_time().le(&now)
+                        && !self.notifications.contains_key(&(event.get_id(), None))
+                    {
+                        due.insert(
+                            (event.get_id(), None),
+                            build_notification(
+                                event.as_ref().as_ref(),
+                                None,
+                                event.get_start_time().timestamp_millis(),
+                            ),
+                        );
+                    }
+                }
+            }
+            pointer_date = pointer_date.checked_add_days(Days::new(1)).unwrap();
+        }
+        // alarms may fire long before their event, so they can not use the day index
+        for event in self.get_all_events::<Event>() {
+            for alarm in event.get_alarms() {
+                let fire_time = alarm.fire_time(event.get_start_time());
+                let key = (event.get_id(), Some(alarm.get_id()));
+                if fire_time.ge(&since)
+                    && fire_time.le(&now)
+                    && !self.notifications.contains_key(&key)
+                {
+                    due.insert(
+                        key,
+                        build_notification(**event, Some(alarm), fire_time.timestamp_millis()),
+                    );
+                }
+            }
+        }
+        for record in self.notifications.values() {
+            if !record.is_due_again(now.timestamp_millis()) {
+                continue;
+            }
+            let event = match self.events_by_id.get(&record.source_id) {
+                Some(event) => event.as_ref().as_ref(),
+                None => continue,
+            };
+            let alarm = match record.alarm_id {
+                Some(alarm_id) => match find_alarm(event, alarm_id) {
+                    Some(alarm) => Some(alarm),
+                    None => continue,
+                },
+                None => None,
+            };
+            due.insert(
+                (record.source_id, record.alarm_id),
+                build_notification(event, alarm, record.snoozed_until.unwrap()),
+            );
+        }
+        let mut due = due.into_values().collect::<Vec<Notification>>();
+        due.sort_by_key(|n| n.fire_time);
+        due
+    }
+
+    // when the next reminder, alarm or snooze fires from now on, in milliseconds, so users
+    // that are not loaded can be woken for it
+    pub fn get_next_notification_time(&self, now: DateTime<FixedOffset>) -> Option<i64> {
+        let reminders = self
+            .events_by_id
+            .values()
+            .filter(|event| matches!(event.get_kind(), Kind::Reminder))
+            .filter(|event| !self.notifications.contains_key(&(event.get_id(), None)))
+            .map(|event| event.get_start_time().timestamp_millis());
+        let alarms = self
+            .get_all_events::<Event>()
+            .iter()
+            .flat_map(|event| {
+                event
+                    .get_alarms()
+                    .iter()
+                    .filter(|alarm| {
+                        !self
+                            .notifications
+                            .contains_key(&(event.get_id(), Some(alarm.get_id())))
+                    })
+                    .map(|alarm| alarm.fire_time(event.get_start_time()).timestamp_millis())
+                    .collect::<Vec<i64>>()
+            })
+            .collect::<Vec<i64>>();
+        let snoozes = self
+            .notifications
+            .values()
+            .filter(|record| !record.dismissed)
+            .filter_map(|record| record.snoozed_until);
+        reminders
+            .chain(alarms)
+            .chain(snoozes)
+            .filter(|time| *time >= now.timestamp_millis())
+            .min()
+    }
+
+    pub fn mark_notification_fired(
+        &mut self,
+        source_id: u128,
+        alarm_id: Option<u128>,
+        fired_time: DateTime<FixedOffset>,
+    ) {
+        let record = self
+            .notifications
+            .entry((source_id, alarm_id))
+            .or_insert(NotificationRecord::new(source_id, alarm_id));
+        record.fired_time = Some(fired_time.timestamp_millis());
+        record.snoozed_until = None;
+        self.touch();
+    }
+
+    pub fn snooze_notification(
+        &mut self,
+        source_id: u128,
+        alarm_id: Option<u128>,
+        until: DateTime<FixedOffset>,
+    ) -> Result<()> {
+        self.check_notification_source(source_id, alarm_id)?;
+        let record = self
+            .notifications
+            .entry((source_id, alarm_id))
+            .or_insert(NotificationRecord::new(source_id, alarm_id));
+        record.snoozed_until = Some(until.timestamp_millis());
+        record.dismissed = false;
+        self.touch();
+        Ok(())
+    }
+
+    pub fn dismiss_notification(&mut self, source_id: u128, alarm_id: Option<u128>) -> Result<()> {
+        self.check_notification_source(source_id, alarm_id)?;
+        let record = self
+            .notifications
+            .entry((source_id, alarm_id))
+            .or_insert(NotificationRecord::new(source_id, alarm_id));
+        record.snoozed_until = None;
+        record.dismissed = true;
+        self.touch();
+        Ok(())
+    }
+
+    pub fn get_notification_record(
+        &self,
+        source_id: u128,
+        alarm_id: Option<u128>,
+    ) -> Option<NotificationRecord> {
+        self.notifications.get(&(source_id, alarm_id)).cloned()
+    }
+
+    pub fn get_all_notification_records(&self) -> Vec<NotificationRecord> {
+        self.notifications.values().cloned().collect()
+    }
+
+    pub fn add_or_update_notification_records(&mut self, records: Vec<NotificationRecord>) {
+        for record in records {
+            self.notifications
+                .insert((record.source_id, record.alarm_id), record);
+        }
+    }
+
+    fn check_notification_source(&self, source_id: u128, alarm_id: Option<u128>) -> Result<()> {
+        let event = match self.events_by_id.get(&source_id) {
+            Some(event) => event.as_ref().as_ref(),
+            None => bail!(InternalError::EventNotFoundError),
+        };
+        if let Some(alarm_id) = alarm_id {
+            if find_alarm(event, alarm_id).is_none() {
+                bail!(InternalError::AlarmNotFoundError)
+            }
+        }
+        Ok(())
+    }
+}
+
+fn find_alarm(event: &dyn EventCommonTrait, alarm_id: u128) -> Option<&Alarm> {
+    event
+        .downcast_ref::<Event>()
+        .and_then(|e| e.get_alarms().iter().find(|a| a.get_id() == alarm_id))
+}
+
+fn build_notification(
+    event: &dyn EventCommonTrait,
+    alarm: Option<&Alarm>,
+    fire_time: i64,
+) -> Notification {
+    Notification {
+        source_id: event.get_id(),
+        alarm_id: alarm.map(|a| a.get_id()),
+        channel: alarm.and_then(|a| a.channel.clone()),
+        kind: event.get_kind().to_string(),
+        title: event.get_title().to_string(),
+        description: event.get_description().to_string(),
+        fire_time,
+        user: String::new(),
+    }
+}
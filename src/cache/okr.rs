@@ -0,0 +1,243 @@
+use std::sync::Arc;
+
+use anyhow::bail;
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+
+use crate::cache::Cache;
+use crate::common::exception::InternalError;
+use crate::model::event::Event;
+use crate::model::{EventCommonTrait, ItemCommonTrait};
+use crate::model::history::Change;
+use crate::model::objective::{
+    KeyResult, KeyResultTimeReport, Objective, ObjectiveTimeReport, Quarter,
+};
+
+impl Cache {
+    pub fn add_or_update_objectives(&mut self, objectives: Vec<Objective>) {
+        for objective in objectives {
+            let id = objective.get_id();
+            let before = self.objectives.insert(id, objective.clone());
+            self.record_change(Change::Objective {
+                id,
+                before,
+                after: Some(objective),
+            });
+        }
+        self.touch();
+    }
+
+    pub fn get_objective(&self, id: u128) -> Result<Objective> {
+        match self.objectives.get(&id) {
+            Some(objective) => Ok(objective.clone()),
+            None => bail!(InternalError::ObjectiveNotFoundError),
+        }
+    }
+
+    pub fn get_all_objectives(&self) -> Vec<Objective> {
+        self.objectives.values().cloned().collect()
+    }
+
+    pub fn get_objectives_by_period(&self, period: Quarter) -> Vec<Objective> {
+        self.objectives
+            .values()
+            .filter(|o| o.period == period)
+            .cloned()
+            .collect()
+    }
+
+    // deleting an objective drops its key results and unlinks their events
+    pub fn delete_objective(&mut self, id: u128) -> Result<()> {
+        let before = match self.objectives.remove(&id) {
+            Some(objective) => objective,
+            None => bail!(InternalError::ObjectiveNotFoundError),
+        };
+        self.record_change(Change::Objective {
+            id,
+            before: Some(before),
+            after: None,
+        });
+        let key_results = self
+            .get_key_results_by_objective(id)
+            .iter()
+            .map(|k| k.get_id())
+            .collect::<Vec<u128>>();
+        for key_result in key_results {
+            self.delete_key_result(key_result)?;
+        }
+        self.touch();
+        Ok(())
+    }
+
+    pub fn add_or_update_key_results(&mut self, key_results: Vec<KeyResult>) -> Result<()> {
+        if key_results
+            .iter()
+            .any(|k| !self.objectives.contains_key(&k.get_objective_id()))
+        {
+            bail!(InternalError::ObjectiveNotFoundError)
+        }
+        for key_result in key_results {
+            let id = key_result.get_id();
+            let before = self.key_results.insert(id, key_result.clone());
+            self.record_change(Change::KeyResult {
+                id,
+                before,
+                after: Some(key_result),
+            });
+        }
+        self.touch();
+        Ok(())
+    }
+
+    pub fn get_key_result(&self, id: u128) -> Result<KeyResult> {
+        match self.key_results.get(&id) {
+            Some(key_result) => Ok(key_result.clone()),
+            None => bail!(InternalError::KeyResultNotFoundError),
+        }
+    }
+
+    pub fn get_all_key_results(&self) -> Vec<KeyResult> {
+        self.key_results.values().cloned().collect()
+    }
+
+    pub fn get_key_results_by_objective(&self, objective_id: u128) -> Vec<KeyResult> {
+        self.key_results
+            .values()
+            .filter(|k| k.get_objective_id() == objective_id)
+            .cloned()
+            .collect()
+    }
+
+    pub fn delete_key_result(&mut self, id: u128) -> Result<()> {
+        let before = match self.key_results.remove(&id) {
+            Some(key_result) => key_result,
+            None => bail!(InternalError::KeyResultNotFoundError),
+        };
+        self.record_change(Change::KeyResult {
+            id,
+            before: Some(before),
+            after: None,
+        });
+        let linked_events = self
+            .get_events_by_key_result(id)
+            .iter()
+            .map(|e| e.get_id())
+            .collect::<Vec<u128>>();
+        for event_id in linked_events {
+            self.link_event_to_key_result(event_id, None)?;
+        }
+        self.touch();
+        Ok(())
+    }
+
+    pub fn update_key_result_value(&mut self, id: u128, current_value: f64) -> Result<()> {
+        let before = self.get_key_result(id)?;
+        let mut after = before.clone();
+        after.current_value = current_value;
+        self.key_results.insert(id, after.clone());
+        self.record_change(Change::KeyResult {
+            id,
+            before: Some(before),
+            after: Some(after),
+        });
+        self.touch();
+        Ok(())
+    }
+
+    pub fn get_objective_progress(&self, id: u128) -> Result<f64> {
+        self.get_objective(id)?;
+        Ok(Objective::progress(&self.get_key_results_by_objective(id)))
+    }
+
+    // pass None as key result to unlink the event
+    pub fn link_event_to_key_result(
+        &mut self,
+        event_id: u128,
+        key_result_id: Option<u128>,
+    ) -> Result<()> {
+        if let Some(key_result_id) = key_result_id {
+            self.get_key_result(key_result_id)?;
+        }
+        let mut event = (**self.get_events_by_id::<Event>(event_id)?).clone();
+        event.set_key_result(key_result_id);
+        self.insert_single_event(Box::new(event))
+    }
+
+    pub fn get_events_by_key_result(&self, key_result_id: u128) -> Vec<Arc<Box<&Event>>> {
+        self.get_all_events::<Event>()
+            .into_iter()
+            .filter(|e| e.get_key_result() == Some(key_result_id))
+            .collect()
+    }
+
+    // hours linked to every key result within [start, end), time before now counts as spent
+    pub fn get_okr_time_report(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        now: DateTime<FixedOffset>,
+    ) -> Vec<ObjectiveTimeReport> {
+        let mut reports = self
+            .objectives
+            .values()
+            .map(|objective| {
+                let mut key_results = self
+                    .get_key_results_by_objective(objective.get_id())
+                    .iter()
+                    .map(|key_result| {
+                        let mut report = KeyResultTimeReport {
+                            key_result_id: key_result.get_id(),
+                            title: key_result.title.clone(),
+                            ..Default::default()
+                        };
+                        for event in self.get_events_by_key_result(key_result.get_id()) {
+                            let event_start = event.get_start_time().max(start);
+                            let event_end = event.get_end_time().min(end);
+                            report.spent_seconds +=
+                                seconds_between(event_start, event_end.min(now));
+                            report.planned_seconds +=
+                                seconds_between(event_start.max(now), event_end);
+                        }
+                        report
+                    })
+                    .collect::<Vec<KeyResultTimeReport>>();
+                key_results.sort_by(|a, b| a.title.cmp(&b.title));
+                ObjectiveTimeReport {
+                    objective_id: objective.get_id(),
+                    title: objective.title.clone(),
+                    planned_seconds: key_results.iter().map(|k| k.planned_seconds).sum(),
+                    spent_seconds: key_results.iter().map(|k| k.spent_seconds).sum(),
+                    key_results,
+                }
+            })
+            .collect::<Vec<ObjectiveTimeReport>>();
+        reports.sort_by(|a, b| a.title.cmp(&b.title));
+        reports
+    }
+
+    pub fn get_quarter_time_report(
+        &self,
+        quarter: Quarter,
+        now: DateTime<FixedOffset>,
+    ) -> Vec<ObjectiveTimeReport> {
+        let start = quarter.start_date().and_hms_opt(0, 0, 0).unwrap().and_utc();
+        let end = quarter
+            .end_date()
+            .succ_opt()
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc();
+        self.get_okr_time_report(DateTime::from(start), DateTime::from(end), now)
+            .into_iter()
+            .filter(|report| {
+                self.get_objective(report.objective_id)
+                    .is_ok_and(|objective| objective.period == quarter)
+            })
+            .collect()
+    }
+}
+
+fn seconds_between(start: DateTime<FixedOffset>, end: DateTime<FixedOffset>) -> i64 {
+    end.signed_duration_since(start).num_seconds().max(0)
+}
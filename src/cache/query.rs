@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use crate::cache::Cache;
+use crate::common::utils::{normalize_tag, tokenize};
+use crate::model::EventCommonTrait;
+use crate::query::{Comparison, Condition, Filter};
+
+impl Cache {
+    // events matching the filter, ordered by start, with the ones of subscribed feeds and
+    // without the ones of hidden calendars the filter does not name
+    pub fn query_events(&self, filter: &Filter) -> Vec<Arc<Box<dyn EventCommonTrait>>> {
+        let mut events = self.query_shown_events(filter);
+        events.extend(
+            self.get_feed_events()
+                .into_iter()
+                .filter(|event| self.matches(filter, event.as_ref().as_ref())),
+        );
+        events.sort_by_key(|event| event.get_start_time());
+        events
+    }
+
+    // the events stored here, without the ones of hidden calendars the filter does not
+    // name, e.g. to be served to CalDAV clients
+    pub fn query_shown_events(&self, filter: &Filter) -> Vec<Arc<Box<dyn EventCommonTrait>>> {
+        let named = filter.calendar_names();
+        let mut events = self.query_own_events(filter);
+        events.retain(|event| self.is_shown(event.as_ref().as_ref(), &named));
+        events
+    }
+
+    // a stored event unless its calendar is hidden
+    pub fn get_shown_event(&self, id: u128) -> Option<Arc<Box<dyn EventCommonTrait>>> {
+        self.events_by_id
+            .get(&id)
+            .filter(|event| self.is_shown(event.as_ref().as_ref(), &[]))
+            .cloned()
+    }
+
+    // only the events stored here, whatever their calendar, e.g. to be synced elsewhere
+    pub fn query_own_events(&self, filter: &Filter) -> Vec<Arc<Box<dyn EventCommonTrait>>> {
+        let mut events = self
+            .events_all
+            .iter()
+            .filter(|event| self.matches(filter, event.as_ref().as_ref()))
+            .cloned()
+            .collect::<Vec<Arc<Box<dyn EventCommonTrait>>>>();
+        events.sort_by_key(|event| event.get_start_time());
+        events
+    }
+
+    pub fn matches(&self, filter: &Filter, event: &dyn EventCommonTrait) -> bool {
+        match filter {
+            Filter::All => true,
+            Filter::And(filters) => filters.iter().all(|f| self.matches(f, event)),
+            Filter::Or(filters) => filters.iter().any(|f| self.matches(f, event)),
+            Filter::Not(filter) => !self.matches(filter, event),
+            Filter::Text(text) => {
+                let mut words = tokenize(event.get_title());
+                words.extend(tokenize(event.get_description()));
+                for tag in event.get_tags() {
+                    words.extend(tokenize(tag));
+                }
+                tokenize(text)
+                    .iter()
+                    .all(|query| words.iter().any(|word| word.starts_with(query.as_str())))
+            }
+            Filter::Condition(condition) => self.matches_condition(condition, event),
+        }
+    }
+
+    fn matches_condition(&self, condition: &Condition, event: &dyn EventCommonTrait) -> bool {
+        // equality on text fields is case insensitive, != is its negation
+        let equal = |comparison: &Comparison, found: bool| match comparison {
+            Comparison::NotEqual => !found,
+            _ => found,
+        };
+        let contains = |text: &str, value: &str| text.to_lowercase().contains(&value.to_lowercase());
+        match condition {
+            Condition::Kind(comparison, kind) => equal(comparison, event.get_kind() == *kind),
+            Condition::Category(comparison, value) => {
+                let category = event.get_categories();
+                let found = self.get_category_name(&category).eq_ignore_ascii_case(value)
+                    || category.to_string().eq_ignore_ascii_case(value);
+                equal(comparison, found)
+            }
+            Condition::Importance(comparison, level) => {
+                comparison.compare(&event.get_importance(), level)
+            }
+            Condition::Tag(comparison, value) => {
+                let tag = normalize_tag(value);
+                let found = event.get_tags().iter().any(|t| normalize_tag(t) == tag);
+                equal(comparison, found)
+            }
+            Condition::Title(comparison, value) => {
+                equal(comparison, contains(event.get_title(), value))
+            }
+            Condition::Description(comparison, value) => {
+                equal(comparison, contains(event.get_description(), value))
+            }
+            Condition::Color(comparison, value) => {
+                equal(comparison, event.get_color().eq_ignore_ascii_case(value))
+            }
+            // * stands for any feed
+            Condition::Feed(comparison, value) => {
+                let found = self
+                    .get_event_feed(event.get_id())
+                    .is_some_and(|feed| value == "*" || feed.name.eq_ignore_ascii_case(value));
+                equal(comparison, found)
+            }
+            // feed events are in no calendar of ours
+            Condition::Calendar(comparison, value) => {
+                let found = self.get_event_feed(event.get_id()).is_none()
+                    && self.get_calendar_name(event).eq_ignore_ascii_case(value);
+                equal(comparison, found)
+            }
+            Condition::Start(comparison, time) => comparison.compare(&event.get_start_time(), time),
+            Condition::End(comparison, time) => comparison.compare(&event.get_end_time(), time),
+            Condition::Duration(comparison, minutes) => {
+                let duration = event
+                    .get_end_time()
+                    .signed_duration_since(event.get_start_time())
+                    .num_minutes();
+                comparison.compare(&duration, minutes)
+            }
+        }
+    }
+}
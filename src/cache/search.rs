@@ -0,0 +1,115 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+
+use crate::cache::Cache;
+use crate::common::utils::tokenize;
+use crate::model::EventCommonTrait;
+use crate::model::search::{SearchHit, SearchQuery};
+
+const TITLE_WEIGHT: f64 = 3.0;
+const TAG_WEIGHT: f64 = 2.0;
+const DESCRIPTION_WEIGHT: f64 = 1.0;
+const PREFIX_PENALTY: f64 = 0.5; // a word only starting with the query word counts less
+
+// inverted index from word to the events containing it and the weight of the occurrences
+#[derive(Default)]
+pub(crate) struct SearchIndex {
+    postings: BTreeMap<String, HashMap<u128, f64>>,
+    words_by_event: HashMap<u128, Vec<String>>,
+}
+
+impl SearchIndex {
+    pub(crate) fn add(&mut self, event: &dyn EventCommonTrait) {
+        self.remove(event.get_id());
+        let mut weights: HashMap<String, f64> = HashMap::new();
+        let fields = [
+            (event.get_title().to_string(), TITLE_WEIGHT),
+            (event.get_description().to_string(), DESCRIPTION_WEIGHT),
+            (event.get_tags().iter().cloned().collect::<Vec<String>>().join(" "), TAG_WEIGHT),
+        ];
+        for (text, weight) in fields {
+            for word in tokenize(text.as_str()) {
+                *weights.entry(word).or_default() += weight;
+            }
+        }
+        for (word, weight) in &weights {
+            self.postings
+                .entry(word.clone())
+                .or_default()
+                .insert(event.get_id(), *weight);
+        }
+        self.words_by_event
+            .insert(event.get_id(), weights.into_keys().collect());
+    }
+
+    pub(crate) fn remove(&mut self, event_id: u128) {
+        for word in self.words_by_event.remove(&event_id).unwrap_or_default() {
+            if let Some(events) = self.postings.get_mut(&word) {
+                events.remove(&event_id);
+                if events.is_empty() {
+                    self.postings.remove(&word);
+                }
+            }
+        }
+    }
+
+    // best score of every event having a word starting with the given one
+    fn lookup(&self, prefix: &str) -> HashMap<u128, f64> {
+        let mut scores: HashMap<u128, f64> = HashMap::new();
+        let range = self
+            .postings
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(|(word, _)| word.starts_with(prefix));
+        for (word, events) in range {
+            let factor = if word == prefix { 1.0 } else { PREFIX_PENALTY };
+            for (id, weight) in events {
+                let score = scores.entry(*id).or_default();
+                *score = score.max(weight * factor);
+            }
+        }
+        scores
+    }
+}
+
+impl Cache {
+    // ranked by score, ties by start time, an empty text matches nothing
+    pub fn search_events(&self, query: &SearchQuery) -> Vec<SearchHit> {
+        let mut scores: Option<HashMap<u128, f64>> = None;
+        for word in tokenize(query.text.as_str()) {
+            let found = self.search_index.lookup(word.as_str());
+            scores = Some(match scores {
+                Some(scores) => scores
+                    .into_iter()
+                    .filter_map(|(id, score)| found.get(&id).map(|s| (id, score + s)))
+                    .collect(),
+                None => found,
+            });
+        }
+        let mut hits = scores
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(id, score)| {
+                self.events_by_id
+                    .get(&id)
+                    .map(|event| SearchHit {
+                        event: event.clone(),
+                        score,
+                    })
+            })
+            .filter(|hit| {
+                (query.kinds.is_empty() || query.kinds.contains(&hit.event.get_kind()))
+                    && query.start.is_none_or(|start| hit.event.get_end_time().gt(&start))
+                    && query.end.is_none_or(|end| hit.event.get_start_time().lt(&end))
+            })
+            .collect::<Vec<SearchHit>>();
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then(a.event.get_start_time().cmp(&b.event.get_start_time()))
+        });
+        if let Some(limit) = query.limit {
+            hits.truncate(limit);
+        }
+        hits
+    }
+}
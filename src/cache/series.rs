@@ -0,0 +1,116 @@
+use anyhow::bail;
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Days, FixedOffset, Months, Weekday};
+
+use crate::cache::Cache;
+use crate::common::exception::InternalError;
+use crate::common::utils::MAX_EVENT_TIMESTAMP;
+use crate::model::{EventCommonTrait, ItemCommonTrait};
+use crate::model::event::Event;
+use crate::model::generator_instance::{GeneratorInstance, Repeat, RepeatStrategy};
+
+// more would be a mistake in the strategy rather than a series anyone wants
+pub const MAX_OCCURRENCES: usize = 1000;
+
+impl Cache {
+    // repeats an event as a series until the until day of the strategy, each occurrence with
+    // its alarms moved along. returns the ids of the stored occurrences, those skipped by
+    // the conflict rules are left out
+    pub fn repeat_event(
+        &mut self,
+        id: u128,
+        strategy: RepeatStrategy,
+        throw_error_when_conflict: bool,
+    ) -> Result<Vec<u128>> {
+        let event = self.get_events_by_id::<Event>(id)?.self_clone(false);
+        if event.get_generator_instance().is_some() {
+            bail!(InternalError::InvalidRepeatStrategyError {
+                reason: format!("event {} already belongs to a series", id)
+            })
+        }
+        let mut instance = GeneratorInstance::new();
+        let occurrences = occurrence_starts(event.get_start_time(), &strategy)?
+            .into_iter()
+            .map(|start| {
+                let end = start + (event.get_end_time() - event.get_start_time());
+                let mut occurrence = event.occurrence(start, end);
+                occurrence.set_generator_instance(instance.get_id());
+                occurrence
+            })
+            .collect::<Vec<Event>>();
+        let mut first = event;
+        first.set_generator_instance(instance.get_id());
+        self.atomically(|cache| {
+            instance.repeat = Some(Repeat {
+                repeat_strategy: strategy,
+                event_queue: vec![id],
+                throw_error_when_conflict,
+            });
+            cache.add_or_update_instances(vec![instance.clone()]);
+            cache.insert_single_event(Box::new(first))?;
+            let ids = occurrences.iter().map(|o| o.get_id()).collect::<Vec<u128>>();
+            let skipped = cache.insert_events(
+                occurrences
+                    .into_iter()
+                    .map(|o| Box::new(o) as Box<dyn EventCommonTrait>)
+                    .collect(),
+            )?;
+            let stored = ids
+                .into_iter()
+                .filter(|id| !skipped.contains(id))
+                .collect::<Vec<u128>>();
+            if let Some(repeat) = instance.repeat.as_mut() {
+                repeat.event_queue.extend(stored.iter());
+            }
+            cache.add_or_update_instances(vec![instance]);
+            Ok(stored)
+        })
+    }
+}
+
+// starts after the first one, there is no holiday calendar so skip_holiday has no effect
+fn occurrence_starts(
+    first: DateTime<FixedOffset>,
+    strategy: &RepeatStrategy,
+) -> Result<Vec<DateTime<FixedOffset>>> {
+    let invalid = |reason: &str| InternalError::InvalidRepeatStrategyError {
+        reason: reason.to_string(),
+    };
+    if !RepeatStrategy::check_valid(strategy) {
+        bail!(invalid("it ends before it starts or skips every day"))
+    }
+    let months = strategy.repeat_gap_month + strategy.repeat_gap_year.saturating_mul(12);
+    if strategy.repeat_gap_day == 0 && months == 0 {
+        bail!(invalid("the gap between occurrences is empty"))
+    }
+    let mut starts = vec![];
+    for n in 1.. {
+        // counted from the first start, so a month end is not pulled earlier month by month
+        let start = months
+            .checked_mul(n)
+            .and_then(|months| first.checked_add_months(Months::new(months)))
+            .zip(strategy.repeat_gap_day.checked_mul(n))
+            .and_then(|(start, days)| start.checked_add_days(Days::new(days as u64)));
+        let start = match start {
+            Some(start)
+                if start.timestamp_millis() <= strategy.until_day
+                    && start.naive_utc() <= *MAX_EVENT_TIMESTAMP =>
+            {
+                start
+            }
+            _ => break,
+        };
+        let weekend = matches!(start.weekday(), Weekday::Sat | Weekday::Sun);
+        if start.timestamp_millis() < strategy.start_day
+            || (weekend && strategy.skip_weekend)
+            || (!weekend && strategy.skip_weekday)
+        {
+            continue;
+        }
+        if starts.len() == MAX_OCCURRENCES {
+            bail!(invalid(format!("it has more than {} occurrences", MAX_OCCURRENCES).as_str()))
+        }
+        starts.push(start);
+    }
+    Ok(starts)
+}
@@ -0,0 +1,174 @@
+use std::sync::Arc;
+
+use anyhow::bail;
+use anyhow::Result;
+use chrono::NaiveDate;
+use tracing::warn;
+
+use crate::cache::Cache;
+use crate::common::exception::InternalError;
+use crate::ics::{format_uid, parse_objects, parse_uid};
+use crate::model::EventCommonTrait;
+use crate::model::subscription::Subscription;
+
+impl Cache {
+    pub fn add_subscription(&mut self, subscription: Subscription) -> Result<()> {
+        let duplicated = self
+            .subscriptions
+            .values()
+            .any(|s| s.name == subscription.name && s.get_id() != subscription.get_id());
+        if duplicated {
+            bail!(InternalError::SubscriptionAlreadyExistError {
+                name: subscription.name.clone()
+            });
+        }
+        let events = parse_feed(&subscription)?;
+        self.set_feed_events(subscription.get_id(), events);
+        self.subscriptions.insert(subscription.get_id(), subscription);
+        self.touch();
+        Ok(())
+    }
+
+    pub fn get_subscription(&self, id: u128) -> Result<Subscription> {
+        match self.subscriptions.get(&id) {
+            Some(subscription) => Ok(subscription.clone()),
+            None => bail!(InternalError::SubscriptionNotFoundError),
+        }
+    }
+
+    pub fn get_subscription_by_name(&self, name: &str) -> Result<Subscription> {
+        match self.subscriptions.values().find(|s| s.name == name) {
+            Some(subscription) => Ok(subscription.clone()),
+            None => bail!(InternalError::SubscriptionNotFoundError),
+        }
+    }
+
+    pub fn get_all_subscriptions(&self) -> Vec<Subscription> {
+        let mut subscriptions = self.subscriptions.values().cloned().collect::<Vec<Subscription>>();
+        subscriptions.sort_by(|a, b| a.name.cmp(&b.name));
+        subscriptions
+    }
+
+    pub fn delete_subscription(&mut self, id: u128) -> Result<()> {
+        if self.subscriptions.remove(&id).is_none() {
+            bail!(InternalError::SubscriptionNotFoundError)
+        }
+        self.set_feed_events(id, vec![]);
+        self.feed_events.remove(&id);
+        self.touch();
+        Ok(())
+    }
+
+    // replaces the events of the feed, content that does not parse keeps the previous
+    // ones and is recorded as the error of the refresh
+    pub fn refresh_feed(&mut self, id: u128, content: String, now: i64) -> Result<usize> {
+        let mut subscription = self.get_subscription(id)?;
+        subscription.refreshed_time = Some(now);
+        let previous = std::mem::replace(&mut subscription.content, content);
+        let events = match parse_feed(&subscription) {
+            Ok(events) => events,
+            Err(error) => {
+                subscription.content = previous;
+                subscription.error = Some(error.to_string());
+                self.subscriptions.insert(id, subscription);
+                return Err(error);
+            }
+        };
+        subscription.error = None;
+        let count = events.len();
+        self.set_feed_events(id, events);
+        self.subscriptions.insert(id, subscription);
+        self.touch();
+        Ok(count)
+    }
+
+    // a feed that could not be fetched is tried again at its next refresh
+    pub fn fail_feed(&mut self, id: u128, error: String, now: i64) -> Result<()> {
+        match self.subscriptions.get_mut(&id) {
+            Some(subscription) => {
+                subscription.refreshed_time = Some(now);
+                subscription.error = Some(error);
+                Ok(())
+            }
+            None => bail!(InternalError::SubscriptionNotFoundError),
+        }
+    }
+
+    pub fn get_feed_events(&self) -> Vec<Arc<Box<dyn EventCommonTrait>>> {
+        self.feed_events.values().flatten().cloned().collect()
+    }
+
+    // the subscription an event was read from, None for our own events
+    pub fn get_event_feed(&self, event_id: u128) -> Option<&Subscription> {
+        self.feed_event_index
+            .get(&event_id)
+            .and_then(|id| self.subscriptions.get(id))
+    }
+
+    // feed events our events may not overlap on the given days, by the subscriptions
+    // asking for it
+    pub(crate) fn get_conflicting_feed_events(
+        &self,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Vec<Arc<Box<dyn EventCommonTrait>>> {
+        self.subscriptions
+            .values()
+            .filter(|s| s.count_conflicts)
+            .filter_map(|s| self.feed_events.get(&s.get_id()))
+            .flatten()
+            .filter(|e| {
+                e.get_start_time().naive_utc().date() <= end_date
+                    && e.get_end_time().naive_utc().date() >= start_date
+            })
+            .cloned()
+            .collect()
+    }
+
+    // keeps the index of feed events in step with the events of the subscription
+    fn set_feed_events(&mut self, id: u128, events: Vec<Arc<Box<dyn EventCommonTrait>>>) {
+        if let Some(previous) = self.feed_events.get(&id) {
+            for event in previous {
+                self.feed_event_index.remove(&event.get_id());
+            }
+        }
+        for event in &events {
+            self.feed_event_index.insert(event.get_id(), id);
+        }
+        self.feed_events.insert(id, events);
+    }
+
+    // feeds are parsed again from the content they were saved with
+    pub fn restore_subscriptions(&mut self, subscriptions: Vec<Subscription>) {
+        self.feed_events.clear();
+        self.feed_event_index.clear();
+        self.subscriptions.clear();
+        for subscription in subscriptions {
+            let events = parse_feed(&subscription).unwrap_or_else(|error| {
+                warn!("feed {} can not be read: {}", subscription.name, error);
+                vec![]
+            });
+            self.set_feed_events(subscription.get_id(), events);
+            self.subscriptions.insert(subscription.get_id(), subscription);
+        }
+    }
+}
+
+// ids are scoped to the subscription, so events of two feeds or our own never share one
+fn parse_feed(subscription: &Subscription) -> Result<Vec<Arc<Box<dyn EventCommonTrait>>>> {
+    // a wrong url usually answers with a web page, which holds no events either
+    if !subscription.content.is_empty() && !subscription.content.contains("BEGIN:VCALENDAR") {
+        bail!(InternalError::InvalidIcsError {
+            reason: "not an iCalendar feed".to_string()
+        })
+    }
+    let prefix = format_uid(subscription.get_id());
+    parse_objects(subscription.content.as_str())?
+        .into_iter()
+        .map(|(uid, mut model)| {
+            model.id = parse_uid(format!("{}/{}", prefix, uid).as_str());
+            model.revision = 0;
+            Ok(Arc::new(model.convert_to()?))
+        })
+        .collect()
+}
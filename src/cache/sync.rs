@@ -0,0 +1,27 @@
+use crate::cache::Cache;
+use crate::model::sync::SyncState;
+
+impl Cache {
+    // a collection never synced starts without any entries
+    pub fn get_sync_state(&self, url: &str) -> SyncState {
+        self.sync_states
+            .get(url)
+            .cloned()
+            .unwrap_or_else(|| SyncState::new(url))
+    }
+
+    pub fn set_sync_state(&mut self, state: SyncState) {
+        self.sync_states.insert(state.url.clone(), state);
+    }
+
+    pub fn get_sync_states(&self) -> Vec<SyncState> {
+        self.sync_states.values().cloned().collect()
+    }
+
+    pub fn restore_sync_states(&mut self, states: Vec<SyncState>) {
+        self.sync_states = states
+            .into_iter()
+            .map(|state| (state.url.clone(), state))
+            .collect();
+    }
+}
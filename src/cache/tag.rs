@@ -0,0 +1,131 @@
+use std::collections::{BTreeSet, HashSet};
+use std::sync::Arc;
+
+use anyhow::bail;
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+
+use crate::cache::Cache;
+use crate::common::exception::InternalError;
+use crate::common::utils::normalize_tag;
+use crate::model::{EventCommonTrait, Kind};
+use crate::model::break_block::Break;
+use crate::model::event::Event;
+use crate::model::reminder::Reminder;
+
+impl Cache {
+    pub(crate) fn index_tags(&mut self, event: &dyn EventCommonTrait) {
+        for tag in event.get_tags() {
+            self.events_by_tag
+                .entry(normalize_tag(tag))
+                .or_default()
+                .insert(event.get_id());
+        }
+    }
+
+    pub(crate) fn unindex_tags(&mut self, event: &dyn EventCommonTrait) {
+        for tag in event.get_tags() {
+            let tag = normalize_tag(tag);
+            if let Some(ids) = self.events_by_tag.get_mut(&tag) {
+                ids.remove(&event.get_id());
+                if ids.is_empty() {
+                    self.events_by_tag.remove(&tag);
+                }
+            }
+        }
+    }
+
+    // owned copy of a stored event, to be changed and inserted again
+    pub(crate) fn clone_event(&self, event_id: u128) -> Result<Box<dyn EventCommonTrait>> {
+        let event = match self.events_by_id.get(&event_id) {
+            Some(event) => event,
+            None => bail!(InternalError::EventNotFoundError),
+        };
+        let event = event.as_ref().as_ref();
+        let copy: Box<dyn EventCommonTrait> = match event.get_kind() {
+            Kind::Event => Box::new(event.downcast_ref::<Event>().unwrap().clone()),
+            Kind::Reminder => Box::new(event.downcast_ref::<Reminder>().unwrap().self_clone(false)),
+            Kind::Break => Box::new(event.downcast_ref::<Break>().unwrap().clone()),
+            Kind::Task => bail!(InternalError::InvalidKindError {
+                kind: Kind::Task.to_string()
+            }),
+        };
+        Ok(copy)
+    }
+
+    // replaces the tags of an event or reminder, tags are stored normalized
+    pub fn set_event_tags(&mut self, event_id: u128, tags: Vec<&str>) -> Result<()> {
+        let mut event = self.clone_event(event_id)?;
+        event.set_tags(
+            tags.iter()
+                .map(|tag| normalize_tag(tag))
+                .filter(|tag| !tag.is_empty())
+                .collect(),
+        );
+        self.insert_single_event(event)
+    }
+
+    pub fn add_event_tags(&mut self, event_id: u128, tags: Vec<&str>) -> Result<()> {
+        let mut event = self.clone_event(event_id)?;
+        let mut all_tags = event.get_tags().clone();
+        all_tags.extend(tags.iter().map(|tag| normalize_tag(tag)).filter(|t| !t.is_empty()));
+        event.set_tags(all_tags);
+        self.insert_single_event(event)
+    }
+
+    pub fn remove_event_tags(&mut self, event_id: u128, tags: Vec<&str>) -> Result<()> {
+        let mut event = self.clone_event(event_id)?;
+        let removed = tags.iter().map(|tag| normalize_tag(tag)).collect::<HashSet<String>>();
+        let remaining = event
+            .get_tags()
+            .iter()
+            .filter(|tag| !removed.contains(&normalize_tag(tag)))
+            .cloned()
+            .collect::<BTreeSet<String>>();
+        event.set_tags(remaining);
+        self.insert_single_event(event)
+    }
+
+    // every tag in use with the number of events carrying it
+    pub fn get_all_tags(&self) -> Vec<(String, usize)> {
+        let mut tags = self
+            .events_by_tag
+            .iter()
+            .map(|(tag, ids)| (tag.clone(), ids.len()))
+            .collect::<Vec<(String, usize)>>();
+        tags.sort();
+        tags
+    }
+
+    // events carrying all given tags and overlapping [start, end) when given, ordered by start
+    pub fn get_events_by_tags(
+        &self,
+        tags: &[&str],
+        start: Option<DateTime<FixedOffset>>,
+        end: Option<DateTime<FixedOffset>>,
+    ) -> Vec<Arc<Box<dyn EventCommonTrait>>> {
+        let mut ids: Option<HashSet<u128>> = None;
+        for tag in tags {
+            let tagged = self
+                .events_by_tag
+                .get(&normalize_tag(tag))
+                .cloned()
+                .unwrap_or_default();
+            ids = Some(match ids {
+                Some(ids) => ids.intersection(&tagged).cloned().collect(),
+                None => tagged,
+            });
+        }
+        let mut events = ids
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| self.events_by_id.get(id).cloned())
+            .filter(|event| {
+                start.is_none_or(|start| event.get_end_time().gt(&start))
+                    && end.is_none_or(|end| event.get_start_time().lt(&end))
+            })
+            .collect::<Vec<Arc<Box<dyn EventCommonTrait>>>>();
+        events.sort_by_key(|event| event.get_start_time());
+        events
+    }
+}
@@ -0,0 +1,87 @@
+use anyhow::bail;
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, NaiveDate};
+
+use crate::cache::Cache;
+use crate::common::exception::InternalError;
+use crate::model::ItemCommonTrait;
+use crate::model::history::Change;
+use crate::model::task::{Task, TaskStatus};
+
+impl Cache {
+    pub fn add_or_update_tasks(&mut self, tasks: Vec<Task>) {
+        for task in tasks {
+            let id = task.get_id();
+            let before = self.tasks.insert(id, task.clone());
+            self.record_change(Change::Task {
+                id,
+                before: before.map(|t| Box::new(t.convert_to())),
+                after: Some(Box::new(task.convert_to())),
+            });
+        }
+        self.touch();
+    }
+
+    pub fn get_task(&self, id: u128) -> Result<Task> {
+        match self.tasks.get(&id) {
+            Some(task) => Ok(task.clone()),
+            None => bail!(InternalError::TaskNotFoundError),
+        }
+    }
+
+    pub fn get_all_tasks(&self) -> Vec<Task> {
+        self.tasks.values().cloned().collect()
+    }
+
+    pub fn delete_task(&mut self, id: u128) -> Result<()> {
+        let before = match self.tasks.remove(&id) {
+            Some(task) => task,
+            None => bail!(InternalError::TaskNotFoundError),
+        };
+        self.record_change(Change::Task {
+            id,
+            before: Some(Box::new(before.convert_to())),
+            after: None,
+        });
+        self.touch();
+        Ok(())
+    }
+
+    pub fn set_task_status(&mut self, id: u128, status: TaskStatus) -> Result<()> {
+        let before = self.get_task(id)?;
+        let mut after = before.clone();
+        after.set_status(status);
+        self.tasks.insert(id, after.clone());
+        self.record_change(Change::Task {
+            id,
+            before: Some(Box::new(before.convert_to())),
+            after: Some(Box::new(after.convert_to())),
+        });
+        self.touch();
+        Ok(())
+    }
+
+    pub fn get_tasks_by_status(&self, status: TaskStatus) -> Vec<Task> {
+        self.tasks
+            .values()
+            .filter(|t| t.get_status() == status)
+            .cloned()
+            .collect()
+    }
+
+    pub fn get_tasks_by_due_day(&self, day: NaiveDate) -> Vec<Task> {
+        self.tasks
+            .values()
+            .filter(|t| t.get_due_time().is_some_and(|due| due.naive_utc().date() == day))
+            .cloned()
+            .collect()
+    }
+
+    pub fn get_overdue_tasks(&self, now: DateTime<FixedOffset>) -> Vec<Task> {
+        self.tasks
+            .values()
+            .filter(|t| t.is_overdue(now))
+            .cloned()
+            .collect()
+    }
+}
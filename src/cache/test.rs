@@ -1,12 +1,29 @@
 #[cfg(test)]
 mod tests {
-    use chrono::{DateTime, Days, Duration, Utc};
+    use chrono::{DateTime, Days, Duration, FixedOffset, NaiveDate, NaiveTime, Utc};
 
     use crate::cache::Cache;
+    use crate::common::exception::InternalError;
     use crate::model::event::Event;
-    use crate::model::EventCommonTrait;
+    use crate::model::{EventCommonTrait, ItemCommonTrait};
+    use crate::model::alarm::Alarm;
+    use crate::model::audit::{AuditAction, AuditEntry, AUDIT_LOG_LIMIT, FieldChange};
+    use crate::model::break_block::Break;
+    use crate::model::break_policy::{BreakPolicy, BreakViolation, LunchWindow, TimeBlock};
+    use crate::model::calendar::Calendar;
+    use crate::model::category::{ConflictBehavior, UserCategory};
+    use crate::model::change_feed::CHANGE_LOG_LIMIT;
     use crate::model::generator_instance::{GeneratorInstance, Repeat, RepeatStrategy};
+    use crate::model::objective::{KeyResult, Objective, Quarter};
     use crate::model::reminder::Reminder;
+    use crate::model::search::SearchQuery;
+    use crate::model::subscription::Subscription;
+    use crate::model::task::{Task, TaskStatus};
+    use crate::model::view::SavedView;
+    use crate::query::parse_query;
+    use crate::model::tracking::TrackingGroup;
+    use crate::model::transaction::Transaction;
+    use crate::model::{Category, ImportantLevel, Kind};
 
 // use crate::model::reminder::Reminder;
 
@@ -172,4 +189,1039 @@ mod tests {
         assert_eq!(reminders.is_ok(), true);
         assert_eq!(reminders.unwrap().get_kind().to_string(), "Reminder");
     }
+
+    #[test]
+    fn objective_progress_is_average_of_key_results() {
+        let mut cache = Cache::init();
+        let objective = Objective::new("Ship v1", "team", Quarter::new(2026, 3).unwrap());
+        let id = objective.get_id();
+        cache.add_or_update_objectives(vec![objective]);
+        let mut first = KeyResult::new(id, "Close bugs", 0.0, 10.0);
+        first.current_value = 5.0;
+        let mut second = KeyResult::new(id, "Cut latency", 200.0, 100.0);
+        second.current_value = 100.0;
+        let second_id = second.get_id();
+        cache.add_or_update_key_results(vec![first, second]).unwrap();
+        assert_eq!(cache.get_objective_progress(id).unwrap(), 0.75);
+        cache.update_key_result_value(second_id, 250.0).unwrap();
+        assert_eq!(cache.get_objective_progress(id).unwrap(), 0.25);
+        let orphan = KeyResult::new(1, "No objective", 0.0, 1.0);
+        assert!(cache.add_or_update_key_results(vec![orphan]).is_err());
+    }
+
+    #[test]
+    fn link_event_to_key_result_and_unlink_on_delete() {
+        let mut cache = Cache::init();
+        let objective = Objective::new("Ship v1", "team", Quarter::new(2026, 3).unwrap());
+        let objective_id = objective.get_id();
+        cache.add_or_update_objectives(vec![objective]);
+        let key_result = KeyResult::new(objective_id, "Close bugs", 0.0, 10.0);
+        let key_result_id = key_result.get_id();
+        cache.add_or_update_key_results(vec![key_result]).unwrap();
+        let mut event = Event::init(None);
+        let event_id = event.get_id();
+        event.set_duration(
+            DateTime::from(Utc::now()),
+            DateTime::from(Utc::now().checked_add_signed(Duration::hours(1)).unwrap()),
+        );
+        cache.insert_events(vec![Box::new(event)]).unwrap();
+        assert!(cache.link_event_to_key_result(event_id, Some(2)).is_err());
+        cache
+            .link_event_to_key_result(event_id, Some(key_result_id))
+            .unwrap();
+        let linked = cache.get_events_by_key_result(key_result_id);
+        assert_eq!(linked.len(), 1);
+        assert_eq!(linked[0].get_id(), event_id);
+        assert_eq!(cache.get_all_events::<Event>().len(), 1);
+        cache.delete_objective(objective_id).unwrap();
+        assert!(cache.get_key_result(key_result_id).is_err());
+        assert_eq!(
+            cache.get_events_by_id::<Event>(event_id).unwrap().get_key_result(),
+            None
+        );
+    }
+
+    #[test]
+    fn quarter_covers_its_three_months() {
+        let quarter = Quarter::new(2026, 4).unwrap();
+        assert_eq!(quarter.start_date(), NaiveDate::from_ymd_opt(2026, 10, 1).unwrap());
+        assert_eq!(quarter.end_date(), NaiveDate::from_ymd_opt(2026, 12, 31).unwrap());
+        assert!(quarter.contains(NaiveDate::from_ymd_opt(2026, 11, 15).unwrap()));
+        assert_eq!(
+            Quarter::from_date(NaiveDate::from_ymd_opt(2026, 2, 28).unwrap()),
+            Quarter::new(2026, 1).unwrap()
+        );
+        for (year, quarter) in [(2026, 0), (2026, 5), (i32::MAX, 1), (-300_000, 2)] {
+            assert!(matches!(
+                Quarter::new(year, quarter).unwrap_err().downcast_ref::<InternalError>(),
+                Some(InternalError::InvalidQuarterError { .. })
+            ));
+        }
+        let stored = serde_json::to_value(quarter).unwrap();
+        assert_eq!(serde_json::from_value::<Quarter>(stored).unwrap(), quarter);
+        let stored = serde_json::json!({ "year": 2026, "quarter": 0 });
+        assert!(serde_json::from_value::<Quarter>(stored).is_err());
+    }
+
+    #[test]
+    fn okr_time_report_splits_planned_and_spent() {
+        let mut cache = Cache::init();
+        let objective = Objective::new("Ship v1", "team", Quarter::new(2026, 3).unwrap());
+        let objective_id = objective.get_id();
+        cache.add_or_update_objectives(vec![objective]);
+        let key_result = KeyResult::new(objective_id, "Close bugs", 0.0, 10.0);
+        let key_result_id = key_result.get_id();
+        cache.add_or_update_key_results(vec![key_result]).unwrap();
+        let day: DateTime<FixedOffset> = DateTime::parse_from_rfc3339("2026-08-03T00:00:00Z").unwrap();
+        let now = day + Duration::hours(12);
+        let mut past = Event::init(None);
+        past.set_duration(day + Duration::hours(9), day + Duration::hours(11));
+        past.set_key_result(Some(key_result_id));
+        let mut ongoing = Event::init(None);
+        ongoing.set_duration(day + Duration::hours(11), day + Duration::hours(14));
+        ongoing.set_key_result(Some(key_result_id));
+        let mut unlinked = Event::init(None);
+        unlinked.set_duration(day + Duration::hours(15), day + Duration::hours(16));
+        cache
+            .insert_events(vec![Box::new(past), Box::new(ongoing), Box::new(unlinked)])
+            .unwrap();
+
+        let report = cache.get_okr_time_report(day, day + Duration::days(1), now);
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].spent_hours(), 3.0);
+        assert_eq!(report[0].planned_hours(), 2.0);
+        assert_eq!(report[0].key_results[0].key_result_id, key_result_id);
+
+        let report = cache.get_okr_time_report(day, now - Duration::hours(2), now);
+        assert_eq!(report[0].spent_hours(), 1.0);
+        assert_eq!(report[0].planned_hours(), 0.0);
+
+        let report = cache.get_quarter_time_report(Quarter::new(2026, 3).unwrap(), now);
+        assert_eq!(report[0].spent_hours(), 3.0);
+        assert!(cache
+            .get_quarter_time_report(Quarter::new(2026, 4).unwrap(), now)
+            .is_empty());
+    }
+
+    #[test]
+    fn tracking_reports_planned_against_actual() {
+        let mut cache = Cache::init();
+        let day: DateTime<FixedOffset> = DateTime::parse_from_rfc3339("2026-08-04T00:00:00Z").unwrap();
+        let mut meeting = Event::init(None);
+        let meeting_id = meeting.get_id();
+        meeting.set_duration(day + Duration::hours(9), day + Duration::hours(10));
+        meeting.set_importance(ImportantLevel::High);
+        cache.insert_events(vec![Box::new(meeting)]).unwrap();
+
+        cache.start_tracking(meeting_id, day + Duration::hours(9)).unwrap();
+        assert!(cache.start_tracking(meeting_id, day + Duration::hours(9)).is_err());
+        cache.stop_tracking(meeting_id, day + Duration::minutes(9 * 60 + 90)).unwrap();
+        assert!(cache.stop_tracking(meeting_id, day + Duration::hours(11)).is_err());
+
+        // ad hoc work inside the meeting does not conflict with it
+        let adhoc_id = cache
+            .start_adhoc_tracking("Hotfix", Category::Other, day + Duration::minutes(9 * 60 + 30))
+            .unwrap();
+        assert_eq!(cache.get_running_tracking(), vec![adhoc_id]);
+        let now = day + Duration::hours(10);
+
+        let report = cache.get_tracking_report(day, day + Duration::days(1), now, TrackingGroup::Day);
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].key, "2026-08-04");
+        assert_eq!(report[0].planned_seconds, 3600);
+        assert_eq!(report[0].actual_seconds, 90 * 60 + 30 * 60);
+        assert_eq!(report[0].unplanned_seconds, 30 * 60);
+        assert_eq!(report[0].difference_seconds(), 3600);
+
+        let report =
+            cache.get_tracking_report(day, day + Duration::days(1), now, TrackingGroup::Category);
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[1].key, "Other");
+        assert_eq!(report[1].planned_seconds, 0);
+        let report =
+            cache.get_tracking_report(day, day + Duration::days(1), now, TrackingGroup::Importance);
+        assert_eq!(report[0].key, "High");
+        assert_eq!(report[0].actual_hours(), 1.5);
+
+        cache.stop_tracking(adhoc_id, now).unwrap();
+        assert!(cache.get_running_tracking().is_empty());
+        let adhoc = cache.get_events_by_id::<Event>(adhoc_id).unwrap();
+        assert_eq!(adhoc.get_start_time(), day + Duration::minutes(9 * 60 + 30));
+        assert_eq!(adhoc.get_end_time(), now);
+        let report = cache.get_tracking_report(day, day + Duration::days(1), now, TrackingGroup::Day);
+        assert_eq!(report[0].planned_seconds, 3600);
+    }
+
+    #[test]
+    fn user_categories_apply_defaults_and_conflict_behavior() {
+        let mut cache = Cache::init();
+        let mut focus = UserCategory::new("Focus", "#0044ff", ImportantLevel::High);
+        focus.conflict = ConflictBehavior::Allow;
+        let mut optional = UserCategory::new("Optional", "#999999", ImportantLevel::Low);
+        optional.conflict = ConflictBehavior::Skip;
+        let (focus_id, optional_id) = (focus.get_id(), optional.get_id());
+        cache.add_or_update_categories(vec![focus.clone(), optional]);
+        assert_eq!(cache.get_category_by_name("Focus").unwrap().get_id(), focus_id);
+
+        let day: DateTime<FixedOffset> = DateTime::parse_from_rfc3339("2026-08-05T00:00:00Z").unwrap();
+        let mut meeting = Event::init(None);
+        meeting.set_duration(day + Duration::hours(9), day + Duration::hours(10));
+        let mut deep_work = Event::init(None);
+        let deep_work_id = deep_work.get_id();
+        deep_work.set_duration(day + Duration::hours(9), day + Duration::hours(11));
+        focus.apply_defaults(&mut deep_work);
+        let mut lunch_talk = Event::init(None);
+        let lunch_talk_id = lunch_talk.get_id();
+        lunch_talk.set_duration(day + Duration::hours(9), day + Duration::hours(10));
+        lunch_talk.set_categories(Category::Custom(optional_id));
+        let skipped = cache
+            .insert_events(vec![Box::new(meeting), Box::new(deep_work), Box::new(lunch_talk)])
+            .unwrap();
+
+        assert_eq!(skipped, vec![lunch_talk_id]);
+        let deep_work = cache.get_events_by_id::<Event>(deep_work_id).unwrap();
+        assert_eq!(deep_work.get_color(), "#0044ff");
+        assert_eq!(deep_work.get_importance().to_string(), "High");
+        assert_eq!(cache.get_category_name(&deep_work.get_categories()), "Focus");
+        // skipped for conflicting with the meeting
+        assert!(cache.get_events_by_id::<Event>(lunch_talk_id).is_err());
+        let mut retry = Event::init(Some(lunch_talk_id));
+        retry.set_duration(day + Duration::hours(9), day + Duration::hours(10));
+        retry.set_categories(Category::Custom(optional_id));
+        assert!(cache.insert_single_event(Box::new(retry)).is_err());
+
+        // a later event may not overlap the meeting, but overlapping deep work is fine
+        let mut call = Event::init(None);
+        call.set_duration(day + Duration::hours(10), day + Duration::hours(11));
+        assert!(cache.insert_events(vec![Box::new(call)]).is_ok());
+        let mut late = Event::init(None);
+        late.set_duration(day + Duration::minutes(9 * 60 + 30), day + Duration::hours(10));
+        assert!(cache.insert_events(vec![Box::new(late)]).is_err());
+
+        cache.delete_category(focus_id).unwrap();
+        assert!(cache.get_category(focus_id).is_err());
+        let deep_work = cache.get_events_by_id::<Event>(deep_work_id).unwrap();
+        assert_eq!(deep_work.get_categories(), Category::Custom(focus_id));
+    }
+
+    #[test]
+    fn unknown_category_values_are_preserved() {
+        assert_eq!(Category::from("Default"), Category::Default);
+        assert_eq!(Category::from("Work").to_string(), "Work");
+        let custom = Category::Custom(42);
+        assert_eq!(Category::from(custom.to_string().as_str()), custom);
+    }
+
+    #[test]
+    fn tags_are_indexed_and_queried_together() {
+        let mut cache = Cache::init();
+        let march: DateTime<FixedOffset> = DateTime::parse_from_rfc3339("2026-03-01T00:00:00Z").unwrap();
+        let april: DateTime<FixedOffset> = DateTime::parse_from_rfc3339("2026-04-01T00:00:00Z").unwrap();
+        let mut call = Event::init(None);
+        let call_id = call.get_id();
+        call.set_duration(march + Duration::hours(9), march + Duration::hours(10));
+        let mut invoice = Reminder::init(None);
+        let invoice_id = invoice.get_id();
+        invoice.set_duration(march + Duration::hours(12), march + Duration::hours(12));
+        let mut later = Event::init(None);
+        let later_id = later.get_id();
+        later.set_duration(april + Duration::hours(9), april + Duration::hours(10));
+        cache
+            .insert_events(vec![Box::new(call), Box::new(invoice), Box::new(later)])
+            .unwrap();
+        cache.set_event_tags(call_id, vec!["#ClientA", "billable"]).unwrap();
+        cache.set_event_tags(invoice_id, vec!["clienta"]).unwrap();
+        cache.add_event_tags(invoice_id, vec!["#billable"]).unwrap();
+        cache.set_event_tags(later_id, vec!["clientA", "billable"]).unwrap();
+
+        let tagged = cache.get_events_by_tags(&["clientA", "#Billable"], Some(march), Some(april));
+        let ids = tagged.iter().map(|e| e.get_id()).collect::<Vec<u128>>();
+        assert_eq!(ids, vec![call_id, invoice_id]);
+        assert_eq!(cache.get_events_by_tags(&["clienta", "billable"], None, None).len(), 3);
+        assert_eq!(cache.get_all_tags(), vec![("billable".to_string(), 3), ("clienta".to_string(), 3)]);
+
+        cache.remove_event_tags(call_id, vec!["BILLABLE"]).unwrap();
+        cache.delete_event(later_id).unwrap();
+        assert_eq!(cache.get_events_by_tags(&["billable"], None, None).len(), 1);
+        assert!(cache.get_events_by_tags(&["unknown"], None, None).is_empty());
+        assert!(cache.set_event_tags(later_id, vec!["x"]).is_err());
+    }
+
+    #[test]
+    fn search_ranks_prefix_matches_across_fields() {
+        let mut cache = Cache::init();
+        let day: DateTime<FixedOffset> = DateTime::parse_from_rfc3339("2026-05-11T00:00:00Z").unwrap();
+        let mut review = Event::init(None);
+        let review_id = review.get_id();
+        review.set_title("Design Review");
+        review.set_duration(day + Duration::hours(9), day + Duration::hours(10));
+        let mut sync = Event::init(None);
+        let sync_id = sync.get_id();
+        sync.set_title("Weekly sync");
+        sync.set_description("prepare the design doc for review");
+        sync.set_duration(day + Duration::hours(11), day + Duration::hours(12));
+        let mut reminder = Reminder::init(None);
+        let reminder_id = reminder.get_id();
+        reminder.set_title("Send designs");
+        reminder.set_duration(day + Duration::days(7), day + Duration::days(7));
+        cache
+            .insert_events(vec![Box::new(review), Box::new(sync), Box::new(reminder)])
+            .unwrap();
+
+        let ids = |cache: &Cache, query: &SearchQuery| {
+            cache.search_events(query).iter().map(|h| h.event.get_id()).collect::<Vec<u128>>()
+        };
+        // a whole word counts more than a prefix, a title more than a description
+        assert_eq!(ids(&cache, &SearchQuery::new("DESIGN")), vec![review_id, reminder_id, sync_id]);
+        assert_eq!(ids(&cache, &SearchQuery::new("des rev")), vec![review_id, sync_id]);
+        assert!(ids(&cache, &SearchQuery::new("design budget")).is_empty());
+        assert!(ids(&cache, &SearchQuery::new("")).is_empty());
+        let query = SearchQuery {
+            kinds: vec![Kind::Reminder],
+            ..SearchQuery::new("design")
+        };
+        assert_eq!(ids(&cache, &query), vec![reminder_id]);
+        let query = SearchQuery {
+            start: Some(day),
+            end: Some(day + Duration::days(1)),
+            limit: Some(1),
+            ..SearchQuery::new("design")
+        };
+        assert_eq!(ids(&cache, &query), vec![review_id]);
+
+        // the index follows updates, deletes and tags
+        cache.set_event_tags(sync_id, vec!["#budget"]).unwrap();
+        assert_eq!(ids(&cache, &SearchQuery::new("design budget")), vec![sync_id]);
+        cache.delete_event(review_id).unwrap();
+        assert_eq!(ids(&cache, &SearchQuery::new("review")), vec![sync_id]);
+    }
+
+    #[test]
+    fn saved_views_filter_like_the_main_calendar() {
+        let mut cache = Cache::init();
+        let now: DateTime<FixedOffset> = DateTime::parse_from_rfc3339("2026-03-18T12:00:00Z").unwrap();
+        let mut this_month = Event::init(None);
+        let this_month_id = this_month.get_id();
+        this_month.set_title("Client call");
+        this_month.set_duration(now, now + Duration::hours(1));
+        let mut next_month = Event::init(None);
+        next_month.set_title("Client workshop");
+        next_month.set_duration(now + Duration::days(20), now + Duration::days(20) + Duration::hours(1));
+        let mut internal = Event::init(None);
+        internal.set_title("Retro");
+        internal.set_duration(now + Duration::hours(2), now + Duration::hours(3));
+        cache
+            .insert_events(vec![Box::new(this_month), Box::new(next_month), Box::new(internal)])
+            .unwrap();
+        cache.set_event_tags(this_month_id, vec!["billable"]).unwrap();
+
+        let billable = SavedView::new("Billable this month", "tag:billable on:this-month").unwrap();
+        let clients = SavedView::new("Clients", "client").unwrap();
+        let clients_id = clients.get_id();
+        cache.add_or_update_views(vec![billable.clone(), clients]).unwrap();
+        assert!(SavedView::new("Broken", "kind:task").is_err());
+        let duplicate = SavedView::new("Clients", "tag:x").unwrap();
+        assert!(cache.add_or_update_views(vec![duplicate]).is_err());
+
+        let ids = |events: Vec<std::sync::Arc<Box<dyn EventCommonTrait>>>| {
+            events.iter().map(|e| e.get_id()).collect::<Vec<u128>>()
+        };
+        let all = parse_query("").unwrap();
+        assert_eq!(ids(cache.query_view(billable.get_id(), &all, now).unwrap()), vec![this_month_id]);
+        // the same view a month later finds nothing
+        let later = now + Duration::days(31);
+        assert!(cache.query_view(billable.get_id(), &all, later).unwrap().is_empty());
+        assert_eq!(cache.query_view(clients_id, &all, now).unwrap().len(), 2);
+        let narrowed = parse_query("workshop").unwrap();
+        assert_eq!(cache.query_view(clients_id, &narrowed, now).unwrap().len(), 1);
+
+        assert_eq!(cache.get_view_by_name("Clients").unwrap().get_id(), clients_id);
+        cache.delete_view(clients_id).unwrap();
+        assert!(cache.query_view(clients_id, &all, now).is_err());
+        assert_eq!(cache.get_all_views().len(), 1);
+    }
+
+    #[test]
+    fn tasks_track_status_and_due_time() {
+        let mut cache = Cache::init();
+        let now: DateTime<FixedOffset> = DateTime::from(Utc::now());
+        let mut overdue = Task::init(None);
+        overdue.set_due_time(Some(now - Duration::hours(1)));
+        let overdue_id = overdue.get_id();
+        let mut no_due = Task::init(None);
+        no_due.add_checklist_item("write tests");
+        let no_due_id = no_due.get_id();
+        cache.add_or_update_tasks(vec![overdue, no_due]);
+        assert_eq!(cache.get_overdue_tasks(now).len(), 1);
+        assert_eq!(cache.get_tasks_by_due_day((now - Duration::hours(1)).naive_utc().date()).len(), 1);
+        assert_eq!(cache.get_tasks_by_status(TaskStatus::Todo).len(), 2);
+
+        cache.set_task_status(overdue_id, TaskStatus::Done).unwrap();
+        let done = cache.get_task(overdue_id).unwrap();
+        assert!(done.get_completed_time().is_some());
+        assert!(cache.get_overdue_tasks(now).is_empty());
+        cache.set_task_status(overdue_id, TaskStatus::InProgress).unwrap();
+        assert!(cache.get_task(overdue_id).unwrap().get_completed_time().is_none());
+
+        // tasks never take part in conflict checking
+        assert!(cache.get_all_raw_events().is_empty());
+        cache.delete_task(no_due_id).unwrap();
+        assert!(cache.get_task(no_due_id).is_err());
+        assert!(cache.delete_task(no_due_id).is_err());
+    }
+
+    #[test]
+    fn due_notifications_respect_fired_snoozed_and_dismissed() {
+        let mut cache = Cache::init();
+        let now: DateTime<FixedOffset> = DateTime::from(Utc::now());
+        let mut due = Reminder::init(None);
+        let due_id = due.get_id();
+        due.set_duration(now - Duration::minutes(5), now - Duration::minutes(4));
+        let mut too_old = Reminder::init(None);
+        too_old.set_duration(now - Duration::hours(3), now - Duration::hours(2));
+        let mut event = Event::init(None);
+        event.set_duration(now - Duration::minutes(3), now - Duration::minutes(2));
+        cache
+            .insert_events(vec![Box::new(due), Box::new(too_old), Box::new(event)])
+            .unwrap();
+        let since = now - Duration::hours(1);
+
+        let notifications = cache.get_due_notifications(since, now);
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].source_id, due_id);
+        cache.mark_notification_fired(due_id, None, now);
+        assert!(cache.get_due_notifications(since, now).is_empty());
+
+        cache.snooze_notification(due_id, None, now + Duration::minutes(10)).unwrap();
+        assert!(cache.get_due_notifications(since, now).is_empty());
+        assert_eq!(
+            cache
+                .get_due_notifications(since, now + Duration::minutes(10))
+                .len(),
+            1
+        );
+        cache.dismiss_notification(due_id, None).unwrap();
+        assert!(cache
+            .get_due_notifications(since, now + Duration::minutes(10))
+            .is_empty());
+        assert!(cache.dismiss_notification(1, None).is_err());
+    }
+
+    #[test]
+    fn due_notifications_include_every_event_alarm() {
+        let mut cache = Cache::init();
+        let now: DateTime<FixedOffset> = DateTime::from(Utc::now());
+        let mut event = Event::init(None);
+        let event_id = event.get_id();
+        event.set_duration(now + Duration::minutes(10), now + Duration::minutes(40));
+        let near = Alarm::before_start(10);
+        let near_id = near.get_id();
+        let mut far = Alarm::before_start(60);
+        far.channel = Some("webhook".to_string());
+        let far_id = far.get_id();
+        event.set_alarms(vec![near, far, Alarm::before_start(5)]);
+        cache.insert_events(vec![Box::new(event)]).unwrap();
+
+        let notifications = cache.get_due_notifications(now - Duration::hours(1), now);
+        assert_eq!(notifications.len(), 2);
+        assert_eq!(notifications[0].alarm_id, Some(far_id));
+        assert_eq!(notifications[0].channel, Some("webhook".to_string()));
+        assert_eq!(notifications[1].alarm_id, Some(near_id));
+
+        cache.mark_notification_fired(event_id, Some(near_id), now);
+        cache.dismiss_notification(event_id, Some(far_id)).unwrap();
+        assert!(cache
+            .get_due_notifications(now - Duration::hours(1), now)
+            .is_empty());
+        assert!(cache.snooze_notification(event_id, Some(1), now).is_err());
+    }
+
+    #[test]
+    fn break_planner_reports_and_fills_free_gaps() {
+        let mut cache = Cache::init();
+        let timezone = FixedOffset::east_opt(8 * 3600).unwrap();
+        let day = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let at = |hour: u32, minute: u32| -> DateTime<FixedOffset> {
+            let time = format!("2026-03-02T{:02}:{:02}:00+08:00", hour, minute);
+            DateTime::parse_from_rfc3339(time.as_str()).unwrap()
+        };
+        let mut events: Vec<Box<dyn EventCommonTrait>> = vec![];
+        let blocks = [(at(9, 0), at(10, 0)), (at(10, 5), at(11, 0)), (at(12, 0), at(13, 30))];
+        for (start, end) in blocks {
+            let mut event = Event::init(None);
+            event.set_duration(start, end);
+            events.push(Box::new(event));
+        }
+        cache.insert_events(events).unwrap();
+        let policy = BreakPolicy::default();
+
+        let report = cache.insert_planned_breaks(day, timezone, &policy).unwrap();
+        assert_eq!(
+            report.violations,
+            vec![BreakViolation::LongWorkStreak {
+                start: at(9, 0),
+                end: at(11, 0)
+            }]
+        );
+        assert_eq!(report.planned.len(), 2);
+        assert_eq!((report.planned[0].start, report.planned[0].end), (at(11, 0), at(11, 10)));
+        assert_eq!((report.planned[1].start, report.planned[1].end), (at(11, 30), at(12, 0)));
+        assert_eq!(cache.get_all_events::<Break>().len(), 2);
+
+        let report = cache.analyse_breaks(day, timezone, &policy);
+        assert!(report.planned.is_empty());
+
+        let policy = BreakPolicy::default()
+            .with_lunch(Some(LunchWindow {
+                start: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+                end: NaiveTime::from_hms_opt(13, 30, 0).unwrap(),
+                minutes: 30,
+            }))
+            .unwrap()
+            .with_meeting_streak(Some(1));
+        let report = cache.analyse_breaks(day, timezone, &policy);
+        assert_eq!(report.violations.len(), 3);
+        assert!(report.violations.contains(&BreakViolation::NoLunchBreak {
+            window_start: at(12, 0),
+            window_end: at(13, 30)
+        }));
+    }
+
+    #[test]
+    fn break_planner_plans_a_break_for_every_interval() {
+        let mut cache = Cache::init();
+        let timezone = FixedOffset::east_opt(0).unwrap();
+        let day = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let at = |hour: u32, minute: u32| -> DateTime<FixedOffset> {
+            let time = format!("2026-03-02T{:02}:{:02}:00+00:00", hour, minute);
+            DateTime::parse_from_rfc3339(time.as_str()).unwrap()
+        };
+        let mut events: Vec<Box<dyn EventCommonTrait>> = vec![];
+        let blocks = [(at(14, 0), at(15, 0)), (at(15, 5), at(16, 0)), (at(16, 5), at(17, 10))];
+        for (start, end) in blocks {
+            let mut event = Event::init(None);
+            event.set_duration(start, end);
+            events.push(Box::new(event));
+        }
+        cache.insert_events(events).unwrap();
+        let policy = BreakPolicy::default().with_lunch(None).unwrap();
+
+        let report = cache.insert_planned_breaks(day, timezone, &policy).unwrap();
+
+        let planned = report
+            .planned
+            .iter()
+            .map(|b| (b.start, b.end))
+            .collect::<Vec<TimeBlock>>();
+        assert_eq!(planned, vec![(at(17, 10), at(17, 20)), (at(17, 20), at(17, 30))]);
+        assert!(cache.analyse_breaks(day, timezone, &policy).planned.is_empty());
+    }
+
+    #[test]
+    fn break_policies_without_length_are_rejected() {
+        for (work, rest) in [(0, 10), (-90, 10), (90, 0), (i64::MAX, 10)] {
+            assert!(matches!(
+                BreakPolicy::new(work, rest).unwrap_err().downcast_ref::<InternalError>(),
+                Some(InternalError::InvalidBreakPolicyError { .. })
+            ));
+        }
+        let lunch = |start: u32, end: u32, minutes: i64| LunchWindow {
+            start: NaiveTime::from_hms_opt(start, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(end, 0, 0).unwrap(),
+            minutes,
+        };
+        let policy = BreakPolicy::new(90, 10).unwrap();
+        assert!(policy.clone().with_lunch(Some(lunch(12, 13, 0))).is_err());
+        assert!(policy.clone().with_lunch(Some(lunch(13, 12, 30))).is_err());
+        assert!(policy.with_lunch(Some(lunch(12, 13, 30))).is_ok());
+    }
+
+    #[test]
+    fn undo_and_redo_restore_whole_steps() {
+        let mut cache = Cache::init();
+        let now = DateTime::from(Utc::now());
+        let instance = GeneratorInstance::new();
+        let instance_id = instance.get_id();
+        let series: Vec<Event> = (0..3)
+            .map(|day| {
+                let mut event = Event::init(None);
+                let start = now + Duration::days(day);
+                event.set_duration(start, start + Duration::hours(1));
+                event.set_generator_instance(instance_id);
+                event
+            })
+            .collect();
+        let ids: Vec<u128> = series.iter().map(|event| event.get_id()).collect();
+        cache.add_or_update_instances(vec![instance]);
+        cache
+            .insert_events(series.into_iter().map(|event| Box::new(event) as _).collect())
+            .unwrap();
+        cache.commit_history_step();
+        for id in &ids {
+            cache.delete_event(*id).unwrap();
+        }
+
+        // the bulk delete comes back as one step
+        assert_eq!(cache.undo().unwrap().summary(), "3 event");
+        assert_eq!(cache.get_all_events::<Event>().len(), 3);
+        assert_eq!(cache.get_events_by_day::<Event>(now.date_naive()).len(), 1);
+        // the series generation is undone together with its instance
+        assert_eq!(cache.undo().unwrap().summary(), "1 instance, 3 event");
+        assert!(cache.get_all_events::<Event>().is_empty());
+        assert!(cache.get_instances(instance_id).is_none());
+        assert!(cache.undo().is_err());
+
+        cache.redo().unwrap();
+        assert_eq!(cache.get_all_events::<Event>().len(), 3);
+        assert!(cache.get_instances(instance_id).is_some());
+        assert_eq!(cache.get_redo_steps().len(), 1);
+        // a new change makes the undone delete stale
+        cache.set_event_tags(ids[0], vec!["series"]).unwrap();
+        assert!(cache.get_redo_steps().is_empty());
+        assert!(cache.redo().is_err());
+        cache.undo().unwrap();
+        assert!(cache.get_events_by_id::<Event>(ids[0]).unwrap().get_tags().is_empty());
+    }
+
+    #[test]
+    fn history_keeps_at_most_limit_steps() {
+        let mut cache = Cache::init();
+        cache.set_history_limit(2);
+        for name in ["a", "b", "c"] {
+            let objective = Objective::new(name, "team", Quarter::new(2026, 3).unwrap());
+            cache.add_or_update_objectives(vec![objective]);
+            cache.commit_history_step();
+        }
+        let steps = cache.get_undo_steps();
+        assert_eq!(steps.len(), 2);
+        cache.undo().unwrap();
+        cache.undo().unwrap();
+        assert!(cache.undo().is_err());
+        assert_eq!(cache.get_all_objectives().len(), 1);
+        assert_eq!(cache.get_all_objectives()[0].title, "a");
+
+        let task = Task::init(None);
+        let task_id = task.get_id();
+        cache.add_or_update_tasks(vec![task]);
+        cache.commit_history_step();
+        cache.set_task_status(task_id, TaskStatus::Done).unwrap();
+        cache.undo().unwrap();
+        assert_eq!(cache.get_task(task_id).unwrap().get_status(), TaskStatus::Todo);
+    }
+
+    #[test]
+    fn audit_log_records_field_changes_per_event() {
+        let mut cache = Cache::init();
+        cache.set_actor("alice");
+        let before = DateTime::from(Utc::now());
+        let mut event = Event::init(None);
+        event.set_title("standup");
+        event.set_duration(before, before + Duration::minutes(15));
+        let id = event.get_id();
+        cache.insert_events(vec![Box::new(event)]).unwrap();
+        let mut renamed = cache.clone_event(id).unwrap();
+        renamed.set_title("daily standup");
+        cache.insert_events(vec![renamed]).unwrap();
+        // storing the same event again changes nothing
+        let unchanged = cache.clone_event(id).unwrap();
+        cache.insert_events(vec![unchanged]).unwrap();
+        cache.commit_history_step();
+        cache.delete_event(id).unwrap();
+
+        let entries = cache.get_event_audit(id);
+        let actions: Vec<AuditAction> = entries.iter().map(|entry| entry.action).collect();
+        assert_eq!(actions, vec![AuditAction::Created, AuditAction::Updated, AuditAction::Deleted]);
+        assert!(entries.iter().all(|entry| entry.actor == "alice"));
+        assert_eq!(
+            entries[1].changes,
+            vec![FieldChange {
+                field: "title".to_string(),
+                old: Some("\"standup\"".to_string()),
+                new: Some("\"daily standup\"".to_string()),
+            }]
+        );
+        assert!(entries[2].changes.iter().all(|change| change.new.is_none()));
+
+        // undo is audited like any other edit
+        cache.undo().unwrap();
+        assert_eq!(cache.get_event_audit(id).last().unwrap().action, AuditAction::Created);
+        let after = DateTime::from(Utc::now()) + Duration::seconds(1);
+        assert_eq!(cache.get_audit_between(before, after).len(), 4);
+        assert!(cache.get_audit_between(after, after + Duration::hours(1)).is_empty());
+    }
+
+    #[test]
+    fn audit_log_keeps_only_the_latest_entries() {
+        let mut cache = Cache::init();
+        let event = Event::init(None);
+        let id = event.get_id();
+        cache.insert_events(vec![Box::new(event)]).unwrap();
+        let entry = cache.get_event_audit(id)[0].clone();
+        let entries = (0..AUDIT_LOG_LIMIT as i64 + 5)
+            .map(|time| AuditEntry { time, ..entry.clone() })
+            .collect();
+
+        cache.restore_audit_log(entries);
+        assert_eq!(cache.get_audit_log().len(), AUDIT_LOG_LIMIT);
+        assert_eq!(cache.get_audit_log()[0].time, 5);
+        cache.set_event_tags(id, vec!["kept"]).unwrap();
+        cache.commit_history_step();
+        assert_eq!(cache.get_audit_log().len(), AUDIT_LOG_LIMIT);
+        assert_eq!(cache.get_event_audit(id).last().unwrap().action, AuditAction::Updated);
+    }
+
+    #[test]
+    fn failed_transactions_roll_back_every_operation() {
+        let mut cache = Cache::init();
+        let now = DateTime::from(Utc::now());
+        let event_at = |hour: i64, title: &str| {
+            let mut event = Event::init(None);
+            event.set_title(title);
+            event.set_duration(now + Duration::hours(hour), now + Duration::hours(hour + 1));
+            event
+        };
+        let kept = event_at(0, "kept");
+        let kept_id = kept.get_id();
+        let removed = event_at(2, "removed");
+        let removed_id = removed.get_id();
+        cache.insert_events(vec![Box::new(kept), Box::new(removed)]).unwrap();
+        cache.commit_history_step();
+        let audited = cache.get_audit_between(now - Duration::hours(1), now + Duration::days(1));
+
+        let mut renamed = cache.clone_event(kept_id).unwrap();
+        renamed.set_title("renamed");
+        let added = event_at(4, "added");
+        let mut transaction = Transaction::new();
+        transaction
+            .update(renamed)
+            .delete(removed_id)
+            .insert(Box::new(added))
+            .insert(Box::new(event_at(4, "overlaps added")));
+        let error = cache.commit_transaction(transaction).unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<InternalError>(),
+            Some(InternalError::ConflictEventError { .. })
+        ));
+        assert!(error.to_string().contains("operation 3"));
+        assert_eq!(cache.get_all_events::<Event>().len(), 2);
+        assert_eq!(cache.get_events_by_id::<Event>(kept_id).unwrap().get_title(), "kept");
+        assert!(cache.get_events_by_id::<Event>(removed_id).is_ok());
+        let after = cache.get_audit_between(now - Duration::hours(1), now + Duration::days(1));
+        assert_eq!(after.len(), audited.len());
+        assert!(cache.redo().is_err());
+        assert_eq!(cache.get_undo_steps().len(), 1);
+
+        // invalid events fail before anything is written
+        let mut backwards = event_at(6, "backwards");
+        backwards.set_duration(now + Duration::hours(7), now + Duration::hours(6));
+        let mut transaction = Transaction::new();
+        transaction.delete(kept_id).insert(Box::new(backwards));
+        assert!(cache.commit_transaction(transaction).is_err());
+        assert!(cache.get_events_by_id::<Event>(kept_id).is_ok());
+
+        let mut transaction = Transaction::new();
+        transaction.delete(removed_id).insert(Box::new(event_at(4, "added")));
+        let result = cache.commit_transaction(transaction).unwrap();
+        assert_eq!(result.deleted, vec![removed_id]);
+        assert_eq!(result.inserted.len(), 1);
+        assert_eq!(cache.get_all_events::<Event>().len(), 2);
+    }
+
+    #[test]
+    fn insert_events_inserts_all_or_nothing() {
+        let mut cache = Cache::init();
+        let now = DateTime::from(Utc::now());
+        let mut first = Event::init(None);
+        first.set_duration(now, now + Duration::hours(1));
+        let mut second = Event::init(None);
+        second.set_duration(now + Duration::hours(2), now + Duration::hours(3));
+        let mut overlapping = Event::init(None);
+        overlapping.set_duration(now, now + Duration::minutes(30));
+        let result =
+            cache.insert_events(vec![Box::new(first), Box::new(second), Box::new(overlapping)]);
+        assert!(result.is_err());
+        assert!(cache.get_all_events::<Event>().is_empty());
+        assert!(cache.get_events_by_day::<Event>(now.date_naive()).is_empty());
+    }
+
+    #[test]
+    fn stale_event_copies_are_rejected() {
+        let mut cache = Cache::init();
+        let now = DateTime::from(Utc::now());
+        let mut event = Event::init(None);
+        event.set_duration(now, now + Duration::hours(1));
+        let id = event.get_id();
+        let cache_revision = cache.get_revision();
+        cache.insert_events(vec![Box::new(event.clone())]).unwrap();
+        assert_eq!(cache.get_events_by_id::<Event>(id).unwrap().get_revision(), 1);
+        assert!(cache.get_revision() > cache_revision);
+
+        let mut first = cache.clone_event(id).unwrap();
+        let mut second = cache.clone_event(id).unwrap();
+        first.set_title("first");
+        second.set_title("second");
+        cache.insert_events(vec![first]).unwrap();
+        let error = cache.insert_events(vec![second]).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<InternalError>(),
+            Some(InternalError::RevisionConflictError { expected: 1, current: 2, .. })
+        ));
+        assert!(cache.check_revision(id, 1).is_err());
+        assert!(cache.check_revision(id, 2).is_ok());
+
+        // revisions keep moving forward through undo
+        cache.commit_history_step();
+        cache.delete_event(id).unwrap();
+        cache.undo().unwrap();
+        assert_eq!(cache.get_events_by_id::<Event>(id).unwrap().get_revision(), 3);
+        // an event never read from the cache does not replace the stored one
+        let error = cache.insert_events(vec![Box::new(event)]).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<InternalError>(),
+            Some(InternalError::RevisionConflictError { expected: 0, current: 3, .. })
+        ));
+        assert_eq!(cache.get_events_by_id::<Event>(id).unwrap().get_revision(), 3);
+    }
+
+    #[test]
+    fn change_log_keeps_cursors_for_resuming() {
+        let mut cache = Cache::init();
+        let now = DateTime::from(Utc::now());
+        let mut event = Event::init(None);
+        event.set_duration(now, now + Duration::hours(1));
+        let id = event.get_id();
+        cache.insert_events(vec![Box::new(event)]).unwrap();
+        cache.set_event_tags(id, vec!["feed"]).unwrap();
+        let cursor = cache.get_change_cursor();
+        let mut overlapping = Event::init(None);
+        overlapping.set_duration(now, now + Duration::hours(1));
+        assert!(cache.insert_events(vec![Box::new(overlapping)]).is_err());
+        // rolled back writes leave no trace
+        assert_eq!(cache.get_change_cursor(), cursor);
+
+        let changes = cache.get_changes_after(0).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!((changes[0].action, changes[0].revision), (AuditAction::Created, 1));
+        assert_eq!((changes[1].action, changes[1].revision), (AuditAction::Updated, 2));
+        assert_eq!(cache.get_changes_after(changes[0].cursor).unwrap(), vec![changes[1].clone()]);
+
+        for _ in 0..CHANGE_LOG_LIMIT {
+            cache.set_event_tags(id, vec!["again"]).unwrap();
+        }
+        assert!(cache.get_changes_after(0).is_err());
+        assert_eq!(cache.get_changes_after(cache.get_change_cursor()).unwrap().len(), 0);
+        // any cursor a client sends is fine, even the largest
+        assert!(cache.get_changes_after(u64::MAX).unwrap().is_empty());
+    }
+
+    #[test]
+    fn undo_keeps_the_step_when_a_restored_event_conflicts() {
+        let mut cache = Cache::init();
+        let day: DateTime<FixedOffset> = DateTime::parse_from_rfc3339("2026-05-01T09:00:00Z").unwrap();
+        let mut first = Event::init(None);
+        first.set_duration(day, day + Duration::hours(1));
+        let mut second = Event::init(None);
+        second.set_duration(day + Duration::days(1), day + Duration::days(1) + Duration::hours(1));
+        let ids = vec![first.get_id(), second.get_id()];
+        cache.insert_events(vec![Box::new(first), Box::new(second)]).unwrap();
+        cache.commit_history_step();
+        for id in &ids {
+            cache.delete_event(*id).unwrap();
+        }
+        cache.commit_history_step();
+        let mut holidays = Subscription::new("holidays", "https://example.com/holidays.ics");
+        holidays.content = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:may-day\r\n\
+            DTSTART;VALUE=DATE:20260501\r\nSUMMARY:May Day\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n"
+            .to_string();
+        holidays.count_conflicts = true;
+        cache.add_subscription(holidays.clone()).unwrap();
+
+        // the second event is restored first, then put back when the first one conflicts
+        let error = cache.undo().unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<InternalError>(),
+            Some(InternalError::ConflictEventError { .. })
+        ));
+        assert!(cache.get_all_events::<Event>().is_empty());
+        assert_eq!(cache.get_undo_steps().len(), 2);
+        assert!(cache.get_redo_steps().is_empty());
+
+        cache.delete_subscription(holidays.get_id()).unwrap();
+        cache.undo().unwrap();
+        assert_eq!(cache.get_all_events::<Event>().len(), 2);
+        assert_eq!(cache.get_undo_steps().len(), 1);
+    }
+
+    #[test]
+    fn feed_events_overlay_queries_without_being_stored() {
+        let mut cache = Cache::init();
+        let mut holidays = Subscription::new("holidays", "https://example.com/holidays.ics");
+        holidays.content = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:may-day\r\n\
+            DTSTART;VALUE=DATE:20260501\r\nSUMMARY:May Day\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n"
+            .to_string();
+        cache.add_subscription(holidays.clone()).unwrap();
+        let day: DateTime<FixedOffset> = DateTime::parse_from_rfc3339("2026-05-01T09:00:00Z").unwrap();
+        let mut event = Event::init(None);
+        event.set_duration(day, day + Duration::hours(1));
+        let own_id = event.get_id();
+        // feeds do not count for conflicts unless asked to
+        cache.insert_events(vec![Box::new(event.clone())]).unwrap();
+
+        let all = parse_query("").unwrap();
+        assert_eq!(cache.query_events(&all).len(), 2);
+        assert_eq!(cache.query_own_events(&all).len(), 1);
+        assert_eq!(cache.get_all_raw_events().len(), 1);
+        let feed_events = cache.query_events(&parse_query("feed:Holidays").unwrap());
+        assert_eq!(feed_events.len(), 1);
+        let feed_id = feed_events[0].get_id();
+        assert_ne!(feed_id, own_id);
+        assert_eq!(cache.get_event_feed(feed_id).unwrap().name, "holidays");
+        assert!(cache.delete_event(feed_id).is_err());
+        assert_eq!(cache.query_events(&parse_query("-feed:*").unwrap()).len(), 1);
+
+        cache.delete_event(own_id).unwrap();
+        holidays.count_conflicts = true;
+        cache.add_subscription(holidays.clone()).unwrap();
+        let error = cache.insert_events(vec![Box::new(event)]).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<InternalError>(),
+            Some(InternalError::ConflictEventError { .. })
+        ));
+
+        // a broken refresh keeps the events read before
+        assert!(cache.refresh_feed(holidays.get_id(), "<html></html>".to_string(), 1).is_err());
+        assert!(cache.get_subscription(holidays.get_id()).unwrap().error.is_some());
+        assert_eq!(cache.get_feed_events().len(), 1);
+        assert!(cache
+            .add_subscription(Subscription::new("holidays", "/tmp/other.ics"))
+            .is_err());
+        cache.delete_subscription(holidays.get_id()).unwrap();
+        assert!(cache.get_feed_events().is_empty());
+    }
+
+    #[test]
+    fn feed_events_are_found_by_id_and_only_checked_on_their_days() {
+        let mut cache = Cache::init();
+        let mut holidays = Subscription::new("holidays", "https://example.com/holidays.ics");
+        holidays.content = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:may-day\r\n\
+            DTSTART;VALUE=DATE:20260501\r\nSUMMARY:May Day\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n"
+            .to_string();
+        holidays.count_conflicts = true;
+        cache.add_subscription(holidays.clone()).unwrap();
+        let feed_id = cache.get_feed_events()[0].get_id();
+        assert_eq!(cache.get_event_feed(feed_id).unwrap().name, "holidays");
+
+        let day = NaiveDate::from_ymd_opt(2026, 5, 1).unwrap();
+        let later_day = NaiveDate::from_ymd_opt(2026, 5, 3).unwrap();
+        assert_eq!(cache.get_conflicting_feed_events(day, day).len(), 1);
+        assert!(cache.get_conflicting_feed_events(later_day, later_day).is_empty());
+
+        // a refresh reading other events drops the ones it no longer holds
+        let moved = holidays.content.replace("may-day", "may-day-moved");
+        cache.refresh_feed(holidays.get_id(), moved, 1).unwrap();
+        assert!(cache.get_event_feed(feed_id).is_none());
+        let moved_id = cache.get_feed_events()[0].get_id();
+        assert_eq!(cache.get_event_feed(moved_id).unwrap().name, "holidays");
+        cache.delete_subscription(holidays.get_id()).unwrap();
+        assert!(cache.get_event_feed(moved_id).is_none());
+    }
+
+    #[test]
+    fn calendars_scope_queries_and_conflicts() {
+        let mut cache = Cache::init();
+        let mut team = Calendar::new("Team", "#00ff00");
+        team.visible = false;
+        team.isolated = true;
+        let personal = Calendar::new("Personal", "#0000ff");
+        cache
+            .add_or_update_calendars(vec![team.clone(), personal.clone()])
+            .unwrap();
+        assert!(cache.add_or_update_calendars(vec![Calendar::new("main", "")]).is_err());
+        assert!(cache.add_or_update_calendars(vec![Calendar::new("team", "")]).is_err());
+        let day: DateTime<FixedOffset> = DateTime::parse_from_rfc3339("2026-04-06T09:00:00Z").unwrap();
+        let mut standup = Event::init(None);
+        standup.set_duration(day, day + Duration::hours(1));
+        standup.set_calendar(Some(team.get_id()));
+        let standup_id = standup.get_id();
+        let mut dentist = Event::init(None);
+        dentist.set_duration(day, day + Duration::hours(1));
+        dentist.set_calendar(Some(personal.get_id()));
+        let dentist_id = dentist.get_id();
+        // the team calendar is isolated, so overlapping it is no conflict
+        cache
+            .insert_events(vec![Box::new(standup), Box::new(dentist)])
+            .unwrap();
+        assert_eq!(cache.get_events_by_id::<Event>(standup_id).unwrap().get_color(), "#00ff00");
+        let mut planning = Event::init(None);
+        planning.set_duration(day, day + Duration::minutes(30));
+        assert!(cache.insert_events(vec![Box::new(planning.clone())]).is_err());
+        planning.set_calendar(Some(team.get_id()));
+        assert!(cache.insert_events(vec![Box::new(planning)]).is_err());
+
+        let ids = |cache: &Cache, query: &str| {
+            cache
+                .query_events(&parse_query(query).unwrap())
+                .iter()
+                .map(|e| e.get_id())
+                .collect::<Vec<u128>>()
+        };
+        // hidden calendars only show when named
+        assert_eq!(ids(&cache, ""), vec![dentist_id]);
+        assert_eq!(ids(&cache, "calendar:team"), vec![standup_id]);
+        assert_eq!(ids(&cache, "calendar:personal OR calendar:team").len(), 2);
+        assert!(ids(&cache, "calendar:main").is_empty());
+        assert_eq!(cache.query_own_events(&parse_query("").unwrap()).len(), 2);
+
+        cache.set_event_calendar(dentist_id, None).unwrap();
+        assert_eq!(ids(&cache, "calendar:main"), vec![dentist_id]);
+        assert!(cache.set_event_calendar(dentist_id, Some(1)).is_err());
+        cache.commit_history_step();
+        cache.delete_calendar(team.get_id()).unwrap();
+        assert_eq!(ids(&cache, "").len(), 2);
+        cache.undo().unwrap();
+        assert_eq!(cache.get_calendar_by_name("team").unwrap().get_id(), team.get_id());
+    }
+
+    #[test]
+    fn series_alarms_fire_once_per_occurrence() {
+        let mut cache = Cache::init();
+        let start: DateTime<FixedOffset> =
+            DateTime::parse_from_rfc3339("2030-03-04T09:00:00Z").unwrap();
+        let mut event = Event::init(None);
+        let id = event.get_id();
+        event.set_duration(start, start + Duration::hours(1));
+        event.add_alarm(Alarm::before_start(10));
+        event.add_alarm(Alarm::at(start - Duration::hours(3)));
+        cache.insert_events(vec![Box::new(event)]).unwrap();
+        let strategy = RepeatStrategy {
+            repeat_gap_day: 7,
+            repeat_gap_month: 0,
+            repeat_gap_year: 0,
+            skip_weekday: false,
+            skip_weekend: false,
+            skip_holiday: false,
+            start_day: 0,
+            until_day: (start + Duration::days(15)).timestamp_millis(),
+        };
+
+        let occurrences = cache.repeat_event(id, strategy.clone(), true).unwrap();
+
+        assert_eq!(occurrences.len(), 2);
+        let series = cache.get_events_by_id::<Event>(id).unwrap().get_generator_instance();
+        let queue = cache.get_instances(series.unwrap()).unwrap().repeat.unwrap().event_queue;
+        assert_eq!(queue, vec![id, occurrences[0], occurrences[1]]);
+        for (week, source_id) in [id, occurrences[0], occurrences[1]].into_iter().enumerate() {
+            let fixed = start + Duration::days(7 * week as i64) - Duration::hours(3);
+            let due = cache.get_due_notifications(fixed, fixed + Duration::minutes(1));
+            assert_eq!(due.iter().map(|n| n.source_id).collect::<Vec<u128>>(), vec![source_id]);
+            let before = fixed + Duration::minutes(170);
+            let due = cache.get_due_notifications(before, before + Duration::minutes(1));
+            assert_eq!(due.iter().map(|n| n.source_id).collect::<Vec<u128>>(), vec![source_id]);
+        }
+        assert!(cache.repeat_event(occurrences[0], strategy.clone(), true).is_err());
+        let mut empty = strategy;
+        empty.repeat_gap_day = 0;
+        assert!(cache.repeat_event(id, empty, true).is_err());
+    }
 }
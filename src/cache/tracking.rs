@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+
+use anyhow::bail;
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+
+use crate::cache::Cache;
+use crate::common::exception::InternalError;
+use crate::model::{Category, EventCommonTrait, ItemCommonTrait};
+use crate::model::event::Event;
+use crate::model::tracking::{TrackingGroup, TrackingReport};
+
+impl Cache {
+    // restarting a stopped event replaces its previous actual times
+    pub fn start_tracking(&mut self, event_id: u128, now: DateTime<FixedOffset>) -> Result<()> {
+        let mut event = (**self.get_events_by_id::<Event>(event_id)?).clone();
+        if event.get_actual_start_time().is_some() && event.get_actual_end_time().is_none() {
+            bail!(InternalError::TrackingAlreadyStartedError { event_id });
+        }
+        event.set_actual_duration(Some(now), None);
+        self.insert_single_event(Box::new(event))
+    }
+
+    pub fn stop_tracking(&mut self, event_id: u128, now: DateTime<FixedOffset>) -> Result<()> {
+        let mut event = (**self.get_events_by_id::<Event>(event_id)?).clone();
+        match (event.get_actual_start_time(), event.get_actual_end_time()) {
+            (Some(start), None) => {
+                let end = now.max(start);
+                event.set_actual_duration(Some(start), Some(end));
+                // ad hoc work is shown where it was done
+                if event.is_unplanned() {
+                    event.set_duration(start, end);
+                }
+            }
+            _ => bail!(InternalError::TrackingNotStartedError { event_id }),
+        }
+        self.insert_single_event(Box::new(event))
+    }
+
+    // ad hoc work has no planned window, it is marked unplanned so it never conflicts
+    pub fn start_adhoc_tracking(
+        &mut self,
+        title: &str,
+        category: Category,
+        now: DateTime<FixedOffset>,
+    ) -> Result<u128> {
+        let mut event = Event::init(None);
+        let event_id = event.get_id();
+        event.set_title(title);
+        event.set_categories(category);
+        event.set_unplanned(true);
+        event.set_duration(now, now);
+        event.set_actual_duration(Some(now), None);
+        self.insert_single_event(Box::new(event))?;
+        Ok(event_id)
+    }
+
+    pub fn get_running_tracking(&self) -> Vec<u128> {
+        self.get_all_events::<Event>()
+            .iter()
+            .filter(|e| e.get_actual_start_time().is_some() && e.get_actual_end_time().is_none())
+            .map(|e| e.get_id())
+            .collect()
+    }
+
+    // planned and actual time within [start, end), running tracking counts until now,
+    // days are local dates in the timezone of start
+    pub fn get_tracking_report(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        now: DateTime<FixedOffset>,
+        group: TrackingGroup,
+    ) -> Vec<TrackingReport> {
+        let mut reports: BTreeMap<String, TrackingReport> = BTreeMap::new();
+        for event in self.get_all_events::<Event>() {
+            let actual = event
+                .get_actual_start_time()
+                .map(|actual_start| (actual_start, event.get_actual_end_time().unwrap_or(now)));
+            let planned_seconds = if event.is_unplanned() {
+                0
+            } else {
+                seconds_within(event.get_start_time(), event.get_end_time(), start, end)
+            };
+            let actual_seconds = actual.map_or(0, |(s, e)| seconds_within(s, e, start, end));
+            if planned_seconds == 0 && actual_seconds == 0 {
+                continue;
+            }
+            let key = match group {
+                TrackingGroup::Day => {
+                    let anchor = actual
+                        .filter(|_| event.is_unplanned())
+                        .map_or(event.get_start_time(), |(s, _)| s);
+                    anchor
+                        .max(start)
+                        .with_timezone(&start.timezone())
+                        .date_naive()
+                        .to_string()
+                }
+                TrackingGroup::Category => self.get_category_name(&event.get_categories()),
+                TrackingGroup::Importance => event.get_importance().to_string(),
+            };
+            let report = reports.entry(key.clone()).or_insert(TrackingReport {
+                key,
+                ..Default::default()
+            });
+            report.planned_seconds += planned_seconds;
+            report.actual_seconds += actual_seconds;
+            if event.is_unplanned() {
+                report.unplanned_seconds += actual_seconds;
+            }
+        }
+        reports.into_values().collect()
+    }
+}
+
+fn seconds_within(
+    from: DateTime<FixedOffset>,
+    to: DateTime<FixedOffset>,
+    start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>,
+) -> i64 {
+    to.min(end)
+        .signed_duration_since(from.max(start))
+        .num_seconds()
+        .max(0)
+}
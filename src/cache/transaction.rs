@@ -0,0 +1,82 @@
+use anyhow::{anyhow, bail};
+use anyhow::Context;
+use anyhow::Result;
+
+use crate::cache::Cache;
+use crate::cache::history::rollback_error;
+use crate::common::exception::InternalError;
+use crate::model::transaction::{Operation, Transaction, TransactionResult};
+
+impl Cache {
+    // runs the writes and puts back everything they changed when they fail,
+    // using the changes the history recorded meanwhile
+    pub(crate) fn atomically<T>(
+        &mut self,
+        writes: impl FnOnce(&mut Cache) -> Result<T>,
+    ) -> Result<T> {
+        let recorded = self.history.pending.len();
+        let audited = self.audit_log.len();
+        let cursor = self.change_cursor;
+        let result = writes(self);
+        if let Err(error) = result {
+            let changes = self.history.pending.split_off(recorded);
+            let rolled_back = self.put_back(changes.iter().rev(), true);
+            self.audit_log.truncate(audited);
+            self.forget_changes_after(cursor);
+            return Err(rollback_error(error, rolled_back));
+        }
+        result
+    }
+
+    // every event is validated before anything is written, conflicts are found while
+    // writing in order, so staged events also conflict with each other
+    pub fn commit_transaction(&mut self, transaction: Transaction) -> Result<TransactionResult> {
+        let operations = transaction.into_operations();
+        for (index, operation) in operations.iter().enumerate() {
+            if let Operation::Insert(event) | Operation::Update(event) = operation {
+                if !event.check_valid() {
+                    return Err(anyhow!(InternalError::InvalidStartEndTimeError {
+                        start_time: event.get_start_time(),
+                        end_time: event.get_end_time(),
+                    }))
+                    .with_context(|| format!("operation {} of the transaction", index));
+                }
+            }
+        }
+        self.atomically(|cache| {
+            let mut result = TransactionResult::default();
+            for (index, operation) in operations.into_iter().enumerate() {
+                cache
+                    .apply_operation(operation, &mut result)
+                    .with_context(|| format!("operation {} of the transaction", index))?;
+            }
+            Ok(result)
+        })
+    }
+
+    fn apply_operation(&mut self, operation: Operation, result: &mut TransactionResult) -> Result<()> {
+        match operation {
+            Operation::Insert(event) => {
+                let id = event.get_id();
+                if self.events_by_id.contains_key(&id) {
+                    bail!(InternalError::EventsAlreadyExistError { event_id: id })
+                }
+                self.insert_event(event)?;
+                result.inserted.push(id);
+            }
+            Operation::Update(event) => {
+                let id = event.get_id();
+                if !self.events_by_id.contains_key(&id) {
+                    bail!(InternalError::EventNotFoundError)
+                }
+                self.insert_event(event)?;
+                result.updated.push(id);
+            }
+            Operation::Delete(id) => {
+                self.delete_event(id)?;
+                result.deleted.push(id);
+            }
+        }
+        Ok(())
+    }
+}
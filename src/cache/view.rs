@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::bail;
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+
+use crate::cache::Cache;
+use crate::common::exception::InternalError;
+use crate::model::EventCommonTrait;
+use crate::model::history::Change;
+use crate::model::view::SavedView;
+use crate::query::{Filter, parse_query_at};
+
+impl Cache {
+    pub fn add_or_update_views(&mut self, views: Vec<SavedView>) -> Result<()> {
+        for view in &views {
+            let duplicated = self
+                .views
+                .values()
+                .any(|v| v.name == view.name && v.get_id() != view.get_id());
+            if duplicated {
+                bail!(InternalError::ViewAlreadyExistError {
+                    name: view.name.clone()
+                });
+            }
+        }
+        for view in views {
+            let id = view.get_id();
+            let before = self.views.insert(id, view.clone());
+            self.record_change(Change::View {
+                id,
+                before,
+                after: Some(view),
+            });
+        }
+        self.touch();
+        Ok(())
+    }
+
+    pub fn get_view(&self, id: u128) -> Result<SavedView> {
+        match self.views.get(&id) {
+            Some(view) => Ok(view.clone()),
+            None => bail!(InternalError::ViewNotFoundError),
+        }
+    }
+
+    pub fn get_view_by_name(&self, name: &str) -> Result<SavedView> {
+        match self.views.values().find(|v| v.name == name) {
+            Some(view) => Ok(view.clone()),
+            None => bail!(InternalError::ViewNotFoundError),
+        }
+    }
+
+    pub fn get_all_views(&self) -> Vec<SavedView> {
+        let mut views = self.views.values().cloned().collect::<Vec<SavedView>>();
+        views.sort_by(|a, b| a.name.cmp(&b.name));
+        views
+    }
+
+    pub fn delete_view(&mut self, id: u128) -> Result<()> {
+        let before = match self.views.remove(&id) {
+            Some(view) => view,
+            None => bail!(InternalError::ViewNotFoundError),
+        };
+        self.record_change(Change::View {
+            id,
+            before: Some(before),
+            after: None,
+        });
+        self.touch();
+        Ok(())
+    }
+
+    // whether a stream of the view, or of the whole calendar without one, carries the
+    // event right now. a missing event is not carried, nor is one of a hidden or isolated
+    // calendar the view does not name
+    pub fn stream_contains(
+        &self,
+        view: Option<u128>,
+        event_id: u128,
+        now: DateTime<FixedOffset>,
+    ) -> Result<bool> {
+        let filter = self.stream_filter(view, now)?;
+        Ok(self.events_by_id.get(&event_id).is_some_and(|event| {
+            self.matches(&filter, event.as_ref().as_ref())
+                && self.is_streamed(event.as_ref().as_ref(), &filter.calendar_names())
+        }))
+    }
+
+    // ids of the events a stream of the view carries right now
+    pub fn stream_events(
+        &self,
+        view: Option<u128>,
+        now: DateTime<FixedOffset>,
+    ) -> Result<HashSet<u128>> {
+        let filter = self.stream_filter(view, now)?;
+        let named = filter.calendar_names();
+        Ok(self
+            .events_all
+            .iter()
+            .filter(|event| {
+                self.matches(&filter, event.as_ref().as_ref())
+                    && self.is_streamed(event.as_ref().as_ref(), &named)
+            })
+            .map(|event| event.get_id())
+            .collect())
+    }
+
+    fn stream_filter(&self, view: Option<u128>, now: DateTime<FixedOffset>) -> Result<Filter> {
+        match view {
+            Some(id) => parse_query_at(self.get_view(id)?.query.as_str(), now),
+            None => Ok(Filter::All),
+        }
+    }
+
+    // events of the view narrowed by another filter, as the main calendar is queried
+    pub fn query_view(
+        &self,
+        id: u128,
+        filter: &Filter,
+        now: DateTime<FixedOffset>,
+    ) -> Result<Vec<Arc<Box<dyn EventCommonTrait>>>> {
+        let view = self.get_view(id)?;
+        let view_filter = parse_query_at(view.query.as_str(), now)?;
+        Ok(self.query_events(&Filter::And(vec![view_filter, filter.clone()])))
+    }
+}
@@ -0,0 +1,442 @@
+use std::sync::Arc;
+
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web::http::header::{ETAG, HeaderName, IF_NONE_MATCH, LOCATION};
+use actix_web::http::{Method, StatusCode};
+use anyhow::bail;
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+
+use crate::api::{error_response, etag, expected_revision};
+use crate::common::exception::InternalError;
+use crate::common::xml::{attribute, elements, escape_xml, requested_props};
+use crate::core::event::{delete_event, get_revision, import_event};
+use crate::core::query::{get_shown_event, query_shown_events};
+use crate::ics::{export_calendar, format_uid, parse_calendar, parse_datetime, parse_uid};
+use crate::model::EventCommonTrait;
+use crate::model::user::UserContext;
+
+mod test;
+
+pub const ROOT_PATH: &str = "/dav/";
+pub const CALENDAR_PATH: &str = "/dav/calendar/";
+
+const CALENDAR_NAME: &str = "break-calendar";
+const DAV_NAMESPACE: &str = "DAV:";
+const CALDAV_NAMESPACE: &str = "urn:ietf:params:xml:ns:caldav";
+const CALENDAR_SERVER_NAMESPACE: &str = "http://calendarserver.org/ns/";
+const DAV_HEADER: &str = "1, 3, calendar-access";
+const ALLOW_HEADER: &str = "OPTIONS, GET, PUT, DELETE, PROPFIND, REPORT";
+
+// the root is both the principal and its calendar home, holding the one calendar of the
+// user the proxy forwards. events of hidden calendars are not served
+pub fn configure(config: &mut web::ServiceConfig) {
+    config
+        .route("/.well-known/caldav", web::to(well_known))
+        .service(
+            web::resource(ROOT_PATH)
+                .route(web::method(propfind()).to(propfind_root))
+                .route(web::method(Method::OPTIONS).to(options)),
+        )
+        .service(
+            web::resource(CALENDAR_PATH)
+                .route(web::method(propfind()).to(propfind_calendar))
+                .route(web::method(report()).to(report_calendar))
+                .route(web::method(Method::OPTIONS).to(options)),
+        )
+        .service(
+            web::resource(format!("{}{{object}}", CALENDAR_PATH))
+                .route(web::get().to(get_object))
+                .route(web::put().to(put_object))
+                .route(web::delete().to(delete_object))
+                .route(web::method(propfind()).to(propfind_object))
+                .route(web::method(Method::OPTIONS).to(options)),
+        );
+}
+
+fn propfind() -> Method {
+    Method::from_bytes(b"PROPFIND").unwrap()
+}
+
+fn report() -> Method {
+    Method::from_bytes(b"REPORT").unwrap()
+}
+
+async fn well_known() -> HttpResponse {
+    HttpResponse::MovedPermanently()
+        .insert_header((LOCATION, ROOT_PATH))
+        .finish()
+}
+
+async fn options() -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(("DAV", DAV_HEADER))
+        .insert_header(("Allow", ALLOW_HEADER))
+        .finish()
+}
+
+async fn propfind_root(user: UserContext, request: HttpRequest, body: String) -> HttpResponse {
+    let requested = requested_props(body.as_str());
+    let props = vec![
+        ("d:resourcetype", "<d:collection/><d:principal/>".to_string()),
+        ("d:displayname", CALENDAR_NAME.to_string()),
+        ("d:current-user-principal", format!("<d:href>{}</d:href>", ROOT_PATH)),
+        ("c:calendar-home-set", format!("<d:href>{}</d:href>", ROOT_PATH)),
+    ];
+    let mut responses = vec![dav_response(ROOT_PATH, props, requested.as_deref())];
+    if depth(&request) > 0 {
+        match calendar_response(&user, requested.as_deref()).await {
+            Ok(response) => responses.push(response),
+            Err(error) => return dav_error(error),
+        }
+    }
+    multistatus(responses)
+}
+
+async fn propfind_calendar(user: UserContext, request: HttpRequest, body: String) -> HttpResponse {
+    let requested = requested_props(body.as_str());
+    let responses = async {
+        let mut responses = vec![calendar_response(&user, requested.as_deref()).await?];
+        if depth(&request) > 0 {
+            for event in query_shown_events(&user, "").await? {
+                responses.push(object_response(&event, requested.as_deref()));
+            }
+        }
+        Ok(responses)
+    }
+    .await;
+    match responses {
+        Ok(responses) => multistatus(responses),
+        Err(error) => dav_error(error),
+    }
+}
+
+async fn propfind_object(user: UserContext, path: web::Path<String>, body: String) -> HttpResponse {
+    let requested = requested_props(body.as_str());
+    match get_shown_event(&user, object_id(path.as_str())).await {
+        Ok(event) => multistatus(vec![object_response(&event, requested.as_deref())]),
+        Err(error) => dav_error(error),
+    }
+}
+
+// calendar-multiget by href or calendar-query, narrowed by a time-range filter only
+async fn report_calendar(user: UserContext, body: String) -> HttpResponse {
+    let requested = requested_props(body.as_str());
+    let responses = async {
+        if !elements(body.as_str(), "calendar-multiget").is_empty() {
+            let events = query_shown_events(&user, "").await?;
+            let responses = elements(body.as_str(), "href")
+                .iter()
+                .map(|(_, href)| {
+                    let href = href.trim();
+                    let name = decode_name(href.rsplit('/').next().unwrap_or_default());
+                    let id = object_id(name.as_str());
+                    match events.iter().find(|event| event.get_id() == id) {
+                        Some(event) => object_response(event, requested.as_deref()),
+                        None => missing_response(href),
+                    }
+                })
+                .collect::<Vec<String>>();
+            return Ok(responses);
+        }
+        if elements(body.as_str(), "calendar-query").is_empty() {
+            bail!(InternalError::InvalidIcsError {
+                reason: "only calendar-query and calendar-multiget reports are supported"
+                    .to_string()
+            })
+        }
+        // only events are stored, a query for todos or journals finds nothing
+        let components = elements(body.as_str(), "comp-filter")
+            .iter()
+            .filter_map(|(attributes, _)| attribute(attributes, "name"))
+            .collect::<Vec<String>>();
+        if components.iter().any(|name| name != "VCALENDAR" && name != "VEVENT") {
+            return Ok(vec![]);
+        }
+        let (start, end) = match elements(body.as_str(), "time-range").first() {
+            Some((attributes, _)) => (
+                attribute(attributes, "start").map(|t| parse_datetime(t.as_str())).transpose()?,
+                attribute(attributes, "end").map(|t| parse_datetime(t.as_str())).transpose()?,
+            ),
+            None => (None, None),
+        };
+        Ok(query_shown_events(&user, "")
+            .await?
+            .iter()
+            .filter(|event| overlaps(event.as_ref().as_ref(), start, end))
+            .map(|event| object_response(event, requested.as_deref()))
+            .collect())
+    }
+    .await;
+    match responses {
+        Ok(responses) => multistatus(responses),
+        Err(error) => dav_error(error),
+    }
+}
+
+async fn get_object(user: UserContext, path: web::Path<String>) -> HttpResponse {
+    match get_shown_event(&user, object_id(path.as_str())).await {
+        Ok(event) => HttpResponse::Ok()
+            .insert_header((ETAG, etag(event.get_revision())))
+            .content_type("text/calendar; charset=utf-8")
+            .body(export_calendar(&[event])),
+        Err(error) => dav_error(error),
+    }
+}
+
+// the resource name decides the id, so a client keeps finding the event where it put
+// it. the name and the UID are served back as the client gave them, If-None-Match: *
+// only creates and If-Match only replaces that revision
+async fn put_object(
+    user: UserContext,
+    request: HttpRequest,
+    path: web::Path<String>,
+    body: String,
+) -> HttpResponse {
+    let imported = async {
+        let mut events = parse_calendar(body.as_str())?;
+        if events.len() != 1 {
+            bail!(InternalError::InvalidIcsError {
+                reason: format!("expected one event, found {}", events.len())
+            })
+        }
+        let mut event = events.remove(0);
+        event.id = object_id(path.as_str());
+        event.href = Some(path.into_inner());
+        let create_only = request
+            .headers()
+            .get(IF_NONE_MATCH)
+            .is_some_and(|value| value == "*");
+        import_event(&user, event, expected_revision(&request)?, create_only).await
+    }
+    .await;
+    match imported {
+        Ok((event, created)) => {
+            let status = if created { StatusCode::CREATED } else { StatusCode::NO_CONTENT };
+            HttpResponse::build(status)
+                .insert_header((ETAG, etag(event.revision)))
+                .finish()
+        }
+        Err(error) => dav_error(error),
+    }
+}
+
+async fn delete_object(
+    user: UserContext,
+    request: HttpRequest,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let deleted = async {
+        delete_event(&user, object_id(path.as_str()), expected_revision(&request)?).await
+    }
+    .await;
+    match deleted {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(error) => dav_error(error),
+    }
+}
+
+// the ctag changes with every write, clients only look at the objects when it did
+async fn calendar_response(
+    user: &UserContext,
+    requested: Option<&[(String, String)]>,
+) -> Result<String> {
+    let revision = get_revision(user).await?;
+    let props = vec![
+        ("d:resourcetype", "<d:collection/><c:calendar/>".to_string()),
+        ("d:displayname", CALENDAR_NAME.to_string()),
+        ("cs:getctag", escape_xml(etag(revision).as_str())),
+        ("c:supported-calendar-component-set", "<c:comp name=\"VEVENT\"/>".to_string()),
+    ];
+    Ok(dav_response(CALENDAR_PATH, props, requested))
+}
+
+// the data is only sent when asked for by name
+fn object_response(
+    event: &Arc<Box<dyn EventCommonTrait>>,
+    requested: Option<&[(String, String)]>,
+) -> String {
+    let mut props = vec![
+        ("d:resourcetype", String::new()),
+        ("d:getetag", escape_xml(etag(event.get_revision()).as_str())),
+        ("d:getcontenttype", "text/calendar; charset=utf-8; component=VEVENT".to_string()),
+    ];
+    let with_data = requested.is_some_and(|requested| {
+        requested.iter().any(|(namespace, name)| {
+            namespace == CALDAV_NAMESPACE && name == "calendar-data"
+        })
+    });
+    if with_data {
+        let calendar = export_calendar(std::slice::from_ref(event));
+        props.push(("c:calendar-data", escape_xml(&calendar)));
+    }
+    dav_response(event_href(event.as_ref().as_ref()).as_str(), props, requested)
+}
+
+pub fn object_href(id: u128) -> String {
+    format!("{}{}.ics", CALENDAR_PATH, format_uid(id))
+}
+
+// where the client put the event, or where it is found by its id
+fn event_href(event: &dyn EventCommonTrait) -> String {
+    match event.get_href() {
+        Some(name) => format!("{}{}", CALENDAR_PATH, encode_name(name)),
+        None => object_href(event.get_id()),
+    }
+}
+
+// a resource name as a path segment, e.g. with a space as %20
+fn encode_name(name: &str) -> String {
+    name.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => (byte as char).to_string(),
+            b'-' | b'.' | b'_' | b'~' | b'@' | b'+' | b'!' | b'$' | b'\'' | b'(' | b')' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+// a path segment back as the resource name, as paths are matched
+fn decode_name(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut name = vec![];
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = segment
+            .get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                name.push(byte);
+                index += 3;
+            }
+            None => {
+                name.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&name).to_string()
+}
+
+fn object_id(name: &str) -> u128 {
+    parse_uid(name.strip_suffix(".ics").unwrap_or(name))
+}
+
+// events without a length count when they start in the range
+fn overlaps(
+    event: &dyn EventCommonTrait,
+    start: Option<DateTime<FixedOffset>>,
+    end: Option<DateTime<FixedOffset>>,
+) -> bool {
+    let after_start = start.is_none_or(|start| {
+        event.get_end_time() > start || event.get_start_time() >= start
+    });
+    let before_end = end.is_none_or(|end| event.get_start_time() < end);
+    after_start && before_end
+}
+
+// infinity is answered like 1, there is nothing deeper
+fn depth(request: &HttpRequest) -> u8 {
+    match request.headers().get(HeaderName::from_static("depth")) {
+        Some(value) if value == "0" => 0,
+        _ => 1,
+    }
+}
+
+// the requested props, all of them without a request. the ones not known here are
+// answered as not found
+fn dav_response(
+    href: &str,
+    props: Vec<(&str, String)>,
+    requested: Option<&[(String, String)]>,
+) -> String {
+    let qualify = |prop: &str| {
+        let (prefix, name) = prop.split_once(':').unwrap_or(("d", prop));
+        let namespace = match prefix {
+            "c" => CALDAV_NAMESPACE,
+            "cs" => CALENDAR_SERVER_NAMESPACE,
+            _ => DAV_NAMESPACE,
+        };
+        (namespace.to_string(), name.to_string())
+    };
+    let (found, missing) = match requested {
+        None => (props, vec![]),
+        Some(requested) => {
+            let found = props
+                .into_iter()
+                .filter(|(prop, _)| requested.contains(&qualify(prop)))
+                .collect::<Vec<(&str, String)>>();
+            let missing = requested
+                .iter()
+                .filter(|prop| !found.iter().any(|(known, _)| qualify(known) == **prop))
+                .cloned()
+                .collect::<Vec<(String, String)>>();
+            (found, missing)
+        }
+    };
+    let mut response = format!("<d:response><d:href>{}</d:href>", escape_xml(href));
+    if !found.is_empty() || missing.is_empty() {
+        let found = found
+            .iter()
+            .map(|(prop, value)| match value.is_empty() {
+                true => format!("<{}/>", prop),
+                false => format!("<{}>{}</{}>", prop, value, prop),
+            })
+            .collect::<String>();
+        response.push_str(
+            format!(
+                "<d:propstat><d:prop>{}</d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat>",
+                found
+            )
+            .as_str(),
+        );
+    }
+    if !missing.is_empty() {
+        let missing = missing
+            .iter()
+            .map(|(namespace, name)| format!("<x:{} xmlns:x=\"{}\"/>", name, escape_xml(namespace)))
+            .collect::<String>();
+        response.push_str(
+            format!(
+                "<d:propstat><d:prop>{}</d:prop>\
+                 <d:status>HTTP/1.1 404 Not Found</d:status></d:propstat>",
+                missing
+            )
+            .as_str(),
+        );
+    }
+    response.push_str("</d:response>");
+    response
+}
+
+fn missing_response(href: &str) -> String {
+    format!(
+        "<d:response><d:href>{}</d:href><d:status>HTTP/1.1 404 Not Found</d:status></d:response>",
+        escape_xml(href)
+    )
+}
+
+fn multistatus(responses: Vec<String>) -> HttpResponse {
+    HttpResponse::build(StatusCode::MULTI_STATUS)
+        .content_type("application/xml; charset=utf-8")
+        .body(format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+             <d:multistatus xmlns:d=\"DAV:\" xmlns:c=\"urn:ietf:params:xml:ns:caldav\" \
+             xmlns:cs=\"http://calendarserver.org/ns/\">{}</d:multistatus>",
+            responses.concat()
+        ))
+}
+
+// a failed If-None-Match is a failed precondition like a failed If-Match
+fn dav_error(error: anyhow::Error) -> HttpResponse {
+    match error.downcast_ref::<InternalError>() {
+        Some(InternalError::EventsAlreadyExistError { .. }) => {
+            HttpResponse::PreconditionFailed().finish()
+        }
+        _ => error_response(error),
+    }
+}
@@ -0,0 +1,320 @@
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use actix_web::{App, HttpServer, test, web};
+    use actix_web::dev::Service;
+    use actix_web::http::header::{ETAG, HeaderName, HeaderValue, IF_MATCH, IF_NONE_MATCH};
+    use actix_web::http::{Method, StatusCode};
+    use uuid::Uuid;
+
+    use crate::api::TrustedProxies;
+    use crate::caldav::{configure, object_href};
+    use crate::core::calendar::save_calendar;
+    use crate::core::event::{get_event, update_event};
+    use crate::core::processor::{dynamic_process, static_process};
+    use crate::core::sync::{SyncSettings, sync_calendar};
+    use crate::ics::parse_uid;
+    use crate::model::calendar::Calendar;
+    use crate::model::event::Event;
+    use crate::model::{EventCommonTrait, ItemCommonTrait};
+    use crate::model::user::UserContext;
+
+    fn event(uid: &str, summary: &str) -> String {
+        event_on(uid, summary, "20310714")
+    }
+
+    fn event_on(uid: &str, summary: &str, day: &str) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//test//EN\r\nBEGIN:VEVENT\r\nUID:{}\r\n\
+             DTSTAMP:20260101T000000Z\r\nDTSTART:{day}T080000Z\r\nDTEND:{day}T083000Z\r\n\
+             SUMMARY:{}\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+            uid,
+            summary,
+            day = day
+        )
+    }
+
+    fn propfind(uri: &str, depth: &str, body: &str) -> test::TestRequest {
+        test::TestRequest::default()
+            .method(Method::from_bytes(b"PROPFIND").unwrap())
+            .uri(uri)
+            .insert_header(("Depth", depth))
+            .set_payload(body.to_string())
+    }
+
+    fn report(body: &str) -> test::TestRequest {
+        test::TestRequest::default()
+            .method(Method::from_bytes(b"REPORT").unwrap())
+            .uri("/dav/calendar/")
+            .insert_header(("Depth", "1"))
+            .set_payload(body.to_string())
+    }
+
+    #[actix_web::test]
+    async fn client_syncs_objects_with_etags() {
+        let app = test::init_service(App::new().configure(configure)).await;
+        let id = Uuid::new_v4().as_u128();
+        let href = object_href(id);
+
+        let request = test::TestRequest::default()
+            .method(Method::from_bytes(b"PROPFIND").unwrap())
+            .uri("/dav/")
+            .insert_header(("Depth", "0"))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+        let body = String::from_utf8(test::read_body(response).await.to_vec()).unwrap();
+        assert!(body.contains("<c:calendar-home-set><d:href>/dav/</d:href>"));
+
+        let request = test::TestRequest::put()
+            .uri(href.as_str())
+            .insert_header((IF_NONE_MATCH, "*"))
+            .set_payload(event(Uuid::from_u128(id).to_string().as_str(), "dav lovage"))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers().get(ETAG).unwrap(), "\"1\"");
+        let request = test::TestRequest::put()
+            .uri(href.as_str())
+            .insert_header((IF_NONE_MATCH, "*"))
+            .set_payload(event(Uuid::from_u128(id).to_string().as_str(), "dav lovage"))
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::PRECONDITION_FAILED);
+
+        let request = test::TestRequest::get().uri(href.as_str()).to_request();
+        let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+        assert!(body.contains("SUMMARY:dav lovage\r\n"));
+
+        let request = test::TestRequest::default()
+            .method(Method::from_bytes(b"PROPFIND").unwrap())
+            .uri("/dav/calendar/")
+            .insert_header(("Depth", "1"))
+            .to_request();
+        let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+        assert!(body.contains("<cs:getctag>"));
+        assert!(body.contains(format!("<d:href>{}</d:href>", href).as_str()));
+
+        let query = |start: &str, end: &str| {
+            format!(
+                "<?xml version=\"1.0\"?><C:calendar-query xmlns:D=\"DAV:\" \
+                 xmlns:C=\"urn:ietf:params:xml:ns:caldav\"><D:prop><D:getetag/><C:calendar-data/>\
+                 </D:prop><C:filter><C:comp-filter name=\"VCALENDAR\"><C:comp-filter name=\"VEVENT\">\
+                 <C:time-range start=\"{}\" end=\"{}\"/></C:comp-filter></C:comp-filter></C:filter>\
+                 </C:calendar-query>",
+                start, end
+            )
+        };
+        let request = report(query("20310714T000000Z", "20310715T000000Z").as_str()).to_request();
+        let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+        assert!(body.contains(format!("<d:href>{}</d:href>", href).as_str()));
+        assert!(body.contains("SUMMARY:dav lovage"));
+        let request = report(query("20310715T000000Z", "20310716T000000Z").as_str()).to_request();
+        let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+        assert!(!body.contains(href.as_str()));
+
+        let missing = object_href(Uuid::new_v4().as_u128());
+        let multiget = format!(
+            "<C:calendar-multiget xmlns:D=\"DAV:\" xmlns:C=\"urn:ietf:params:xml:ns:caldav\">\
+             <D:prop><D:getetag/></D:prop><D:href>{}</D:href><D:href>{}</D:href>\
+             </C:calendar-multiget>",
+            href, missing
+        );
+        let request = report(multiget.as_str()).to_request();
+        let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+        assert!(body.contains("<d:getetag>&quot;1&quot;</d:getetag>"));
+        assert!(body.contains(format!("<d:href>{}</d:href><d:status>HTTP/1.1 404", missing).as_str()));
+        assert!(!body.contains("calendar-data"));
+
+        // fields iCalendar does not carry survive an update from a client
+        let user = UserContext::default_user();
+        dynamic_process(&user, move |mut cache| cache.set_event_tags(id, vec!["kept"]))
+            .await
+            .unwrap();
+        let request = test::TestRequest::put()
+            .uri(href.as_str())
+            .insert_header((IF_MATCH, "\"2\""))
+            .set_payload(event(Uuid::from_u128(id).to_string().as_str(), "dav lovage renamed"))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(response.headers().get(ETAG).unwrap(), "\"3\"");
+        let tags = static_process(&user, move |cache| cache.snapshot_event(id).unwrap().tags)
+            .await
+            .unwrap();
+        assert!(tags.contains("kept"));
+        let request = test::TestRequest::put()
+            .uri(href.as_str())
+            .insert_header((IF_MATCH, "\"2\""))
+            .set_payload(event(Uuid::from_u128(id).to_string().as_str(), "stale"))
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::PRECONDITION_FAILED);
+
+        let request = test::TestRequest::delete()
+            .uri(href.as_str())
+            .insert_header((IF_MATCH, "\"3\""))
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::NO_CONTENT);
+        let request = test::TestRequest::get().uri(href.as_str()).to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::NOT_FOUND);
+        let request = test::TestRequest::put()
+            .uri(href.as_str())
+            .set_payload("BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n")
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn client_names_and_uids_are_served_unchanged() {
+        let app = test::init_service(App::new().configure(configure)).await;
+        let href = "/dav/calendar/abc@example.com.ics";
+        let request = test::TestRequest::put()
+            .uri(href)
+            .set_payload(event_on("abc@example.com", "dav sorrel", "20310721"))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert!(response.status().is_success());
+        let revision = response.headers().get(ETAG).unwrap().clone();
+
+        let request = test::TestRequest::get().uri(href).to_request();
+        let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+        assert!(body.contains("UID:abc@example.com\r\n"));
+        assert!(body.contains("SUMMARY:dav sorrel\r\n"));
+
+        // only the props asked for, the unknown ones as not found
+        let props = "<?xml version=\"1.0\"?><A:propfind xmlns:A=\"DAV:\"><A:prop><A:getetag/>\
+                     <X:color xmlns:X=\"urn:example:x\"/></A:prop></A:propfind>";
+        let request = propfind("/dav/calendar/", "1", props).to_request();
+        let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+        let listed = format!("<d:href>{}</d:href><d:propstat><d:prop><d:getetag>", href);
+        assert!(body.contains(listed.as_str()));
+        assert!(body.contains("<x:color xmlns:x=\"urn:example:x\"/></d:prop>\
+                               <d:status>HTTP/1.1 404 Not Found</d:status>"));
+        assert!(!body.contains("getcontenttype"));
+        assert!(!body.contains("getctag"));
+        let multiget = "<C:calendar-multiget xmlns:D=\"DAV:\" xmlns:C=\"urn:ietf:params:xml:ns:caldav\">\
+                        <D:prop><C:calendar-data/></D:prop>\
+                        <D:href>/dav/calendar/abc%40example.com.ics</D:href></C:calendar-multiget>";
+        let request = report(multiget).to_request();
+        let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+        assert!(body.contains("UID:abc@example.com"));
+        assert!(!body.contains("getetag"));
+        let request = propfind(href, "0", "").to_request();
+        let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+        assert!(body.contains(format!("<d:href>{}</d:href>", href).as_str()));
+        assert!(body.contains("<d:getcontenttype>"));
+        assert!(!body.contains("404"));
+
+        let request = test::TestRequest::delete()
+            .uri(href)
+            .insert_header((IF_MATCH, revision))
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[actix_web::test]
+    async fn hidden_calendars_are_not_served() {
+        let app = test::init_service(App::new().configure(configure)).await;
+        let user = UserContext::default_user();
+        let mut hidden = Calendar::new(format!("dav hidden {}", Uuid::new_v4()).as_str(), "");
+        hidden.visible = false;
+        let hidden = save_calendar(&user, hidden).await.unwrap();
+        let mut event = Event::init(None);
+        event.set_title("dav hidden chervil");
+        event.set_calendar(Some(hidden.get_id()));
+        let id = event.get_id();
+        dynamic_process(&user, move |mut cache| cache.insert_events(vec![Box::new(event)]))
+            .await
+            .unwrap();
+
+        let request = propfind("/dav/calendar/", "1", "").to_request();
+        let body = String::from_utf8(test::call_and_read_body(&app, request).await.to_vec()).unwrap();
+        assert!(!body.contains(object_href(id).as_str()));
+        let request = test::TestRequest::get().uri(object_href(id).as_str()).to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::NOT_FOUND);
+        let request = propfind(object_href(id).as_str(), "0", "").to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn objects_out_of_time_are_rejected() {
+        let app = test::init_service(App::new().configure(configure)).await;
+        let late = event_on("late@example.com", "dav late chervil", "21010101");
+        let endless = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\n\
+             UID:endless@example.com\r\nDTSTART:20310714T080000Z\r\n\
+             DURATION:P99999999999999W\r\nSUMMARY:dav endless chervil\r\n\
+             END:VEVENT\r\nEND:VCALENDAR\r\n";
+        for (name, body) in [("late", late.as_str()), ("endless", endless)] {
+            let request = test::TestRequest::put()
+                .uri(format!("/dav/calendar/{}@example.com.ics", name).as_str())
+                .insert_header((IF_NONE_MATCH, "*"))
+                .set_payload(body.to_string())
+                .to_request();
+            let response = test::call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    // the sync engine is the client, against the server listening like it does in production
+    #[actix_web::test]
+    async fn sync_client_keeps_names_and_uids_of_the_server() {
+        let server = HttpServer::new(|| {
+            // the stand-in proxy runs on the same machine
+            let proxies = TrustedProxies(vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]);
+            App::new()
+                .app_data(web::Data::new(proxies))
+                .wrap_fn(|mut request, service| {
+                    // as the proxy in front of the server would
+                    request.headers_mut().insert(
+                        HeaderName::from_static("x-forwarded-user"),
+                        HeaderValue::from_static("dav-server"),
+                    );
+                    service.call(request)
+                })
+                .configure(configure)
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let base = format!("http://{}", server.addrs()[0]);
+        actix_web::rt::spawn(server.run());
+        let client = reqwest::Client::new();
+        let href = format!("{}/dav/calendar/abc@example.com.ics", base);
+        let response = client
+            .put(href.as_str())
+            .header("If-None-Match", "*")
+            .body(event_on("abc@example.com", "dav parsley", "20310728"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+
+        let user = UserContext::new("dav-client").unwrap();
+        let settings = SyncSettings::new(format!("{}/dav/calendar/", base).as_str());
+        let report = sync_calendar(&user, &settings).await.unwrap();
+        assert_eq!((report.pulled, report.conflicts.len()), (1, 0));
+        let id = parse_uid("abc@example.com");
+        let mut model = get_event(&user, id).await.unwrap();
+        assert_eq!(model.title, "dav parsley");
+        model.title = "dav parsley renamed".to_string();
+        update_event(&user, model, None).await.unwrap();
+        let report = sync_calendar(&user, &settings).await.unwrap();
+        assert_eq!((report.pushed, report.conflicts.len()), (1, 0));
+
+        let body = client.get(href.as_str()).send().await.unwrap().text().await.unwrap();
+        assert!(body.contains("UID:abc@example.com\r\n"));
+        assert!(body.contains("SUMMARY:dav parsley renamed\r\n"));
+        let propfind = reqwest::Method::from_bytes(b"PROPFIND").unwrap();
+        let listing = client
+            .request(propfind, format!("{}/dav/calendar/", base))
+            .header("Depth", "1")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(listing.contains("<d:href>/dav/calendar/abc@example.com.ics</d:href>"));
+        assert_eq!(listing.matches("<d:href>/dav/calendar/").count(), 2);
+    }
+}
@@ -19,10 +19,94 @@ pub enum InternalError {
     EventsAlreadyExistError { event_id: u128 },
     #[error("data persistence error")]
     DataPersistenceError,
+    #[error("the change was kept in memory but could not be saved")]
+    UnsavedChangeError,
     #[error("unknown data error")]
     UnknownError,
     #[error("Event not found error")]
     EventNotFoundError,
     #[error("Busy Cache")]
     BusyCache,
+    #[error("Objective not found error")]
+    ObjectiveNotFoundError,
+    #[error("invalid quarter {quarter} of {year}, quarters are 1 to 4")]
+    InvalidQuarterError { year: i32, quarter: u32 },
+    #[error("Key result not found error")]
+    KeyResultNotFoundError,
+    #[error("Task not found error")]
+    TaskNotFoundError,
+    #[error("Alarm not found error")]
+    AlarmNotFoundError,
+    #[error("invalid query at {position}: {reason}")]
+    QueryParseError { position: usize, reason: String },
+    #[error("invalid time {time:?}")]
+    InvalidTimeError { time: String },
+    #[error("event {event_id} is at revision {current}, not {expected}")]
+    RevisionConflictError {
+        event_id: u128,
+        expected: u64,
+        current: u64,
+    },
+    #[error("invalid revision {revision:?}")]
+    InvalidRevisionError { revision: String },
+    #[error("a change needs the revision it is based on, as If-Match or in the body")]
+    PreconditionRequiredError,
+    #[error("invalid change cursor {cursor:?}")]
+    InvalidCursorError { cursor: String },
+    #[error("changes after cursor {cursor} are no longer kept")]
+    ChangeCursorExpiredError { cursor: u64 },
+    #[error("nothing to undo")]
+    NothingToUndoError,
+    #[error("nothing to redo")]
+    NothingToRedoError,
+    #[error("View not found error")]
+    ViewNotFoundError,
+    #[error("a view named {name} already exists")]
+    ViewAlreadyExistError { name: String },
+    #[error("invalid user {user:?}")]
+    InvalidUserError { user: String },
+    #[error("the user header is only accepted from a trusted proxy")]
+    UntrustedUserHeaderError,
+    #[error("invalid trusted proxy {proxy:?}")]
+    InvalidTrustedProxyError { proxy: String },
+    #[error("Calendar not found error")]
+    CalendarNotFoundError,
+    #[error("a calendar named {name} already exists")]
+    CalendarAlreadyExistError { name: String },
+    #[error("Subscription not found error")]
+    SubscriptionNotFoundError,
+    #[error("a subscription named {name} already exists")]
+    SubscriptionAlreadyExistError { name: String },
+    #[error("event {event_id} was skipped by the conflict rule of its category or calendar")]
+    SkippedEventError { event_id: u128 },
+    #[error("event {event_id} belongs to a subscribed feed and is read-only")]
+    ReadOnlyEventError { event_id: u128 },
+    #[error("Category not found error")]
+    CategoryNotFoundError,
+    #[error("time tracking of event {event_id} is already running")]
+    TrackingAlreadyStartedError { event_id: u128 },
+    #[error("time tracking of event {event_id} is not running")]
+    TrackingNotStartedError { event_id: u128 },
+    #[error("invalid tracking group {group:?}, expected day, category or importance")]
+    InvalidTrackingGroupError { group: String },
+    #[error("invalid kind {kind:?} for this record")]
+    InvalidKindError { kind: String },
+    #[error("invalid iCalendar data: {reason}")]
+    InvalidIcsError { reason: String },
+    #[error("invalid break policy: {reason}")]
+    InvalidBreakPolicyError { reason: String },
+    #[error("invalid repeat strategy: {reason}")]
+    InvalidRepeatStrategyError { reason: String },
+    #[error("invalid focus link {link:?}, expected task:<id> or key-result:<id>")]
+    InvalidFocusLinkError { link: String },
+    #[error("invalid conflict policy {policy:?}")]
+    InvalidConflictPolicyError { policy: String },
+    #[error("feeds are only read from http, https or webcal urls of public hosts")]
+    InvalidFeedSourceError,
+    #[error("remote calendar error: {reason}")]
+    RemoteCalendarError { reason: String },
+    #[error("invalid notification sink {sink:?}, expected stdout, webhook:<url> or command:<...>")]
+    InvalidNotificationSinkError { sink: String },
+    #[error("notification delivery error: {reason}")]
+    NotificationDeliveryError { reason: String },
 }
@@ -1,9 +1,12 @@
 use std::str::FromStr;
 use std::sync::Arc;
 
-use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+use anyhow::anyhow;
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone};
 use lazy_static::lazy_static;
 
+use crate::common::exception::InternalError;
 use crate::model::EventCommonTrait;
 
 lazy_static! {
@@ -17,6 +20,19 @@ lazy_static! {
         .unwrap();
 }
 
+// tags match without the leading # and case insensitive, "#ClientA" is "clienta"
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').to_lowercase()
+}
+
+// lowercase words of letters and digits, used by the full text index and its queries
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
 pub fn get_cache_day_number_from_time(time: DateTime<FixedOffset>) -> i64 {
     time.naive_utc()
         .signed_duration_since(*MIN_EVENT_TIMESTAMP)
@@ -35,27 +51,20 @@ pub fn check_conflict(
     let mut result = Vec::new();
     for i in 0..events.len() {
         for j in 0..events.len() {
-            if i != j {
+            // same id means an update of the event itself, not a conflict
+            if i != j && events[i].get_id() != events[j].get_id() {
+                let first_event = events[i].clone();
+                let second_event = events[j].clone();
+                if first_event.is_blocking()
+                    && second_event.is_blocking()
+                    && first_event
+                    .get_start_time()
+                    .lt(&second_event.get_end_time())
+                    && second_event
+                    .get_start_time()
+                    .lt(&first_event.get_end_time())
                 {
-                    let first_event = events[i].clone();
-                    let second_event = events[j].clone();
-                    if first_event
-                        .get_start_time()
-                        .le(&second_event.get_end_time())
-                        || first_event
-                        .get_end_time()
-                        .ge(&second_event.get_start_time())
-                        || (first_event
-                        .get_start_time()
-                        .lt(&second_event.get_start_time())
-                        && first_event.get_end_time().gt(&second_event.get_end_time()))
-                        || (first_event
-                        .get_start_time()
-                        .gt(&second_event.get_start_time())
-                        && first_event.get_end_time().lt(&second_event.get_end_time()))
-                    {
-                        result.push((events[i].clone(), events[j].clone()));
-                    }
+                    result.push((events[i].clone(), events[j].clone()));
                 }
             }
         }
@@ -63,8 +72,31 @@ pub fn check_conflict(
     result
 }
 
-pub fn convert_from_string_to_datetime(naive_time: i64, offset: String) -> DateTime<FixedOffset> {
-    DateTime::from_timestamp_millis(naive_time)
+// both parts may come from clients, so neither is trusted
+pub fn convert_from_string_to_datetime(
+    naive_time: i64,
+    offset: String,
+) -> Result<DateTime<FixedOffset>> {
+    let time = DateTime::from_timestamp_millis(naive_time).ok_or(
+        InternalError::InvalidTimeError {
+            time: naive_time.to_string(),
+        },
+    )?;
+    let offset = FixedOffset::from_str(&offset)
+        .map_err(|_| InternalError::InvalidTimeZoneError { time_zone: offset })?;
+    Ok(time.with_timezone(&offset))
+}
+
+pub fn parse_time(time: &str) -> Result<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(time).map_err(|_| {
+        anyhow!(InternalError::InvalidTimeError {
+            time: time.to_string()
+        })
+    })
+}
+
+pub fn get_local_day_start(day: NaiveDate, timezone: FixedOffset) -> DateTime<FixedOffset> {
+    timezone
+        .from_local_datetime(&day.and_hms_opt(0, 0, 0).unwrap())
         .unwrap()
-        .with_timezone(&FixedOffset::from_str(&offset).unwrap())
 }
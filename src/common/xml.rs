@@ -0,0 +1,110 @@
+// just enough xml for webdav bodies, namespaces are matched by local name only
+
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// attributes and content of every element with the local name, whatever its namespace
+// prefix, e.g. the hrefs of a multiget. nested elements of the same name are not split
+pub fn elements(xml: &str, name: &str) -> Vec<(String, String)> {
+    let mut found = vec![];
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open + 1..];
+        let tag_end = match rest.find('>') {
+            Some(tag_end) => tag_end,
+            None => break,
+        };
+        let tag = &rest[..tag_end];
+        let qualified = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+        if qualified.rsplit(':').next() != Some(name) {
+            continue;
+        }
+        let attributes = tag[qualified.len()..].trim_end_matches('/').to_string();
+        rest = &rest[tag_end + 1..];
+        if tag.ends_with('/') {
+            found.push((attributes, String::new()));
+            continue;
+        }
+        let close = format!("</{}>", qualified);
+        match rest.find(close.as_str()) {
+            Some(end) => {
+                found.push((attributes, unescape_xml(&rest[..end])));
+                rest = &rest[end + close.len()..];
+            }
+            None => found.push((attributes, String::new())),
+        }
+    }
+    found
+}
+
+// namespace and local name of the properties in the first prop element, e.g. of a
+// propfind. None when there is none, as for allprop or an empty body
+pub fn requested_props(xml: &str) -> Option<Vec<(String, String)>> {
+    let (_, prop) = elements(xml, "prop").into_iter().next()?;
+    let mut props = vec![];
+    let mut depth = 0;
+    let mut rest = prop.as_str();
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open + 1..];
+        let tag_end = match rest.find('>') {
+            Some(tag_end) => tag_end,
+            None => break,
+        };
+        let tag = &rest[..tag_end];
+        rest = &rest[tag_end + 1..];
+        if tag.starts_with('/') {
+            depth -= 1;
+            continue;
+        }
+        if depth == 0 {
+            let qualified = tag
+                .split(|c: char| c.is_whitespace() || c == '/')
+                .next()
+                .unwrap_or_default();
+            let (prefix, name) = qualified.rsplit_once(':').unwrap_or(("", qualified));
+            // declared on the property itself or on any element around it
+            let namespace = namespace(tag, prefix)
+                .or_else(|| namespace(xml, prefix))
+                .unwrap_or("DAV:".to_string());
+            props.push((namespace, name.to_string()));
+        }
+        if !tag.ends_with('/') {
+            depth += 1;
+        }
+    }
+    Some(props)
+}
+
+// the first declaration of the prefix anywhere in the xml, the default one without prefix
+fn namespace(xml: &str, prefix: &str) -> Option<String> {
+    let name = if prefix.is_empty() { "xmlns".to_string() } else { format!("xmlns:{}", prefix) };
+    xml.split(|c: char| c.is_whitespace() || c == '<')
+        .find(|attribute| attribute.starts_with(format!("{}=", name).as_str()))
+        .and_then(|declaration| attribute(declaration, name.as_str()))
+}
+
+pub fn attribute(attributes: &str, name: &str) -> Option<String> {
+    for quote in ['"', '\''] {
+        let key = format!("{}={}", name, quote);
+        if let Some(start) = attributes.find(key.as_str()) {
+            let value = &attributes[start + key.len()..];
+            return value.find(quote).map(|end| unescape_xml(&value[..end]));
+        }
+    }
+    None
+}
+
+pub fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
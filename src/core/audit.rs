@@ -0,0 +1,18 @@
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+
+use crate::core::processor::static_process;
+use crate::model::audit::AuditEntry;
+use crate::model::user::UserContext;
+
+pub async fn get_event_audit(user: &UserContext, event_id: u128) -> Result<Vec<AuditEntry>> {
+    static_process(user, move |cache| cache.get_event_audit(event_id)).await
+}
+
+pub async fn get_audit_between(
+    user: &UserContext,
+    start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>,
+) -> Result<Vec<AuditEntry>> {
+    static_process(user, move |cache| cache.get_audit_between(start, end)).await
+}
@@ -0,0 +1,29 @@
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+
+use crate::core::processor::{dynamic_process, static_process};
+use crate::model::break_policy::{BreakPolicy, BreakReport};
+use crate::model::user::UserContext;
+
+// the day is the local date of day in its offset
+pub async fn analyse_breaks(
+    user: &UserContext,
+    day: DateTime<FixedOffset>,
+    policy: BreakPolicy,
+) -> Result<BreakReport> {
+    static_process(user, move |cache| {
+        cache.analyse_breaks(day.date_naive(), *day.offset(), &policy)
+    })
+    .await
+}
+
+pub async fn plan_breaks(
+    user: &UserContext,
+    day: DateTime<FixedOffset>,
+    policy: BreakPolicy,
+) -> Result<BreakReport> {
+    dynamic_process(user, move |mut cache| {
+        cache.insert_planned_breaks(day.date_naive(), *day.offset(), &policy)
+    })
+    .await
+}
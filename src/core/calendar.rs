@@ -0,0 +1,44 @@
+use anyhow::anyhow;
+use anyhow::Result;
+
+use crate::common::exception::InternalError;
+use crate::core::processor::{dynamic_process, static_process};
+use crate::model::calendar::{Calendar, MAIN_CALENDAR};
+use crate::model::user::UserContext;
+
+// also updates a calendar with the same id
+pub async fn save_calendar(user: &UserContext, calendar: Calendar) -> Result<Calendar> {
+    let saved = calendar.clone();
+    dynamic_process(user, move |mut cache| cache.add_or_update_calendars(vec![calendar])).await?;
+    Ok(saved)
+}
+
+pub async fn delete_calendar(user: &UserContext, id: u128) -> Result<()> {
+    dynamic_process(user, move |mut cache| cache.delete_calendar(id)).await
+}
+
+pub async fn get_calendars(user: &UserContext) -> Result<Vec<Calendar>> {
+    static_process(user, |cache| cache.get_all_calendars()).await
+}
+
+// calendars are addressed by id or by name
+pub async fn find_calendar(user: &UserContext, key: String) -> Result<Calendar> {
+    static_process(user, move |cache| {
+        key.parse::<u128>()
+            .ok()
+            .and_then(|id| cache.get_calendar(id).ok())
+            .or_else(|| cache.get_calendar_by_name(key.as_str()).ok())
+    })
+    .await?
+    .ok_or(anyhow!(InternalError::CalendarNotFoundError))
+}
+
+// the main calendar is addressed by its name too
+pub async fn move_event(user: &UserContext, event_id: u128, calendar: String) -> Result<()> {
+    let calendar = if calendar.eq_ignore_ascii_case(MAIN_CALENDAR) {
+        None
+    } else {
+        Some(find_calendar(user, calendar).await?.get_id())
+    };
+    dynamic_process(user, move |mut cache| cache.set_event_calendar(event_id, calendar)).await
+}
@@ -0,0 +1,118 @@
+use std::collections::VecDeque;
+
+use anyhow::bail;
+use anyhow::Result;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::core::processor::{static_process, subscribe_changes};
+use crate::model::change_feed::ChangeEvent;
+use crate::model::user::UserContext;
+
+// changes of events as they are written, resumable from the cursor of the last one seen
+pub struct ChangeFeed {
+    user: UserContext,
+    receiver: broadcast::Receiver<ChangeEvent>,
+    backlog: VecDeque<ChangeEvent>,
+    cursor: u64,
+    lagged: bool, // kept until the backlog is read back, so a cancelled next loses nothing
+}
+
+// from the given cursor on, or only new changes without one
+pub async fn subscribe(user: &UserContext, after: Option<u64>) -> Result<ChangeFeed> {
+    let receiver = subscribe_changes(user).await?;
+    let (cursor, backlog) = static_process(user, move |cache| {
+        let cursor = after.unwrap_or(cache.get_change_cursor());
+        cache.get_changes_after(cursor).map(|backlog| (cursor, backlog))
+    })
+    .await??;
+    Ok(ChangeFeed {
+        user: user.clone(),
+        receiver,
+        backlog: backlog.into(),
+        cursor,
+        lagged: false,
+    })
+}
+
+pub async fn get_changes_after(user: &UserContext, cursor: u64) -> Result<Vec<ChangeEvent>> {
+    static_process(user, move |cache| cache.get_changes_after(cursor)).await?
+}
+
+impl ChangeFeed {
+    // cursor of the last change returned, to resume from after reconnecting
+    pub fn cursor(&self) -> u64 {
+        self.cursor
+    }
+
+    pub async fn next(&mut self) -> Result<ChangeEvent> {
+        loop {
+            if self.lagged {
+                self.backlog = get_changes_after(&self.user, self.cursor).await?.into();
+                self.lagged = false;
+            }
+            if let Some(change) = self.backlog.pop_front() {
+                self.cursor = change.cursor;
+                return Ok(change);
+            }
+            match self.receiver.recv().await {
+                // already returned from the backlog
+                Ok(change) if change.cursor <= self.cursor => continue,
+                Ok(change) => {
+                    self.cursor = change.cursor;
+                    return Ok(change);
+                }
+                // missed broadcasts are read back from the cache
+                Err(RecvError::Lagged(_)) => self.lagged = true,
+                Err(RecvError::Closed) => bail!("change feed closed"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use crate::core::change_feed::subscribe;
+    use crate::core::processor::dynamic_process;
+    use crate::model::audit::AuditAction;
+    use crate::model::event::Event;
+    use crate::model::ItemCommonTrait;
+    use crate::model::user::UserContext;
+
+    #[tokio::test]
+    async fn subscribers_receive_and_resume_changes() {
+        let user = UserContext::new("change-feed").unwrap();
+        let mut feed = subscribe(&user, None).await.unwrap();
+        let event = Event::init(None);
+        let id = event.get_id();
+        dynamic_process(&user, move |mut cache| cache.insert_events(vec![Box::new(event)]))
+            .await
+            .unwrap();
+        dynamic_process(&user, move |mut cache| cache.delete_event(id)).await.unwrap();
+
+        let mut seen = vec![];
+        while seen.len() < 2 {
+            let change = timeout(Duration::from_secs(5), feed.next()).await.unwrap().unwrap();
+            if change.event_id == id {
+                seen.push(change);
+            }
+        }
+        assert_eq!(seen[0].action, AuditAction::Created);
+        assert_eq!(seen[0].revision, 1);
+        assert_eq!(seen[1].action, AuditAction::Deleted);
+
+        // a new subscriber resumes right after the creation
+        let mut resumed = subscribe(&user, Some(seen[0].cursor)).await.unwrap();
+        let change = loop {
+            let change = resumed.next().await.unwrap();
+            if change.event_id == id {
+                break change;
+            }
+        };
+        assert_eq!(change, seen[1]);
+    }
+}
@@ -0,0 +1,138 @@
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Result;
+
+use crate::common::exception::InternalError;
+use crate::common::utils::{MAX_EVENT_TIMESTAMP, MIN_EVENT_TIMESTAMP};
+use crate::core::processor::{dynamic_process, static_process};
+use crate::model::EventCommonTrait;
+use crate::model::generator_instance::RepeatStrategy;
+use crate::model::user::UserContext;
+use crate::persistent::PersistentModel;
+
+// the stored form carries the revision to send back with an update
+pub async fn get_event(user: &UserContext, id: u128) -> Result<PersistentModel> {
+    static_process(user, move |cache| cache.snapshot_event(id))
+        .await?
+        .map(|model| *model)
+        .ok_or(anyhow!(InternalError::EventNotFoundError))
+}
+
+// stores the event unless it changed since expected, by default since the revision
+// of the model, and returns it with its new revision
+pub async fn update_event(
+    user: &UserContext,
+    model: PersistentModel,
+    expected: Option<u64>,
+) -> Result<PersistentModel> {
+    let id = model.id;
+    let mut event = model.convert_to()?;
+    check_times(event.as_ref())?;
+    if let Some(expected) = expected {
+        event.set_revision(expected);
+    }
+    dynamic_process(user, move |mut cache| {
+        cache.insert_single_event(event)?;
+        cache
+            .snapshot_event(id)
+            .map(|model| *model)
+            .ok_or(anyhow!(InternalError::EventNotFoundError))
+    })
+    .await
+}
+
+// events from clients must end after they start, within the days the calendar holds
+fn check_times(event: &dyn EventCommonTrait) -> Result<()> {
+    if !event.check_valid() {
+        bail!(InternalError::InvalidStartEndTimeError {
+            start_time: event.get_start_time(),
+            end_time: event.get_end_time()
+        })
+    }
+    Ok(())
+}
+
+// iCalendar events without an end take no time, which is valid there
+fn check_imported_times(event: &dyn EventCommonTrait) -> Result<()> {
+    let (start, end) = (event.get_start_time(), event.get_end_time());
+    if start.gt(&end)
+        || start.naive_utc().lt(&MIN_EVENT_TIMESTAMP)
+        || end.naive_utc().gt(&MAX_EVENT_TIMESTAMP)
+    {
+        bail!(InternalError::InvalidIcsError {
+            reason: format!("event from {} to {} is out of the calendar", start, end)
+        })
+    }
+    Ok(())
+}
+
+// stores an event read from iCalendar and returns it and whether it is new, fields
+// iCalendar does not carry are kept from the stored event
+pub async fn import_event(
+    user: &UserContext,
+    mut model: PersistentModel,
+    expected: Option<u64>,
+    create_only: bool,
+) -> Result<(PersistentModel, bool)> {
+    let id = model.id;
+    dynamic_process(user, move |mut cache| {
+        let stored = cache.snapshot_event(id);
+        if let Some(stored) = &stored {
+            if create_only {
+                bail!(InternalError::EventsAlreadyExistError { event_id: id })
+            }
+            model.color = stored.color.clone();
+            model.important_level = stored.important_level.clone();
+            model.generator_instance = stored.generator_instance.clone();
+            model.key_result = stored.key_result;
+            model.task = stored.task;
+            model.actual_start_time = stored.actual_start_time;
+            model.actual_start_time_timezone = stored.actual_start_time_timezone.clone();
+            model.actual_end_time = stored.actual_end_time;
+            model.actual_end_time_timezone = stored.actual_end_time_timezone.clone();
+            model.unplanned = stored.unplanned;
+            model.calendar = stored.calendar;
+            model.tags = stored.tags.clone();
+            model.href = model.href.take().or(stored.href.clone());
+        }
+        // without an expected revision the stored event is replaced, whatever its revision
+        model.revision = expected.or(stored.as_ref().map(|stored| stored.revision)).unwrap_or(0);
+        let event = model.convert_to()?;
+        check_imported_times(event.as_ref())?;
+        cache.insert_single_event(event)?;
+        let imported = cache
+            .snapshot_event(id)
+            .map(|model| *model)
+            .ok_or(anyhow!(InternalError::EventNotFoundError))?;
+        Ok((imported, stored.is_none()))
+    })
+    .await
+}
+
+// repeats a stored event as a series, the ids of the occurrences that were stored are returned
+pub async fn repeat_event(
+    user: &UserContext,
+    id: u128,
+    strategy: RepeatStrategy,
+    throw_error_when_conflict: bool,
+) -> Result<Vec<u128>> {
+    dynamic_process(user, move |mut cache| {
+        cache.repeat_event(id, strategy, throw_error_when_conflict)
+    })
+    .await
+}
+
+pub async fn delete_event(user: &UserContext, id: u128, expected: Option<u64>) -> Result<()> {
+    dynamic_process(user, move |mut cache| {
+        if let Some(expected) = expected {
+            cache.check_revision(id, expected)?;
+        }
+        cache.delete_event(id)
+    })
+    .await
+}
+
+// revision of the whole cache, changes with every write
+pub async fn get_revision(user: &UserContext) -> Result<u64> {
+    static_process(user, |cache| cache.get_revision()).await
+}
@@ -0,0 +1,336 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, Utc};
+use tokio::select;
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+use crate::common::exception::InternalError;
+use crate::core::processor::{dynamic_process, static_process};
+use crate::model::{Category, EventCommonTrait, ItemCommonTrait};
+use crate::model::event::Event;
+use crate::model::user::UserContext;
+
+#[derive(Clone)]
+pub struct FocusSettings {
+    pub title: String,
+    pub work: Duration,
+    pub short_break: Duration,
+    pub long_break: Duration,
+    pub cycles_before_long_break: usize, // 0 for short breaks only
+    pub cycles: usize, // number of work sessions before the timer finishes
+    pub category: String,
+    pub link: FocusLink,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FocusLink {
+    None,
+    Task(u128),
+    KeyResult(u128),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FocusPhase {
+    Work { cycle: usize },
+    ShortBreak { cycle: usize },
+    LongBreak { cycle: usize },
+    Finished,
+}
+
+// a completed work session and the event it was recorded as
+#[derive(Debug, Clone)]
+pub struct FocusSession {
+    pub event_id: u128,
+    pub cycle: usize,
+    pub start_time: DateTime<FixedOffset>,
+    pub end_time: DateTime<FixedOffset>,
+}
+
+pub struct FocusHandle {
+    stop: oneshot::Sender<()>,
+    phase: watch::Receiver<FocusPhase>,
+    join: JoinHandle<Result<Vec<FocusSession>>>,
+}
+
+impl Default for FocusSettings {
+    fn default() -> Self {
+        FocusSettings {
+            title: "Focus".to_string(),
+            work: Duration::from_secs(25 * 60),
+            short_break: Duration::from_secs(5 * 60),
+            long_break: Duration::from_secs(15 * 60),
+            cycles_before_long_break: 4,
+            cycles: 4,
+            category: Category::Default.to_string(),
+            link: FocusLink::None,
+        }
+    }
+}
+
+// a session is linked as task:<id> or key-result:<id>
+impl FromStr for FocusLink {
+    type Err = InternalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InternalError::InvalidFocusLinkError {
+            link: s.to_string(),
+        };
+        match s.split_once(':') {
+            Some(("task", id)) => id.parse().map(FocusLink::Task).map_err(|_| invalid()),
+            Some(("key-result", id)) => {
+                id.parse().map(FocusLink::KeyResult).map_err(|_| invalid())
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl FocusHandle {
+    pub fn phase(&self) -> FocusPhase {
+        *self.phase.borrow()
+    }
+
+    // waits for the timer to move on, Finished once it did for the last time
+    pub async fn next_phase(&mut self) -> FocusPhase {
+        match self.phase.changed().await {
+            Ok(()) => *self.phase.borrow_and_update(),
+            Err(_) => FocusPhase::Finished,
+        }
+    }
+
+    // sessions recorded so far, an interrupted work session is dropped
+    pub async fn stop(self) -> Result<Vec<FocusSession>> {
+        let _ = self.stop.send(());
+        self.join.await?
+    }
+
+    pub async fn wait(self) -> Result<Vec<FocusSession>> {
+        self.join.await?
+    }
+}
+
+// the linked task or key result must exist before the timer starts
+pub async fn start_focus(user: &UserContext, settings: FocusSettings) -> Result<FocusHandle> {
+    let link = settings.link;
+    static_process(user, move |cache| match link {
+        FocusLink::None => Ok(()),
+        FocusLink::Task(id) => cache.get_task(id).map(|_| ()),
+        FocusLink::KeyResult(id) => cache.get_key_result(id).map(|_| ()),
+    })
+    .await??;
+    let user = user.clone();
+    let (stop_sender, mut stop_receiver) = oneshot::channel();
+    let (phase_sender, phase_receiver) = watch::channel(FocusPhase::Work { cycle: 1 });
+    let join = tokio::spawn(async move {
+        let mut sessions = vec![];
+        for cycle in 1..=settings.cycles {
+            let _ = phase_sender.send(FocusPhase::Work { cycle });
+            let start_time = DateTime::from(Utc::now());
+            select! {
+                _ = sleep(settings.work) => {}
+                _ = &mut stop_receiver => break,
+            }
+            let end_time = DateTime::from(Utc::now());
+            // a session overlapping the calendar is not recorded, the timer goes on
+            match record_session(&user, &settings, cycle, start_time, end_time).await {
+                Ok(session) => sessions.push(session),
+                Err(error) if is_overlap(&error) => {
+                    warn!("focus session {} not recorded: {}", cycle, error)
+                }
+                Err(error) => return Err(error),
+            }
+            if cycle == settings.cycles {
+                break;
+            }
+            let long_break = settings.cycles_before_long_break > 0
+                && cycle % settings.cycles_before_long_break == 0;
+            let rest = if long_break {
+                let _ = phase_sender.send(FocusPhase::LongBreak { cycle });
+                settings.long_break
+            } else {
+                let _ = phase_sender.send(FocusPhase::ShortBreak { cycle });
+                settings.short_break
+            };
+            select! {
+                _ = sleep(rest) => {}
+                _ = &mut stop_receiver => break,
+            }
+        }
+        let _ = phase_sender.send(FocusPhase::Finished);
+        info!("focus timer finished with {} sessions", sessions.len());
+        Ok(sessions)
+    });
+    Ok(FocusHandle {
+        stop: stop_sender,
+        phase: phase_receiver,
+        join,
+    })
+}
+
+async fn record_session(
+    user: &UserContext,
+    settings: &FocusSettings,
+    cycle: usize,
+    start_time: DateTime<FixedOffset>,
+    end_time: DateTime<FixedOffset>,
+) -> Result<FocusSession> {
+    let mut event = Event::init(None);
+    let event_id = event.get_id();
+    event.set_title(settings.title.as_str());
+    event.set_duration(start_time, end_time);
+    event.set_categories(Category::from(settings.category.as_str()));
+    match settings.link {
+        FocusLink::None => {}
+        FocusLink::Task(id) => event.set_task(Some(id)),
+        FocusLink::KeyResult(id) => event.set_key_result(Some(id)),
+    }
+    // skipped by the rules of its category or calendar, the session is not recorded either
+    dynamic_process(user, move |mut cache| cache.insert_single_event(Box::new(event))).await?;
+    Ok(FocusSession {
+        event_id,
+        cycle,
+        start_time,
+        end_time,
+    })
+}
+
+fn is_overlap(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<InternalError>(),
+        Some(InternalError::ConflictEventError { .. })
+            | Some(InternalError::SkippedEventError { .. })
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{DateTime, Utc};
+
+    use crate::core::focus::{FocusLink, FocusPhase, FocusSettings, start_focus};
+    use crate::core::processor::{dynamic_process, static_process};
+    use uuid::Uuid;
+
+    use crate::model::{EventCommonTrait, ImportantLevel, ItemCommonTrait};
+    use crate::model::category::{ConflictBehavior, UserCategory};
+    use crate::model::event::Event;
+    use crate::model::task::Task;
+    use crate::model::user::UserContext;
+
+    fn settings(link: FocusLink) -> FocusSettings {
+        FocusSettings {
+            work: Duration::from_millis(40),
+            short_break: Duration::from_millis(10),
+            long_break: Duration::from_millis(20),
+            cycles_before_long_break: 2,
+            cycles: 3,
+            link,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn focus_sessions_are_recorded_as_linked_events() {
+        let user = UserContext::new("focus-linked").unwrap();
+        let task = Task::init(None);
+        let task_id = task.get_id();
+        dynamic_process(&user, move |mut cache| {
+            cache.add_or_update_tasks(vec![task]);
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        let handle = start_focus(&user, settings(FocusLink::Task(task_id))).await.unwrap();
+        assert_eq!(handle.phase(), FocusPhase::Work { cycle: 1 });
+        let sessions = handle.wait().await.unwrap();
+
+        assert_eq!(sessions.len(), 3);
+        let ids = sessions.iter().map(|s| s.event_id).collect::<Vec<u128>>();
+        let linked = static_process(&user, move |cache| {
+            ids.iter()
+                .filter_map(|id| cache.get_events_by_id::<Event>(*id).ok())
+                .filter(|e| e.get_task() == Some(task_id) && e.get_title() == "Focus")
+                .count()
+        })
+        .await
+        .unwrap();
+        assert_eq!(linked, 3);
+    }
+
+    #[tokio::test]
+    async fn stopped_timer_keeps_only_completed_sessions() {
+        let user = UserContext::new("focus-stopped").unwrap();
+        let handle = start_focus(&user, FocusSettings {
+            work: Duration::from_secs(60),
+            ..settings(FocusLink::None)
+        })
+        .await
+        .unwrap();
+        assert!(handle.stop().await.unwrap().is_empty());
+        assert!(start_focus(&user, settings(FocusLink::KeyResult(1))).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn overlapping_sessions_are_skipped_without_stopping_the_timer() {
+        let user = UserContext::new("focus-overlap").unwrap();
+        let now = DateTime::from(Utc::now());
+        let mut meeting = Event::init(None);
+        meeting.set_duration(now - chrono::Duration::minutes(1), now + chrono::Duration::hours(1));
+        dynamic_process(&user, move |mut cache| cache.insert_events(vec![Box::new(meeting)]))
+            .await
+            .unwrap();
+
+        let handle = start_focus(&user, FocusSettings {
+            cycles_before_long_break: 0,
+            ..settings(FocusLink::None)
+        })
+        .await
+        .unwrap();
+
+        assert!(handle.wait().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn sessions_skipped_by_their_category_are_not_reported() {
+        let user = UserContext::new("focus-skip").unwrap();
+        let mut optional = UserCategory::new("Optional", "#cccccc", ImportantLevel::Low);
+        optional.conflict = ConflictBehavior::Skip;
+        let category = Uuid::from_u128(optional.get_id()).to_string();
+        let now = DateTime::from(Utc::now());
+        let mut meeting = Event::init(None);
+        meeting.set_duration(now - chrono::Duration::minutes(1), now + chrono::Duration::hours(1));
+        dynamic_process(&user, move |mut cache| {
+            cache.add_or_update_categories(vec![optional]);
+            cache.insert_events(vec![Box::new(meeting)])
+        })
+        .await
+        .unwrap();
+
+        let handle = start_focus(&user, FocusSettings {
+            cycles: 1,
+            category,
+            ..settings(FocusLink::None)
+        })
+        .await
+        .unwrap();
+
+        assert!(handle.wait().await.unwrap().is_empty());
+        let events = static_process(&user, |cache| cache.get_all_events::<Event>().len());
+        assert_eq!(events.await.unwrap(), 1);
+    }
+
+    #[test]
+    fn links_are_parsed_by_kind() {
+        assert_eq!("task:7".parse::<FocusLink>().unwrap(), FocusLink::Task(7));
+        assert_eq!("key-result:8".parse::<FocusLink>().unwrap(), FocusLink::KeyResult(8));
+        assert!("objective:9".parse::<FocusLink>().is_err());
+        assert!("task:nine".parse::<FocusLink>().is_err());
+    }
+}
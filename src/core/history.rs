@@ -0,0 +1,26 @@
+use anyhow::Result;
+
+use crate::core::processor::{dynamic_process, static_process};
+use crate::model::history::HistoryStep;
+use crate::model::user::UserContext;
+
+pub async fn undo(user: &UserContext) -> Result<HistoryStep> {
+    dynamic_process(user, move |mut cache| cache.undo()).await
+}
+
+pub async fn redo(user: &UserContext) -> Result<HistoryStep> {
+    dynamic_process(user, move |mut cache| cache.redo()).await
+}
+
+pub async fn set_history_limit(user: &UserContext, limit: usize) -> Result<()> {
+    dynamic_process(user, move |mut cache| {
+        cache.set_history_limit(limit);
+        Ok(())
+    })
+    .await
+}
+
+// steps that can be undone and redone, each the next one first
+pub async fn get_history(user: &UserContext) -> Result<(Vec<HistoryStep>, Vec<HistoryStep>)> {
+    static_process(user, |cache| (cache.get_undo_steps(), cache.get_redo_steps())).await
+}
@@ -0,0 +1,245 @@
+use std::collections::HashSet;
+
+use anyhow::bail;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use tokio::select;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::core::change_feed::{ChangeFeed, subscribe};
+use crate::core::processor::static_process;
+use crate::model::audit::AuditAction;
+use crate::model::change_feed::ChangeEvent;
+use crate::model::notification::Notification;
+use crate::model::user::UserContext;
+use crate::notification::NotificationSink;
+
+const ALERT_CHANNEL_CAPACITY: usize = 64;
+
+lazy_static! {
+    static ref ALERTS: broadcast::Sender<Notification> =
+        broadcast::channel(ALERT_CHANNEL_CAPACITY).0;
+}
+
+// hands fired reminders to the connected live streams
+pub struct LiveSink;
+
+impl NotificationSink for LiveSink {
+    fn notify(&self, notification: &Notification) -> Result<()> {
+        // nobody listening is not a failed delivery
+        let _ = ALERTS.send(notification.clone());
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum LiveMessage {
+    Change(ChangeEvent),
+    Alert(Notification),
+}
+
+// changes and alerts of one user for one client, only of events in the view when it has one
+// and never of hidden or isolated calendars the view does not name
+pub struct LiveStream {
+    user: UserContext,
+    feed: ChangeFeed,
+    alerts: broadcast::Receiver<Notification>,
+    view: Option<u128>,
+    visible: HashSet<u128>, // events the client knows, to tell it when they leave
+}
+
+pub async fn open_stream(
+    user: &UserContext,
+    view: Option<u128>,
+    after: Option<u64>,
+) -> Result<LiveStream> {
+    let alerts = ALERTS.subscribe();
+    let feed = subscribe(user, after).await?;
+    let now = DateTime::from(Utc::now());
+    let visible = static_process(user, move |cache| cache.stream_events(view, now)).await??;
+    Ok(LiveStream {
+        user: user.clone(),
+        feed,
+        alerts,
+        view,
+        visible,
+    })
+}
+
+impl LiveStream {
+    pub async fn next(&mut self) -> Result<LiveMessage> {
+        loop {
+            // changes first, so an alert never overtakes the change that made its event visible
+            let message = select! {
+                biased;
+                change = self.feed.next() => LiveMessage::Change(change?),
+                alert = self.alerts.recv() => match alert {
+                    Ok(alert) if alert.user == self.user.get_name() => LiveMessage::Alert(alert),
+                    Ok(_) => continue,
+                    // alerts are only worth something when they are on time
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => bail!("alert channel closed"),
+                },
+            };
+            if self.is_visible(&message).await? {
+                return Ok(message);
+            }
+        }
+    }
+
+    async fn is_visible(&mut self, message: &LiveMessage) -> Result<bool> {
+        let view = self.view;
+        let event_id = match message {
+            LiveMessage::Change(change) if change.action == AuditAction::Deleted => {
+                return Ok(self.visible.remove(&change.event_id));
+            }
+            LiveMessage::Change(change) => change.event_id,
+            LiveMessage::Alert(alert) => alert.source_id,
+        };
+        let now = DateTime::from(Utc::now());
+        let contains = static_process(&self.user, move |cache| {
+            cache.stream_contains(view, event_id, now)
+        })
+        .await??;
+        Ok(match message {
+            LiveMessage::Alert(_) => contains,
+            // an event moving out of sight is the last change the client sees of it
+            LiveMessage::Change(_) if contains => {
+                self.visible.insert(event_id);
+                true
+            }
+            LiveMessage::Change(_) => self.visible.remove(&event_id),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{DateTime, Utc};
+    use tokio::time::timeout;
+
+    use crate::core::calendar::save_calendar;
+    use crate::core::live::{LiveMessage, LiveSink, LiveStream, open_stream};
+    use crate::core::processor::dynamic_process;
+    use crate::core::view::create_view;
+    use crate::model::audit::AuditAction;
+    use crate::model::calendar::Calendar;
+    use crate::model::event::Event;
+    use crate::model::{EventCommonTrait, ItemCommonTrait};
+    use crate::model::notification::Notification;
+    use crate::model::user::UserContext;
+    use crate::notification::NotificationSink;
+
+    fn alert(source_id: u128, user: &UserContext) -> Notification {
+        Notification {
+            source_id,
+            alarm_id: None,
+            channel: None,
+            kind: "Reminder".to_string(),
+            title: "live alert".to_string(),
+            description: String::new(),
+            fire_time: Utc::now().timestamp(),
+            user: user.get_name().to_string(),
+        }
+    }
+
+    async fn next(stream: &mut LiveStream) -> LiveMessage {
+        timeout(Duration::from_secs(5), stream.next()).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn view_streams_only_carry_events_of_the_view() {
+        let user = UserContext::new("live-saffron").unwrap();
+        let view = create_view(&user, "live saffron".to_string(), "saffron".to_string())
+            .await
+            .unwrap();
+        let mut stream = open_stream(&user, Some(view.get_id()), None).await.unwrap();
+        let now = DateTime::from(Utc::now());
+        let mut inside = Event::init(None);
+        inside.set_title("live saffron");
+        inside.set_duration(now, now);
+        let inside_id = inside.get_id();
+        let mut outside = Event::init(None);
+        outside.set_title("live cumin");
+        outside.set_duration(now, now);
+        let outside_id = outside.get_id();
+        dynamic_process(&user, move |mut cache| {
+            cache.insert_events(vec![Box::new(outside), Box::new(inside)])
+        })
+        .await
+        .unwrap();
+        match next(&mut stream).await {
+            LiveMessage::Change(change) => {
+                assert_eq!((change.event_id, change.action), (inside_id, AuditAction::Created))
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        // alerts of other users never reach the stream
+        let other = UserContext::new("live-other").unwrap();
+        LiveSink.notify(&alert(inside_id, &other)).unwrap();
+        LiveSink.notify(&alert(outside_id, &user)).unwrap();
+        LiveSink.notify(&alert(inside_id, &user)).unwrap();
+        match next(&mut stream).await {
+            LiveMessage::Alert(alert) => {
+                assert_eq!((alert.source_id, alert.user.as_str()), (inside_id, "live-saffron"))
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        dynamic_process(&user, move |mut cache| cache.delete_event(outside_id))
+            .await
+            .unwrap();
+        dynamic_process(&user, move |mut cache| cache.delete_event(inside_id))
+            .await
+            .unwrap();
+        match next(&mut stream).await {
+            LiveMessage::Change(change) => {
+                assert_eq!((change.event_id, change.action), (inside_id, AuditAction::Deleted))
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn streams_leave_out_hidden_and_isolated_calendars() {
+        let user = UserContext::new("live-calendars").unwrap();
+        let mut hidden = Calendar::new("live hidden", "");
+        hidden.visible = false;
+        let mut isolated = Calendar::new("live isolated", "");
+        isolated.isolated = true;
+        let hidden = save_calendar(&user, hidden).await.unwrap();
+        let isolated = save_calendar(&user, isolated).await.unwrap();
+        let query = "calendar:\"live hidden\"".to_string();
+        let view = create_view(&user, "live named".to_string(), query).await.unwrap();
+        let mut stream = open_stream(&user, None, None).await.unwrap();
+        let mut view_stream = open_stream(&user, Some(view.get_id()), None).await.unwrap();
+
+        let now = DateTime::from(Utc::now());
+        let mut events = vec![];
+        for calendar in [Some(hidden.get_id()), Some(isolated.get_id()), None] {
+            let mut event = Event::init(None);
+            event.set_duration(now, now);
+            event.set_calendar(calendar);
+            events.push(event);
+        }
+        let ids = events.iter().map(|event| event.get_id()).collect::<Vec<u128>>();
+        dynamic_process(&user, move |mut cache| {
+            cache.insert_events(events.into_iter().map(|e| Box::new(e) as _).collect())
+        })
+        .await
+        .unwrap();
+        match next(&mut stream).await {
+            LiveMessage::Change(change) => assert_eq!(change.event_id, ids[2]),
+            other => panic!("unexpected message {:?}", other),
+        }
+        match next(&mut view_stream).await {
+            LiveMessage::Change(change) => assert_eq!(change.event_id, ids[0]),
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+}
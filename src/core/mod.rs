@@ -3,7 +3,24 @@ use chrono::TimeZone;
 use crate::model::EventCommonTrait;
 
 mod executorPool;
-mod processor;
+pub mod audit;
+pub mod break_planner;
+pub mod calendar;
+pub mod change_feed;
+pub mod event;
+pub mod processor;
+pub mod focus;
+pub mod history;
+pub mod live;
+pub mod query;
+pub mod scheduler;
+pub mod search;
+pub mod subscription;
+pub mod sync;
+pub mod tag;
+pub mod tracking;
+pub mod transaction;
+pub mod view;
 
 //
 // pub fn create_events(
@@ -1,95 +1,289 @@
+use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::bail;
 use anyhow::Result;
-use futures::executor::block_on;
+use chrono::Utc;
 use lazy_static::lazy_static;
-use tokio::spawn;
-use tokio::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tokio::sync::{broadcast, Mutex, OnceCell, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use tokio::time::timeout;
-use tracing::{info, warn};
+use tracing::{error, info, warn};
 
 use crate::cache::Cache;
 use crate::common::exception::InternalError;
+use crate::model::audit::DEFAULT_ACTOR;
+use crate::model::change_feed::ChangeEvent;
+use crate::model::user::UserContext;
 use crate::persistent::Persistent;
 
+const LOCK_TIMEOUT: Duration = Duration::from_secs(3);
+const CHANGE_CHANNEL_CAPACITY: usize = 256;
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const EVICTION_INTERVAL: Duration = Duration::from_secs(60);
+
+// the data of one user, loaded on first use and dropped again once idle
+struct Tenant {
+    cache: RwLock<Cache>,
+    persistent: Mutex<Persistent>,
+    changes: broadcast::Sender<ChangeEvent>,
+    published_cursor: Mutex<u64>,
+    last_used: std::sync::Mutex<Option<Instant>>, // None until used by the user themselves
+}
+
 lazy_static! {
-    static ref PERSISTENT_SYSTEM: Mutex<Persistent> = Mutex::new(Persistent::init());
-    static ref CACHE: Arc<RwLock<Cache>> = {
-        let cache = block_on(block_on(PERSISTENT_SYSTEM.lock()).load());
-        Arc::new(RwLock::new(if cache.is_ok() {
-            cache.unwrap()
-        } else {
-            Cache::init()
-        }))
-    };
+    // a cell per user, so loading one user holds up neither the others nor itself twice
+    static ref TENANTS: Mutex<HashMap<String, Arc<OnceCell<Arc<Tenant>>>>> =
+        Mutex::new(HashMap::new());
+    // when users that were dropped have a reminder to fire next, in milliseconds
+    static ref WAKE_TIMES: std::sync::Mutex<HashMap<String, i64>> =
+        std::sync::Mutex::new(HashMap::new());
 }
 
-pub async fn dynamic_process<F: FnOnce(RwLockWriteGuard<Cache>) + Send + 'static>(
-    process_func: F,
-) -> Result<()> {
+// data that can not be read is reported instead of starting over, which would replace it
+// with the next save. a failed load is tried again on the next call
+async fn tenant(user: &UserContext) -> Result<Arc<Tenant>> {
+    let cell = TENANTS
+        .lock()
+        .await
+        .entry(user.get_name().to_string())
+        .or_default()
+        .clone();
+    let tenant = cell
+        .get_or_try_init(|| async {
+            let persistent = Persistent::for_user(user);
+            let cache = persistent.load().await?;
+            info!("load data of user {}", user.get_name());
+            WAKE_TIMES.lock().unwrap().remove(user.get_name());
+            Ok::<Arc<Tenant>, anyhow::Error>(Arc::new(Tenant {
+                cache: RwLock::new(cache),
+                persistent: Mutex::new(persistent),
+                changes: broadcast::channel(CHANGE_CHANNEL_CAPACITY).0,
+                published_cursor: Mutex::new(0),
+                last_used: std::sync::Mutex::new(None),
+            }))
+        })
+        .await?
+        .clone();
+    if !user.is_background() {
+        *tenant.last_used.lock().unwrap() = Some(Instant::now());
+    }
+    Ok(tenant)
+}
+
+// runs the closure on the cache of the user and saves it before returning its result,
+// a failed save is reported as an unsaved change even when the closure succeeded
+pub async fn dynamic_process<T, F>(user: &UserContext, process_func: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(RwLockWriteGuard<Cache>) -> Result<T> + Send + 'static,
+{
     info!("start dynamic process");
-    let result = async {
-        info!("try to get cache write lock");
-        let cache = CACHE.write().await;
-        info!("get cache write lock success");
-        async {
-            process_func(cache);
-        }.await
-    };
-    match timeout(Duration::from_secs(3), result).await {
-        Ok(_) => {
-            info!("dynamic process success");
-            let cache_replicas = CACHE.clone();
-            spawn(async move {
-                let cache = cache_replicas.read().await;
-                let persistent = PERSISTENT_SYSTEM.lock().await;
-                persistent
-                    .save(&*cache)
-                    .await
-                    .expect("save cache failed");
-                drop(cache);
-            });
-            Ok(())
-        }
+    let tenant = tenant(user).await?;
+    info!("try to get cache write lock");
+    let mut cache = match timeout(LOCK_TIMEOUT, tenant.cache.write()).await {
+        Ok(cache) => cache,
         Err(_) => {
             warn!("dynamic process timeout");
             bail!(InternalError::BusyCache)
         }
+    };
+    info!("get cache write lock success");
+    // everything the closure changes is undone as one step and audited as made by the
+    // user, background calls as made by the server
+    cache.commit_history_step();
+    cache.set_actor(if user.is_background() { DEFAULT_ACTOR } else { user.get_name() });
+    let cursor = cache.get_change_cursor();
+    let result = process_func(cache);
+    publish_changes(&tenant, cursor).await;
+    // the closure may have changed the cache before failing, so it is saved anyway. what
+    // could not be saved stays in memory and is saved again with the next change
+    let saved = save_cache(&tenant).await;
+    let result = match (result, saved) {
+        (Ok(result), Ok(())) => result,
+        (Ok(_), Err(error)) => return Err(error.context(InternalError::UnsavedChangeError)),
+        (Err(error), Ok(())) => return Err(error),
+        (Err(error), Err(save_error)) => {
+            let context = format!("saving what it changed failed too: {:#}", save_error);
+            return Err(error.context(context));
+        }
+    };
+    info!("dynamic process success");
+    Ok(result)
+}
+
+// sends the changes after the cursor that were not sent yet, in cursor order
+async fn publish_changes(tenant: &Tenant, cursor: u64) {
+    let mut published = tenant.published_cursor.lock().await;
+    let cache = tenant.cache.read().await;
+    let changes = cache
+        .get_changes_after(cursor.max(*published))
+        .unwrap_or_else(|_| cache.get_change_log());
+    drop(cache);
+    for change in changes {
+        *published = change.cursor;
+        // without subscribers there is nobody to tell
+        let _ = tenant.changes.send(change);
     }
 }
 
-pub async fn static_process<T: Clone, F: Fn(RwLockReadGuard<Cache>) -> T>(
+// subscribe before reading the cache, so no change falls between both. the user stays
+// loaded while subscribed
+pub async fn subscribe_changes(user: &UserContext) -> Result<broadcast::Receiver<ChangeEvent>> {
+    Ok(tenant(user).await?.changes.subscribe())
+}
+
+async fn save_cache(tenant: &Tenant) -> Result<()> {
+    let cache = tenant.cache.read().await;
+    let persistent = tenant.persistent.lock().await;
+    persistent.save(&cache).await.inspect_err(|error| {
+        error!("save cache failed: {:#}", error);
+    })
+}
+
+pub async fn static_process<T, F: FnOnce(RwLockReadGuard<Cache>) -> T>(
+    user: &UserContext,
     read_function: F,
 ) -> Result<T> {
     info!("start static process");
-    let cache_reader = CACHE.read().await;
+    let tenant = tenant(user).await?;
+    let cache_reader = tenant.cache.read().await;
     Ok(read_function(cache_reader))
 }
 
+// drops the users nobody used for the given time and nothing is running or streaming
+// for. every change was saved already, only when to wake them for a reminder is kept
+pub async fn evict_idle_users(idle: Duration) -> Vec<String> {
+    let now = Utc::now().fixed_offset();
+    let mut tenants = TENANTS.lock().await;
+    // a cell someone else holds is being loaded or about to be used
+    let idle_users = tenants
+        .iter()
+        .filter(|(_, cell)| Arc::strong_count(cell) == 1)
+        .filter(|(_, cell)| {
+            cell.get().is_some_and(|tenant| {
+                Arc::strong_count(tenant) == 1
+                    && tenant.changes.receiver_count() == 0
+                    && tenant
+                        .last_used
+                        .lock()
+                        .unwrap()
+                        .is_none_or(|used| used.elapsed() >= idle)
+            })
+        })
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<String>>();
+    for name in &idle_users {
+        let Some(tenant) = tenants.remove(name).and_then(|cell| cell.get().cloned()) else {
+            continue;
+        };
+        if let Some(time) = tenant.cache.read().await.get_next_notification_time(now) {
+            WAKE_TIMES.lock().unwrap().insert(name.clone(), time);
+        }
+        info!("drop idle data of user {}", name);
+    }
+    idle_users
+}
+
+pub fn start_evictor() -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(EVICTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            evict_idle_users(IDLE_TIMEOUT).await;
+        }
+    })
+}
+
+// users stored on disk are checked for due reminders once, then only when woken
+pub fn wake_stored_users() {
+    let mut wake_times = WAKE_TIMES.lock().unwrap();
+    for user in Persistent::stored_users() {
+        wake_times.entry(user.get_name().to_string()).or_insert(0);
+    }
+}
+
+pub async fn loaded_users() -> Vec<UserContext> {
+    let mut users = TENANTS
+        .lock()
+        .await
+        .iter()
+        .filter(|(_, cell)| cell.initialized())
+        .filter_map(|(name, _)| UserContext::new(name).ok())
+        .map(|user| user.background())
+        .collect::<Vec<UserContext>>();
+    users.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+    users
+}
+
+// the loaded users and those with a reminder due by now, in milliseconds
+pub async fn scheduled_users(now: i64) -> Vec<UserContext> {
+    let mut users = loaded_users().await;
+    let woken = WAKE_TIMES
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, time)| **time <= now)
+        .filter_map(|(name, _)| UserContext::new(name).ok())
+        .map(|user| user.background())
+        .collect::<Vec<UserContext>>();
+    for user in woken {
+        if !users.contains(&user) {
+            users.push(user);
+        }
+    }
+    users
+}
+
+#[cfg(test)]
 mod tests {
     use std::thread;
     use std::time::Duration;
 
     use chrono::{DateTime, Utc};
-    use futures::executor::block_on;
     use tokio::join;
-    use tokio::time::sleep;
 
-    use crate::core::processor::{CACHE, dynamic_process, static_process};
+    use crate::common::exception::InternalError;
+    use crate::core::processor::{
+        dynamic_process, evict_idle_users, IDLE_TIMEOUT, scheduled_users, static_process, tenant,
+    };
+    use crate::model::audit::DEFAULT_ACTOR;
     use crate::model::event::Event;
-    use crate::model::EventCommonTrait;
+    use crate::model::{EventCommonTrait, ItemCommonTrait};
+    use crate::model::reminder::Reminder;
+    use crate::model::user::UserContext;
+    use crate::persistent::Persistent;
 
     #[tokio::test]
     async fn test_dynamic_process() {
-        let result = dynamic_process(|mut e| {
+        let user = UserContext::default_user();
+        let result = dynamic_process(&user, |mut e| {
             let mut event = Event::init(None);
             event.set_duration(DateTime::from(Utc::now()), DateTime::from(Utc::now()));
-            e.insert_events(vec![Box::new(event)]).unwrap()
+            e.insert_events(vec![Box::new(event)])
         });
         assert!(result.await.is_ok());
-        assert!(CACHE.read().await.get_all_events::<Event>().len() > 0);
+        let tenant = tenant(&user).await.unwrap();
+        assert!(!tenant.cache.read().await.get_all_events::<Event>().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_process_returns_closure_result() {
+        let user = UserContext::default_user();
+        let id = dynamic_process(&user, |mut e| {
+            let event = Event::init(None);
+            let id = event.get_id();
+            e.insert_events(vec![Box::new(event)]).map(|_| id)
+        })
+        .await
+        .unwrap();
+        let tenant = tenant(&user).await.unwrap();
+        assert!(tenant.cache.read().await.get_events_by_id::<Event>(id).is_ok());
+        let result = dynamic_process(&user, |mut e| e.delete_event(1)).await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref::<InternalError>(),
+            Some(InternalError::EventNotFoundError)
+        ));
     }
 
     #[tokio::test]
@@ -97,19 +291,19 @@ mod tests {
         let mut event = Event::init(None);
         let id = event.get_id();
         event.set_duration(DateTime::from(Utc::now()), DateTime::from(Utc::now()));
-        CACHE
+        let user = UserContext::default_user();
+        tenant(&user)
+            .await
+            .unwrap()
+            .cache
             .write()
             .await
             .insert_events(vec![Box::new(event)])
             .unwrap();
-        let result = static_process(move |e| {
+        let result = static_process(&user, move |e| {
             e.get_all_events::<Event>()
                 .iter()
-                .map(|e| {
-                    let e = **e.clone();
-                    let e = e.clone();
-                    e
-                })
+                .map(|e| (***e).clone())
                 .collect::<Vec<Event>>()
         })
             .await;
@@ -123,35 +317,32 @@ mod tests {
         let mut event = Event::init(None);
         let id = event.get_id();
         event.set_duration(DateTime::from(Utc::now()), DateTime::from(Utc::now()));
-        CACHE
+        let user = UserContext::default_user();
+        tenant(&user)
+            .await
+            .unwrap()
+            .cache
             .write()
             .await
             .insert_events(vec![Box::new(event)])
             .unwrap();
-        let result = dynamic_process(|_| {
-            block_on(sleep(Duration::from_secs(10)));
+        let result = dynamic_process(&user, |_| {
+            thread::sleep(Duration::from_secs(10));
+            Ok(())
         })
             .await;
         assert!(result.is_ok());
-        let first_result = static_process(|e| {
+        let first_result = static_process(&user, |e| {
             e.get_all_events::<Event>()
                 .iter()
-                .map(|e| {
-                    let e = **e.clone();
-                    let e = e.clone();
-                    e
-                })
+                .map(|e| (***e).clone())
                 .collect::<Vec<Event>>()
         })
             .await;
         assert!(first_result.is_ok());
-        let result = static_process(|e| {
+        let result = static_process(&user, |e| {
             e.get_events_by_id::<Event>(id)
-                .map(|e| {
-                    let e = **e.clone();
-                    let e = e.clone();
-                    e
-                })
+                .map(|e| (**e).clone())
                 .unwrap()
         })
             .await
@@ -161,12 +352,107 @@ mod tests {
 
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_process_conflict() {
-        let result_0 = dynamic_process(|_| {
+        let user = UserContext::default_user();
+        let result_0 = dynamic_process(&user, |_| {
             thread::sleep(Duration::from_secs(10));
+            Ok(())
         });
-        let result_1 = dynamic_process(|_| {});
+        let result_1 = dynamic_process(&user, |_| Ok(()));
         let (a, b) = join!(result_0, result_1);
         assert!(a.is_ok());
         assert!(b.is_err());
     }
+
+    #[tokio::test]
+    async fn users_keep_their_own_data_across_eviction() {
+        let alice = UserContext::new("processor-alice").unwrap();
+        let bob = UserContext::new("processor-bob").unwrap();
+        let carol = UserContext::new("processor-carol").unwrap();
+        let event = Event::init(None);
+        let id = event.get_id();
+        dynamic_process(&alice, move |mut e| e.insert_events(vec![Box::new(event)]))
+            .await
+            .unwrap();
+        let found = static_process(&bob, move |e| e.get_events_by_id::<Event>(id).is_ok());
+        assert!(!found.await.unwrap());
+        let actor = static_process(&alice, move |e| e.get_event_audit(id)[0].actor.clone());
+        assert_eq!(actor.await.unwrap(), "processor-alice");
+
+        // carol is only ever used in the background, so she is dropped right away
+        let now = Utc::now();
+        let mut reminder = Reminder::init(None);
+        let reminder_id = reminder.get_id();
+        let start = DateTime::from(now + chrono::Duration::hours(1));
+        reminder.set_duration(start, start);
+        let background = carol.background();
+        dynamic_process(&background, move |mut e| e.insert_events(vec![Box::new(reminder)]))
+            .await
+            .unwrap();
+        let evicted = evict_idle_users(IDLE_TIMEOUT).await;
+        assert!(evicted.contains(&"processor-carol".to_string()));
+        assert!(!evicted.contains(&"processor-alice".to_string()));
+
+        // and woken again for her reminder
+        let woken = scheduled_users(now.timestamp_millis()).await;
+        assert!(!woken.contains(&background));
+        let later = (now + chrono::Duration::hours(2)).timestamp_millis();
+        assert!(scheduled_users(later).await.contains(&background));
+        let found = static_process(&carol, move |e| {
+            e.get_events_by_id::<Reminder>(reminder_id).is_ok()
+        });
+        assert!(found.await.unwrap());
+        let actor = static_process(&carol, move |e| {
+            e.get_event_audit(reminder_id)[0].actor.clone()
+        });
+        assert_eq!(actor.await.unwrap(), DEFAULT_ACTOR);
+    }
+
+    #[tokio::test]
+    async fn unsaved_changes_are_reported_and_kept() {
+        let user = UserContext::new("processor-dora").unwrap();
+        static_process(&user, |_| ()).await.unwrap();
+        // a directory in the way of the file cannot be replaced by the save
+        let path = Persistent::for_user(&user).get_file_path().to_string();
+        let blocked = std::path::Path::new(path.as_str());
+        std::fs::create_dir_all(blocked.join("content")).unwrap();
+        let event = Event::init(None);
+        let id = event.get_id();
+        let result = dynamic_process(&user, move |mut e| e.insert_events(vec![Box::new(event)]));
+        let error = result.await.unwrap_err();
+        std::fs::remove_dir_all(blocked).unwrap();
+        assert!(matches!(
+            error.downcast_ref::<InternalError>(),
+            Some(InternalError::UnsavedChangeError)
+        ));
+        let found = static_process(&user, move |e| e.get_events_by_id::<Event>(id).is_ok());
+        assert!(found.await.unwrap());
+
+        std::fs::create_dir_all(blocked.join("content")).unwrap();
+        let result = dynamic_process(&user, |mut e| e.delete_event(1)).await;
+        std::fs::remove_dir_all(blocked).unwrap();
+        let error = result.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<InternalError>(),
+            Some(InternalError::EventNotFoundError)
+        ));
+        assert!(error.to_string().contains("failed too"));
+    }
+
+    #[tokio::test]
+    async fn unreadable_data_is_reported_and_left_alone() {
+        let user = UserContext::new("processor-erin").unwrap();
+        let file = std::path::PathBuf::from(Persistent::for_user(&user).get_file_path());
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(&file, "not a calendar").unwrap();
+        let result = dynamic_process(&user, |mut e| e.insert_events(vec![]));
+        assert!(matches!(
+            result.await.unwrap_err().downcast_ref::<InternalError>(),
+            Some(InternalError::DataPersistenceError)
+        ));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "not a calendar");
+
+        // and read once it can be
+        std::fs::remove_file(&file).unwrap();
+        assert!(static_process(&user, |e| e.get_all_events::<Event>().is_empty()).await.unwrap());
+    }
 }
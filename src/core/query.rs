@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use anyhow::Result;
+
+use crate::common::exception::InternalError;
+use crate::core::processor::static_process;
+use crate::model::EventCommonTrait;
+use crate::model::user::UserContext;
+use crate::query::parse_query;
+
+pub async fn query_events(
+    user: &UserContext,
+    query: &str,
+) -> Result<Vec<Arc<Box<dyn EventCommonTrait>>>> {
+    let filter = parse_query(query)?;
+    static_process(user, move |cache| cache.query_events(&filter)).await
+}
+
+// the events together with the revision of the cache they were read at
+pub async fn query_events_at_revision(
+    user: &UserContext,
+    query: &str,
+) -> Result<(u64, Vec<Arc<Box<dyn EventCommonTrait>>>)> {
+    let filter = parse_query(query)?;
+    static_process(user, move |cache| (cache.get_revision(), cache.query_events(&filter))).await
+}
+
+// leaves out the events of subscribed feeds, which are not ours to serve or sync
+pub async fn query_own_events(
+    user: &UserContext,
+    query: &str,
+) -> Result<Vec<Arc<Box<dyn EventCommonTrait>>>> {
+    let filter = parse_query(query)?;
+    static_process(user, move |cache| cache.query_own_events(&filter)).await
+}
+
+// stored events without the ones of hidden calendars the query does not name
+pub async fn query_shown_events(
+    user: &UserContext,
+    query: &str,
+) -> Result<Vec<Arc<Box<dyn EventCommonTrait>>>> {
+    let filter = parse_query(query)?;
+    static_process(user, move |cache| cache.query_shown_events(&filter)).await
+}
+
+// an event of a hidden calendar is not found
+pub async fn get_shown_event(
+    user: &UserContext,
+    id: u128,
+) -> Result<Arc<Box<dyn EventCommonTrait>>> {
+    static_process(user, move |cache| cache.get_shown_event(id))
+        .await?
+        .ok_or(anyhow!(InternalError::EventNotFoundError))
+}
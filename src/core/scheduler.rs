@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, Utc};
+use tokio::spawn;
+use tokio::task::{JoinHandle, spawn_blocking};
+use tracing::{info, warn};
+
+use crate::core::processor::{dynamic_process, scheduled_users, static_process};
+use crate::model::notification::Notification;
+use crate::model::user::UserContext;
+use crate::notification::NotificationSink;
+
+pub struct ReminderScheduler {
+    sinks: Vec<Arc<dyn NotificationSink>>,
+    channels: HashMap<String, Vec<Arc<dyn NotificationSink>>>, // sinks for alarms with a channel
+    interval: Duration,
+    grace: chrono::Duration, // how late a missed reminder still fires, e.g. after a restart
+}
+
+impl ReminderScheduler {
+    pub fn new() -> Self {
+        ReminderScheduler {
+            sinks: vec![],
+            channels: HashMap::new(),
+            interval: Duration::from_secs(30),
+            grace: chrono::Duration::minutes(10),
+        }
+    }
+
+    pub fn with_sink<S: NotificationSink + 'static>(mut self, sink: S) -> Self {
+        self.sinks.push(Arc::new(sink));
+        self
+    }
+
+    pub fn with_channel_sink<S: NotificationSink + 'static>(
+        mut self,
+        channel: &str,
+        sink: S,
+    ) -> Self {
+        self.channels
+            .entry(channel.to_string())
+            .or_default()
+            .push(Arc::new(sink));
+        self
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_grace(mut self, grace: chrono::Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    // fire everything due at now for the loaded users and those woken for a reminder, a
+    // failing user does not stop the others
+    pub async fn tick(&self, now: DateTime<FixedOffset>) -> Result<Vec<Notification>> {
+        let mut fired = vec![];
+        for user in scheduled_users(now.timestamp_millis()).await {
+            match self.tick_user(&user, now).await {
+                Ok(notifications) => fired.extend(notifications),
+                Err(e) => warn!("reminder tick of user {} failed: {}", user.get_name(), e),
+            }
+        }
+        Ok(fired)
+    }
+
+    // a failing sink does not stop the others
+    pub async fn tick_user(
+        &self,
+        user: &UserContext,
+        now: DateTime<FixedOffset>,
+    ) -> Result<Vec<Notification>> {
+        let since = now - self.grace;
+        let mut due =
+            static_process(user, move |cache| cache.get_due_notifications(since, now)).await?;
+        if due.is_empty() {
+            return Ok(due);
+        }
+        for notification in due.iter_mut() {
+            notification.user = user.get_name().to_string();
+        }
+        for notification in &due {
+            // unknown channels fall back to the default sinks instead of being dropped
+            let sinks = notification
+                .channel
+                .as_ref()
+                .and_then(|channel| self.channels.get(channel))
+                .unwrap_or(&self.sinks);
+            for sink in sinks {
+                let sink = sink.clone();
+                let notification = notification.clone();
+                let result = spawn_blocking(move || sink.notify(&notification)).await?;
+                if let Err(e) = result {
+                    warn!("notification delivery failed: {}", e);
+                }
+            }
+        }
+        let fired = due
+            .iter()
+            .map(|n| (n.source_id, n.alarm_id))
+            .collect::<Vec<(u128, Option<u128>)>>();
+        dynamic_process(user, move |mut cache| {
+            for (source_id, alarm_id) in fired {
+                cache.mark_notification_fired(source_id, alarm_id, now);
+            }
+            Ok(())
+        })
+        .await?;
+        info!("fired {} notifications", due.len());
+        Ok(due)
+    }
+
+    pub fn start(self) -> JoinHandle<()> {
+        spawn(async move {
+            let mut interval = tokio::time::interval(self.interval);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.tick(DateTime::from(Utc::now())).await {
+                    warn!("reminder scheduler tick failed: {}", e);
+                }
+            }
+        })
+    }
+}
+
+// alarm id is None for a standalone reminder
+pub async fn snooze_reminder(
+    user: &UserContext,
+    source_id: u128,
+    alarm_id: Option<u128>,
+    until: DateTime<FixedOffset>,
+) -> Result<()> {
+    dynamic_process(user, move |mut cache| {
+        cache.snooze_notification(source_id, alarm_id, until)
+    })
+    .await
+}
+
+pub async fn dismiss_reminder(
+    user: &UserContext,
+    source_id: u128,
+    alarm_id: Option<u128>,
+) -> Result<()> {
+    dynamic_process(user, move |mut cache| cache.dismiss_notification(source_id, alarm_id)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::Result;
+    use chrono::{DateTime, Duration, FixedOffset, Utc};
+
+    use crate::core::processor::dynamic_process;
+    use crate::core::scheduler::{ReminderScheduler, dismiss_reminder, snooze_reminder};
+    use crate::model::{EventCommonTrait, ItemCommonTrait};
+    use crate::model::notification::Notification;
+    use crate::model::reminder::Reminder;
+    use crate::model::user::UserContext;
+    use crate::notification::NotificationSink;
+
+    struct RecordSink(Arc<Mutex<Vec<u128>>>);
+
+    impl NotificationSink for RecordSink {
+        fn notify(&self, notification: &Notification) -> Result<()> {
+            self.0.lock().unwrap().push(notification.source_id);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn tick_fires_once_and_again_after_snooze() {
+        let user = UserContext::new("scheduler-reminder").unwrap();
+        let now: DateTime<FixedOffset> = DateTime::from(Utc::now());
+        let mut reminder = Reminder::init(None);
+        let id = reminder.get_id();
+        reminder.set_duration(now - Duration::seconds(2), now - Duration::seconds(1));
+        dynamic_process(&user, move |mut cache| cache.insert_events(vec![Box::new(reminder)]))
+            .await
+            .unwrap();
+        let fired = Arc::new(Mutex::new(vec![]));
+        let scheduler = ReminderScheduler::new().with_sink(RecordSink(fired.clone()));
+
+        scheduler.tick(now).await.unwrap();
+        scheduler.tick(now).await.unwrap();
+        assert_eq!(fired.lock().unwrap().iter().filter(|i| **i == id).count(), 1);
+
+        snooze_reminder(&user, id, None, now + Duration::minutes(5)).await.unwrap();
+        scheduler.tick(now).await.unwrap();
+        assert_eq!(fired.lock().unwrap().iter().filter(|i| **i == id).count(), 1);
+        scheduler.tick(now + Duration::minutes(5)).await.unwrap();
+        assert_eq!(fired.lock().unwrap().iter().filter(|i| **i == id).count(), 2);
+
+        snooze_reminder(&user, id, None, now + Duration::minutes(10)).await.unwrap();
+        dismiss_reminder(&user, id, None).await.unwrap();
+        scheduler.tick(now + Duration::minutes(10)).await.unwrap();
+        assert_eq!(fired.lock().unwrap().iter().filter(|i| **i == id).count(), 2);
+        assert!(snooze_reminder(&user, 1, None, now).await.is_err());
+    }
+}
@@ -0,0 +1,9 @@
+use anyhow::Result;
+
+use crate::core::processor::static_process;
+use crate::model::search::{SearchHit, SearchQuery};
+use crate::model::user::UserContext;
+
+pub async fn search_events(user: &UserContext, query: SearchQuery) -> Result<Vec<SearchHit>> {
+    static_process(user, move |cache| cache.search_events(&query)).await
+}
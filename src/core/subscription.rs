@@ -0,0 +1,347 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Result;
+use chrono::Utc;
+use reqwest::header::LOCATION;
+use reqwest::redirect::Policy;
+use reqwest::{Client, Url};
+use tokio::net::lookup_host;
+use tokio::spawn;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::common::exception::InternalError;
+use crate::core::processor::{dynamic_process, loaded_users, static_process};
+use crate::model::subscription::Subscription;
+use crate::model::user::UserContext;
+
+const REFRESH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const MAX_FEED_REDIRECTS: usize = 5;
+
+// the feed is fetched once before subscribing, so a wrong source is not kept. local
+// sources are only for feeds the owner of the machine adds
+pub async fn subscribe_feed(
+    user: &UserContext,
+    name: String,
+    source: String,
+    refresh_minutes: Option<i64>,
+    count_conflicts: bool,
+    local: bool,
+) -> Result<Subscription> {
+    let mut subscription = Subscription::new(name.as_str(), source.as_str());
+    if let Some(refresh_minutes) = refresh_minutes {
+        subscription.refresh_minutes = refresh_minutes;
+    }
+    subscription.count_conflicts = count_conflicts;
+    subscription.local = local;
+    subscription.content = fetch_feed(source.as_str(), local).await?;
+    subscription.refreshed_time = Some(Utc::now().timestamp_millis());
+    let created = subscription.clone();
+    dynamic_process(user, move |mut cache| cache.add_subscription(subscription)).await?;
+    Ok(created)
+}
+
+pub async fn unsubscribe_feed(user: &UserContext, id: u128) -> Result<()> {
+    dynamic_process(user, move |mut cache| cache.delete_subscription(id)).await
+}
+
+pub async fn get_subscriptions(user: &UserContext) -> Result<Vec<Subscription>> {
+    static_process(user, |cache| cache.get_all_subscriptions()).await
+}
+
+// subscriptions are addressed by id or by name
+pub async fn find_subscription(user: &UserContext, key: String) -> Result<Subscription> {
+    static_process(user, move |cache| {
+        key.parse::<u128>()
+            .ok()
+            .and_then(|id| cache.get_subscription(id).ok())
+            .or_else(|| cache.get_subscription_by_name(key.as_str()).ok())
+    })
+    .await?
+    .ok_or(anyhow!(InternalError::SubscriptionNotFoundError))
+}
+
+// returns how many events the feed holds now, a failed refresh keeps the previous ones
+pub async fn refresh_subscription(user: &UserContext, id: u128) -> Result<usize> {
+    let subscription = static_process(user, move |cache| cache.get_subscription(id)).await??;
+    let now = Utc::now().timestamp_millis();
+    match fetch_feed(subscription.source.as_str(), subscription.local).await {
+        Ok(content) => {
+            dynamic_process(user, move |mut cache| cache.refresh_feed(id, content, now)).await
+        }
+        Err(error) => {
+            let reason = error.to_string();
+            dynamic_process(user, move |mut cache| cache.fail_feed(id, reason, now)).await?;
+            Err(error)
+        }
+    }
+}
+
+// a failing feed does not stop the others
+pub async fn refresh_due_subscriptions(user: &UserContext) -> Result<usize> {
+    let now = Utc::now().timestamp_millis();
+    let due = static_process(user, move |cache| {
+        cache
+            .get_all_subscriptions()
+            .into_iter()
+            .filter(|s| s.is_due(now))
+            .collect::<Vec<Subscription>>()
+    })
+    .await?;
+    let mut refreshed = 0;
+    for subscription in due {
+        match refresh_subscription(user, subscription.get_id()).await {
+            Ok(events) => {
+                info!("feed {} refreshed with {} events", subscription.name, events);
+                refreshed += 1;
+            }
+            Err(e) => warn!("feed {} refresh failed: {}", subscription.name, e),
+        }
+    }
+    Ok(refreshed)
+}
+
+pub fn start_refresher() -> JoinHandle<()> {
+    spawn(async {
+        let mut interval = tokio::time::interval(REFRESH_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            // feeds of users that are not loaded are refreshed once they are back
+            for user in loaded_users().await {
+                if let Err(e) = refresh_due_subscriptions(&user).await {
+                    warn!("feed refresh of user {} failed: {}", user.get_name(), e);
+                }
+            }
+        }
+    })
+}
+
+// webcal is how feeds are usually published, it is fetched over https. errors name
+// neither the source nor what failed, the details are only logged
+async fn fetch_feed(source: &str, local: bool) -> Result<String> {
+    let url = match source.strip_prefix("webcal://") {
+        Some(rest) => format!("https://{}", rest),
+        None => source.to_string(),
+    };
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        if !local {
+            bail!(InternalError::InvalidFeedSourceError)
+        }
+        return tokio::fs::read_to_string(source).await.map_err(|error| {
+            warn!("feed file {} can not be read: {}", source, error);
+            unreadable_feed()
+        });
+    }
+    let mut url = Url::parse(url.as_str()).map_err(|_| InternalError::InvalidFeedSourceError)?;
+    // redirects are followed here, so the host of every hop is checked
+    for _ in 0..=MAX_FEED_REDIRECTS {
+        let response = feed_client(&url, local)
+            .await?
+            .get(url.clone())
+            .send()
+            .await
+            .map_err(|error| {
+                warn!("feed {} can not be fetched: {}", url, error);
+                unreadable_feed()
+            })?;
+        if response.status().is_redirection() {
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| url.join(location).ok());
+            match location {
+                Some(next) if next.scheme() == "http" || next.scheme() == "https" => url = next,
+                _ => bail!(InternalError::InvalidFeedSourceError),
+            }
+            continue;
+        }
+        if !response.status().is_success() {
+            bail!(InternalError::RemoteCalendarError {
+                reason: format!("the server responded {}", response.status())
+            })
+        }
+        return response.text().await.map_err(|error| {
+            warn!("feed {} can not be read: {}", url, error);
+            unreadable_feed()
+        });
+    }
+    bail!(InternalError::RemoteCalendarError {
+        reason: "the feed redirects too often".to_string()
+    })
+}
+
+// the client only connects to the addresses checked here, so the host can not resolve
+// to another one in between
+async fn feed_client(url: &Url, local: bool) -> Result<Client> {
+    let port = url.port_or_known_default().unwrap_or(443);
+    let host = url.host_str().ok_or(InternalError::InvalidFeedSourceError)?;
+    // ipv6 hosts are written in brackets
+    let (domain, addresses) = match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(ip) => (None, vec![SocketAddr::new(ip, port)]),
+        Err(_) => {
+            let addresses = lookup_host((host, port)).await.map_err(|error| {
+                warn!("feed host {} can not be resolved: {}", host, error);
+                unreadable_feed()
+            })?;
+            (Some(host), addresses.collect::<Vec<SocketAddr>>())
+        }
+    };
+    if addresses.is_empty() {
+        return Err(unreadable_feed());
+    }
+    if !local && addresses.iter().any(|address| !is_public(address.ip())) {
+        bail!(InternalError::InvalidFeedSourceError)
+    }
+    let mut builder = Client::builder().redirect(Policy::none()).no_proxy();
+    if let Some(domain) = domain {
+        builder = builder.resolve_to_addrs(domain, addresses.as_slice());
+    }
+    Ok(builder.build()?)
+}
+
+// whether the address is reachable from anywhere, not only from our own network
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let shared = ip.octets()[0] == 100 && ip.octets()[1] & 0xc0 == 64;
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || shared)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+fn unreadable_feed() -> anyhow::Error {
+    anyhow!(InternalError::RemoteCalendarError {
+        reason: "the feed can not be read".to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::{DateTime, Duration, FixedOffset};
+
+    use crate::common::exception::InternalError;
+    use crate::core::event::delete_event;
+    use crate::core::processor::dynamic_process;
+    use crate::core::query::{query_events, query_own_events};
+    use crate::core::subscription::{
+        find_subscription, refresh_subscription, subscribe_feed, unsubscribe_feed,
+    };
+    use crate::model::EventCommonTrait;
+    use crate::model::event::Event;
+    use crate::model::user::UserContext;
+
+    fn feed(summary: &str) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nUID:founders@example.com\r\n\
+             DTSTART:20330301T090000Z\r\nDTEND:20330301T170000Z\r\nSUMMARY:{}\r\n\
+             END:VEVENT\r\nEND:VCALENDAR\r\n",
+            summary
+        )
+    }
+
+    #[tokio::test]
+    async fn feed_events_are_read_only_overlays() {
+        let user = UserContext::new("feed-juniper").unwrap();
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("team.ics");
+        fs::write(&path, feed("team offsite juniper")).unwrap();
+        let source = path.to_str().unwrap().to_string();
+        let subscription =
+            subscribe_feed(&user, "team juniper".to_string(), source.clone(), None, true, true)
+                .await
+                .unwrap();
+        let id = subscription.get_id();
+
+        let events = query_events(&user, "juniper").await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(query_own_events(&user, "juniper").await.unwrap().is_empty());
+        assert!(query_events(&user, "juniper -feed:*").await.unwrap().is_empty());
+        assert_eq!(query_events(&user, "feed:\"team juniper\"").await.unwrap().len(), 1);
+        let feed_event_id = events[0].get_id();
+        let error = delete_event(&user, feed_event_id, None).await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<InternalError>(),
+            Some(InternalError::ReadOnlyEventError { .. })
+        ));
+
+        // the subscription counts conflicts, so our events may not overlap the offsite
+        let start: DateTime<FixedOffset> =
+            DateTime::parse_from_rfc3339("2033-03-01T10:00:00Z").unwrap();
+        let mut event = Event::init(None);
+        event.set_duration(start, start + Duration::hours(1));
+        let overlapping = Box::new(event.clone());
+        assert!(dynamic_process(&user, move |mut cache| cache.insert_events(vec![overlapping]))
+            .await
+            .is_err());
+
+        fs::write(&path, feed("team offsite juniper moved")).unwrap();
+        assert_eq!(refresh_subscription(&user, id).await.unwrap(), 1);
+        let events = query_events(&user, "juniper").await.unwrap();
+        assert_eq!(events[0].get_title(), "team offsite juniper moved");
+        assert_eq!(events[0].get_id(), feed_event_id);
+
+        // an unreadable feed keeps what was read before
+        fs::remove_file(&path).unwrap();
+        assert!(refresh_subscription(&user, id).await.is_err());
+        let subscription = find_subscription(&user, "team juniper".to_string()).await.unwrap();
+        assert!(!subscription.error.unwrap().contains("team.ics"));
+        assert_eq!(query_events(&user, "juniper").await.unwrap().len(), 1);
+
+        unsubscribe_feed(&user, id).await.unwrap();
+        assert!(query_events(&user, "juniper").await.unwrap().is_empty());
+        dynamic_process(&user, move |mut cache| cache.insert_events(vec![Box::new(event)]))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn shared_feeds_are_only_read_from_public_urls() {
+        let user = UserContext::new("feed-shared").unwrap();
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("secret.ics");
+        fs::write(&path, feed("secret")).unwrap();
+        let sources = [
+            path.to_str().unwrap(),
+            "ftp://example.com/feed.ics",
+            "http://127.0.0.1:1/feed.ics",
+            "http://169.254.169.254/latest/meta-data",
+            "webcal://[::1]/feed.ics",
+            "https://localhost/feed.ics",
+        ];
+        for source in sources {
+            let name = "shared".to_string();
+            let error = subscribe_feed(&user, name, source.to_string(), None, false, false)
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<InternalError>(),
+                Some(InternalError::InvalidFeedSourceError)
+            ));
+            assert!(!error.to_string().contains("secret"));
+        }
+        assert!(find_subscription(&user, "shared".to_string()).await.is_err());
+    }
+}
@@ -0,0 +1,711 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Result;
+use reqwest::header::{CONTENT_TYPE, ETAG, IF_MATCH, IF_NONE_MATCH};
+use reqwest::{Client, Method, RequestBuilder, StatusCode, Url};
+use tracing::{info, warn};
+
+use crate::common::exception::InternalError;
+use crate::common::xml::{elements, escape_xml};
+use crate::core::event::{delete_event, get_event, import_event};
+use crate::core::processor::{dynamic_process, static_process};
+use crate::core::query::query_own_events;
+use crate::ics::{export_object, format_uid, parse_objects};
+use crate::model::sync::{ConflictPolicy, SyncEntry, SyncReport, SyncState};
+use crate::model::user::UserContext;
+
+pub struct SyncSettings {
+    url: String, // of the remote collection, ending with a slash
+    username: Option<String>,
+    password: Option<String>,
+    policy: ConflictPolicy,
+    query: Option<String>, // local events matching it are pushed, without it only synced ones are
+}
+
+// remote objects changed since the sync token, None for removed ones. a complete
+// listing holds every object, so synced objects missing from it were removed
+struct Listing {
+    changes: HashMap<String, Option<String>>,
+    complete: bool,
+    sync_token: Option<String>,
+}
+
+enum Pushed {
+    Stored(Option<String>),
+    Stale, // the remote object changed since it was listed
+}
+
+struct RemoteCollection {
+    client: Client,
+    url: Url,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl SyncSettings {
+    pub fn new(url: &str) -> Self {
+        let url = if url.ends_with('/') { url.to_string() } else { format!("{}/", url) };
+        SyncSettings {
+            url,
+            username: None,
+            password: None,
+            policy: ConflictPolicy::default(),
+            query: None,
+        }
+    }
+
+    pub fn with_credentials(mut self, username: &str, password: &str) -> Self {
+        self.username = Some(username.to_string());
+        self.password = Some(password.to_string());
+        self
+    }
+
+    pub fn with_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_query(mut self, query: &str) -> Self {
+        self.query = Some(query.to_string());
+        self
+    }
+}
+
+// pulls remote changes, then pushes local ones. an event changed on both sides since
+// the last sync is resolved by the policy, the state is kept even when the sync fails
+pub async fn sync_calendar(user: &UserContext, settings: &SyncSettings) -> Result<SyncReport> {
+    let remote = RemoteCollection::new(settings)?;
+    let url = settings.url.clone();
+    let mut state = static_process(user, move |cache| cache.get_sync_state(url.as_str())).await?;
+    let listing = remote.list(state.sync_token.as_deref()).await?;
+    let mut report = SyncReport::default();
+    let synced = sync_changes(
+        user,
+        settings,
+        &remote,
+        listing.changes,
+        listing.complete,
+        &mut state,
+        &mut report,
+    )
+    .await;
+    // changes left in conflict or not reached are listed again by the next sync, what
+    // was synced before an error is kept so it is not done twice
+    if synced.is_ok() && report.conflicts.is_empty() {
+        state.sync_token = listing.sync_token;
+    }
+    let saved = dynamic_process(user, move |mut cache| {
+        cache.set_sync_state(state);
+        Ok(())
+    })
+    .await;
+    synced?;
+    saved?;
+    info!(
+        "synced {}: {} pulled, {} pushed, {} conflicts",
+        settings.url,
+        report.pulled,
+        report.pushed,
+        report.conflicts.len()
+    );
+    Ok(report)
+}
+
+// applies the listed remote changes, then pushes the local ones, recording each in the state
+async fn sync_changes(
+    user: &UserContext,
+    settings: &SyncSettings,
+    remote: &RemoteCollection,
+    mut changes: HashMap<String, Option<String>>,
+    complete: bool,
+    state: &mut SyncState,
+    report: &mut SyncReport,
+) -> Result<()> {
+    let mut handled = HashSet::new();
+
+    let mut by_href = state
+        .entries
+        .iter()
+        .map(|(id, entry)| (entry.href.clone(), *id))
+        .collect::<HashMap<String, u128>>();
+    if complete {
+        for href in by_href.keys() {
+            changes.entry(href.clone()).or_insert(None);
+        }
+    }
+    for (href, etag) in changes {
+        let id = by_href.get(href.as_str()).copied();
+        let entry = id.and_then(|id| state.entries.get(&id).cloned());
+        match etag {
+            // unchanged, or what this engine pushed itself
+            Some(etag) if entry.as_ref().is_some_and(|e| e.etag.as_ref() == Some(&etag)) => {}
+            Some(etag) => {
+                let (body, _) = remote.get(href.as_str()).await?;
+                // an object of other components or with overridden occurrences is
+                // only read as its first event
+                let (uid, mut model) = match parse_objects(body.as_str())?.into_iter().next() {
+                    Some(object) => object,
+                    None => continue,
+                };
+                model.id = id.unwrap_or(model.id);
+                let id = model.id;
+                handled.insert(id);
+                let local = get_event(user, id).await.ok();
+                let changed = locally_changed(entry.as_ref(), local.as_ref().map(|l| l.revision));
+                let mut entry = entry.unwrap_or(SyncEntry {
+                    href: href.clone(),
+                    uid,
+                    etag: None,
+                    revision: 0,
+                });
+                entry.etag = Some(etag);
+                if changed {
+                    match settings.policy {
+                        ConflictPolicy::Skip => {
+                            report.conflicts.push(id);
+                            continue;
+                        }
+                        // pushed below over the remote change
+                        ConflictPolicy::KeepLocal => {
+                            handled.remove(&id);
+                            by_href.insert(href, id);
+                            state.entries.insert(id, entry);
+                            continue;
+                        }
+                        ConflictPolicy::KeepRemote => {}
+                    }
+                }
+                let expected = if changed { None } else { local.as_ref().map(|l| l.revision) };
+                let create_only = local.is_none() && !changed;
+                let imported = import_event(user, model, expected, create_only).await;
+                let imported = match imported {
+                    Ok((imported, _)) => imported,
+                    // left to the user like any other overlapping event
+                    Err(error) if is_overlap(&error) => {
+                        warn!("remote event {} overlaps a local one: {}", id, error);
+                        report.conflicts.push(id);
+                        continue;
+                    }
+                    Err(error) => return Err(error),
+                };
+                entry.revision = imported.revision;
+                by_href.insert(href, id);
+                state.entries.insert(id, entry);
+                report.pulled += 1;
+            }
+            None => {
+                let (id, entry) = match (id, entry) {
+                    (Some(id), Some(entry)) => (id, entry),
+                    _ => continue,
+                };
+                handled.insert(id);
+                let local = get_event(user, id).await.ok();
+                let changed = locally_changed(Some(&entry), local.as_ref().map(|l| l.revision));
+                match (local, changed, settings.policy) {
+                    (None, _, _) => {}
+                    (Some(_), true, ConflictPolicy::Skip) => {
+                        report.conflicts.push(id);
+                        continue;
+                    }
+                    // created again below
+                    (Some(_), true, ConflictPolicy::KeepLocal) => {
+                        handled.remove(&id);
+                        state.entries.insert(id, SyncEntry { etag: None, revision: 0, ..entry });
+                        continue;
+                    }
+                    (Some(local), changed, _) => {
+                        let expected = if changed { None } else { Some(local.revision) };
+                        delete_event(user, id, expected).await?;
+                        report.deleted_local += 1;
+                    }
+                }
+                state.entries.remove(&id);
+            }
+        }
+    }
+
+    let synced = state.entries.keys().copied().collect::<Vec<u128>>();
+    for id in synced.into_iter().filter(|id| !handled.contains(id)) {
+        let entry = state.entries[&id].clone();
+        let local = get_event(user, id).await.ok();
+        match local {
+            None => {
+                let removed = match &entry.etag {
+                    Some(etag) => remote.delete(entry.href.as_str(), etag.as_str()).await?,
+                    None => true,
+                };
+                if removed {
+                    state.entries.remove(&id);
+                    report.deleted_remote += 1;
+                } else {
+                    report.conflicts.push(id);
+                }
+            }
+            Some(local) if local.revision != entry.revision => {
+                let body = export_object(local.convert_to()?.as_ref(), entry.uid.as_str());
+                match remote.put(entry.href.as_str(), body, entry.etag.as_deref()).await? {
+                    Pushed::Stored(etag) => {
+                        let entry = state.entries.get_mut(&id).unwrap();
+                        entry.etag = etag;
+                        entry.revision = local.revision;
+                        report.pushed += 1;
+                    }
+                    Pushed::Stale => report.conflicts.push(id),
+                }
+            }
+            Some(_) => {}
+        }
+    }
+
+    if let Some(query) = &settings.query {
+        for event in query_own_events(user, query.as_str()).await? {
+            let id = event.get_id();
+            if state.entries.contains_key(&id) || handled.contains(&id) {
+                continue;
+            }
+            let uid = format_uid(id);
+            let href = remote.url.join(format!("{}.ics", uid).as_str())?.path().to_string();
+            let body = export_object(event.as_ref().as_ref(), uid.as_str());
+            match remote.put(href.as_str(), body, None).await? {
+                Pushed::Stored(etag) => {
+                    let revision = event.get_revision();
+                    state.entries.insert(id, SyncEntry { href, uid, etag, revision });
+                    report.pushed += 1;
+                }
+                Pushed::Stale => report.conflicts.push(id),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// a synced event that is gone changed too, an event never synced changed when it exists
+fn locally_changed(entry: Option<&SyncEntry>, revision: Option<u64>) -> bool {
+    match entry {
+        Some(entry) => revision != Some(entry.revision),
+        None => revision.is_some(),
+    }
+}
+
+impl RemoteCollection {
+    fn new(settings: &SyncSettings) -> Result<Self> {
+        Ok(RemoteCollection {
+            client: Client::new(),
+            url: Url::parse(settings.url.as_str())?,
+            username: settings.username.clone(),
+            password: settings.password.clone(),
+        })
+    }
+
+    fn request(&self, method: Method, href: &str) -> Result<RequestBuilder> {
+        let request = self.client.request(method, self.url.join(href)?);
+        Ok(match &self.username {
+            Some(username) => request.basic_auth(username, self.password.as_ref()),
+            None => request,
+        })
+    }
+
+    // by sync token when the server keeps them, an expired token starts over
+    async fn list(&self, sync_token: Option<&str>) -> Result<Listing> {
+        if let Some(sync_token) = sync_token {
+            if let Ok(listing) = self.sync_collection(sync_token).await {
+                return Ok(listing);
+            }
+        }
+        match self.sync_collection("").await {
+            Ok(listing) => Ok(listing),
+            Err(_) => self.propfind().await,
+        }
+    }
+
+    async fn sync_collection(&self, sync_token: &str) -> Result<Listing> {
+        let body = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?><d:sync-collection xmlns:d=\"DAV:\">\
+             <d:sync-token>{}</d:sync-token><d:sync-level>1</d:sync-level>\
+             <d:prop><d:getetag/></d:prop></d:sync-collection>",
+            escape_xml(sync_token)
+        );
+        let report = Method::from_bytes(b"REPORT")?;
+        let body = self.multistatus(report, "1", body).await?;
+        Ok(Listing {
+            changes: objects(body.as_str()),
+            complete: sync_token.is_empty(),
+            sync_token: elements(body.as_str(), "sync-token")
+                .pop()
+                .map(|(_, token)| token.trim().to_string()),
+        })
+    }
+
+    async fn propfind(&self) -> Result<Listing> {
+        let body = "<?xml version=\"1.0\" encoding=\"utf-8\"?><d:propfind xmlns:d=\"DAV:\">\
+                    <d:prop><d:getetag/><d:resourcetype/></d:prop></d:propfind>";
+        let propfind = Method::from_bytes(b"PROPFIND")?;
+        let body = self.multistatus(propfind, "1", body.to_string()).await?;
+        Ok(Listing {
+            changes: objects(body.as_str()),
+            complete: true,
+            sync_token: None,
+        })
+    }
+
+    async fn multistatus(&self, method: Method, depth: &str, body: String) -> Result<String> {
+        let response = self
+            .request(method, "")?
+            .header("Depth", depth)
+            .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+            .body(body)
+            .send()
+            .await?;
+        if response.status() != StatusCode::MULTI_STATUS {
+            bail!(remote_error(response.status()))
+        }
+        Ok(response.text().await?)
+    }
+
+    async fn get(&self, href: &str) -> Result<(String, Option<String>)> {
+        let response = self.request(Method::GET, href)?.send().await?;
+        if !response.status().is_success() {
+            bail!(remote_error(response.status()))
+        }
+        let etag = header_value(response.headers().get(ETAG));
+        Ok((response.text().await?, etag))
+    }
+
+    // without an etag the object is created and must not exist yet
+    async fn put(&self, href: &str, body: String, etag: Option<&str>) -> Result<Pushed> {
+        let request = self
+            .request(Method::PUT, href)?
+            .header(CONTENT_TYPE, "text/calendar; charset=utf-8")
+            .body(body);
+        let request = match etag {
+            Some(etag) => request.header(IF_MATCH, etag),
+            None => request.header(IF_NONE_MATCH, "*"),
+        };
+        let response = request.send().await?;
+        match response.status() {
+            StatusCode::PRECONDITION_FAILED => Ok(Pushed::Stale),
+            status if status.is_success() => match header_value(response.headers().get(ETAG)) {
+                Some(etag) => Ok(Pushed::Stored(Some(etag))),
+                // some servers change what was put, so they leave the etag to be fetched
+                None => Ok(Pushed::Stored(self.get(href).await?.1)),
+            },
+            status => bail!(remote_error(status)),
+        }
+    }
+
+    // false when the object changed since it was listed, an object already gone is removed
+    async fn delete(&self, href: &str, etag: &str) -> Result<bool> {
+        let response = self
+            .request(Method::DELETE, href)?
+            .header(IF_MATCH, etag)
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::PRECONDITION_FAILED => Ok(false),
+            StatusCode::NOT_FOUND => Ok(true),
+            status if status.is_success() => Ok(true),
+            status => bail!(remote_error(status)),
+        }
+    }
+}
+
+// hrefs of the objects in a multistatus, collections are skipped
+fn objects(body: &str) -> HashMap<String, Option<String>> {
+    elements(body, "response")
+        .iter()
+        .filter_map(|(_, response)| {
+            let href = elements(response, "href").first()?.1.trim().to_string();
+            if href.ends_with('/') {
+                return None;
+            }
+            let etag = elements(response, "getetag")
+                .first()
+                .map(|(_, etag)| etag.trim().to_string())
+                .filter(|etag| !etag.is_empty());
+            Some((href, etag))
+        })
+        .collect()
+}
+
+fn is_overlap(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<InternalError>(),
+        Some(InternalError::ConflictEventError { .. })
+            | Some(InternalError::SkippedEventError { .. })
+    )
+}
+
+fn header_value(value: Option<&reqwest::header::HeaderValue>) -> Option<String> {
+    value.and_then(|value| value.to_str().ok()).map(|value| value.to_string())
+}
+
+fn remote_error(status: StatusCode) -> anyhow::Error {
+    anyhow!(InternalError::RemoteCalendarError {
+        reason: format!("the server responded {}", status)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    use actix_web::{App, HttpRequest, HttpResponse, HttpServer, web};
+    use actix_web::http::header::{ETAG, IF_MATCH, IF_NONE_MATCH};
+    use chrono::{DateTime, Duration};
+
+    use crate::common::xml::elements;
+    use crate::core::event::{delete_event, get_event, update_event};
+    use crate::core::processor::{dynamic_process, static_process};
+    use crate::core::sync::{SyncSettings, sync_calendar};
+    use crate::ics::{format_uid, parse_uid};
+    use crate::model::event::Event;
+    use crate::model::{EventCommonTrait, ItemCommonTrait};
+    use crate::model::sync::{ConflictPolicy, SyncReport};
+    use crate::model::user::UserContext;
+
+    // remote collection at /cal/, the sync token is the version of the last change
+    #[derive(Default)]
+    struct StandIn {
+        objects: BTreeMap<String, (u64, String)>,
+        removed: Vec<(u64, String)>,
+        version: u64,
+        broken: bool, // refuses every write
+    }
+
+    impl StandIn {
+        fn store(&mut self, href: &str, body: String) -> u64 {
+            self.version += 1;
+            self.objects.insert(href.to_string(), (self.version, body));
+            self.version
+        }
+
+        fn edit(&mut self, href: &str, from: &str, to: &str) {
+            let body = self.objects[href].1.replace(from, to);
+            self.store(href, body);
+        }
+    }
+
+    async fn stand_in(
+        request: HttpRequest,
+        body: String,
+        remote: web::Data<Mutex<StandIn>>,
+    ) -> HttpResponse {
+        let mut remote = remote.lock().unwrap();
+        let href = request.path().to_string();
+        let current = remote.objects.get(&href).map(|(version, _)| format!("\"{}\"", version));
+        let header = |name| request.headers().get(name).and_then(|v| v.to_str().ok());
+        match request.method().as_str() {
+            "REPORT" => {
+                let token = elements(body.as_str(), "sync-token")[0].1.clone();
+                let since = match token.as_str() {
+                    "" => 0,
+                    token => match token.parse::<u64>() {
+                        Ok(since) => since,
+                        Err(_) => return HttpResponse::Forbidden().finish(),
+                    },
+                };
+                let mut responses = remote
+                    .objects
+                    .iter()
+                    .filter(|(_, (version, _))| *version > since)
+                    .map(|(href, (version, _))| {
+                        format!(
+                            "<d:response><d:href>{}</d:href><d:propstat><d:prop>\
+                             <d:getetag>\"{}\"</d:getetag></d:prop></d:propstat></d:response>",
+                            href, version
+                        )
+                    })
+                    .collect::<String>();
+                for (_, href) in remote.removed.iter().filter(|(v, _)| since > 0 && *v > since) {
+                    responses.push_str(
+                        format!(
+                            "<d:response><d:href>{}</d:href>\
+                             <d:status>HTTP/1.1 404 Not Found</d:status></d:response>",
+                            href
+                        )
+                        .as_str(),
+                    );
+                }
+                HttpResponse::MultiStatus().body(format!(
+                    "<d:multistatus xmlns:d=\"DAV:\">{}<d:sync-token>{}</d:sync-token>\
+                     </d:multistatus>",
+                    responses, remote.version
+                ))
+            }
+            "GET" => match remote.objects.get(&href) {
+                Some((_, body)) => HttpResponse::Ok()
+                    .insert_header((ETAG, current.unwrap()))
+                    .body(body.clone()),
+                None => HttpResponse::NotFound().finish(),
+            },
+            "PUT" if remote.broken => HttpResponse::InternalServerError().finish(),
+            "PUT" => {
+                let stale = match (header(IF_MATCH), header(IF_NONE_MATCH)) {
+                    (Some(etag), _) => current.as_deref() != Some(etag),
+                    (_, Some("*")) => current.is_some(),
+                    _ => false,
+                };
+                if stale {
+                    return HttpResponse::PreconditionFailed().finish();
+                }
+                let version = remote.store(href.as_str(), body);
+                HttpResponse::Created()
+                    .insert_header((ETAG, format!("\"{}\"", version)))
+                    .finish()
+            }
+            "DELETE" => {
+                if header(IF_MATCH).is_some() && header(IF_MATCH) != current.as_deref() {
+                    return HttpResponse::PreconditionFailed().finish();
+                }
+                remote.objects.remove(&href);
+                remote.version += 1;
+                let version = remote.version;
+                remote.removed.push((version, href));
+                HttpResponse::NoContent().finish()
+            }
+            _ => HttpResponse::MethodNotAllowed().finish(),
+        }
+    }
+
+    fn quokka() -> UserContext {
+        UserContext::new("sync-quokka").unwrap()
+    }
+
+    async fn title(id: u128) -> String {
+        get_event(&quokka(), id).await.unwrap().title
+    }
+
+    async fn rename(id: u128, title: &str) {
+        let user = quokka();
+        let mut model = get_event(&user, id).await.unwrap();
+        model.title = title.to_string();
+        update_event(&user, model, None).await.unwrap();
+    }
+
+    // serves the stand-in, returns the url of its collection
+    fn serve(remote: web::Data<Mutex<StandIn>>) -> String {
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(remote.clone())
+                .default_service(web::to(stand_in))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let url = format!("http://{}/cal/", server.addrs()[0]);
+        actix_web::rt::spawn(server.run());
+        url
+    }
+
+    #[actix_web::test]
+    async fn events_sync_both_ways_with_conflict_policies() {
+        let user = quokka();
+        let remote = web::Data::new(Mutex::new(StandIn::default()));
+        let url = serve(remote.clone());
+
+        let remote_id = parse_uid("quokka@stand-in");
+        remote.lock().unwrap().store(
+            "/cal/quokka.ics",
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nUID:quokka@stand-in\r\n\
+             DTSTART:20320301T090000Z\r\nDTEND:20320301T100000Z\r\nSUMMARY:remote quokka\r\n\
+             END:VEVENT\r\nEND:VCALENDAR\r\n"
+                .to_string(),
+        );
+        let mut local = Event::init(None);
+        local.set_title("local quokkasync");
+        let start = DateTime::parse_from_rfc3339("2032-03-02T09:00:00Z").unwrap();
+        local.set_duration(start, start + Duration::hours(1));
+        let local_id = local.get_id();
+        let local_href = format!("/cal/{}.ics", format_uid(local_id));
+        dynamic_process(&user, move |mut cache| cache.insert_events(vec![Box::new(local)]))
+            .await
+            .unwrap();
+        let settings = || SyncSettings::new(url.as_str()).with_query("quokkasync");
+
+        let report = sync_calendar(&user, &settings()).await.unwrap();
+        assert_eq!((report.pulled, report.pushed), (1, 1));
+        assert_eq!(title(remote_id).await, "remote quokka");
+        assert!(remote.lock().unwrap().objects[&local_href].1.contains("SUMMARY:local quokkasync"));
+        // nothing changed on either side
+        assert_eq!(sync_calendar(&user, &settings()).await.unwrap(), SyncReport::default());
+
+        rename(remote_id, "remote quokka renamed here").await;
+        remote.lock().unwrap().edit(local_href.as_str(), "local quokkasync", "local quokkasync renamed");
+        let report = sync_calendar(&user, &settings()).await.unwrap();
+        assert_eq!((report.pulled, report.pushed), (1, 1));
+        assert_eq!(title(local_id).await, "local quokkasync renamed");
+        let pushed = remote.lock().unwrap().objects["/cal/quokka.ics"].1.clone();
+        assert!(pushed.contains("UID:quokka@stand-in\r\n"));
+        assert!(pushed.contains("SUMMARY:remote quokka renamed here"));
+
+        rename(local_id, "local quokkasync mine").await;
+        remote.lock().unwrap().edit(local_href.as_str(), "renamed", "theirs");
+        let report = sync_calendar(&user, &settings().with_policy(ConflictPolicy::Skip))
+            .await
+            .unwrap();
+        assert_eq!(report.conflicts, vec![local_id]);
+        assert_eq!(title(local_id).await, "local quokkasync mine");
+        let report = sync_calendar(&user, &settings().with_policy(ConflictPolicy::KeepRemote))
+            .await
+            .unwrap();
+        assert_eq!((report.pulled, report.conflicts.len()), (1, 0));
+        assert_eq!(title(local_id).await, "local quokkasync theirs");
+
+        delete_event(&user, remote_id, None).await.unwrap();
+        {
+            let mut stand_in = remote.lock().unwrap();
+            stand_in.objects.remove(&local_href);
+            stand_in.version += 1;
+            let version = stand_in.version;
+            stand_in.removed.push((version, local_href.clone()));
+        }
+        let report = sync_calendar(&user, &settings()).await.unwrap();
+        assert_eq!((report.deleted_local, report.deleted_remote), (1, 1));
+        assert!(remote.lock().unwrap().objects.is_empty());
+        assert!(get_event(&user, local_id).await.is_err());
+        let url = url.clone();
+        let state = static_process(&user, move |cache| cache.get_sync_state(url.as_str()))
+            .await
+            .unwrap();
+        assert!(state.entries.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn pulled_events_are_kept_when_pushing_fails() {
+        let user = UserContext::new("sync-wombat").unwrap();
+        let remote = web::Data::new(Mutex::new(StandIn::default()));
+        let url = serve(remote.clone());
+        remote.lock().unwrap().store(
+            "/cal/wombat.ics",
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nUID:wombat@stand-in\r\n\
+             DTSTART:20320401T090000Z\r\nDTEND:20320401T100000Z\r\nSUMMARY:remote wombat\r\n\
+             END:VEVENT\r\nEND:VCALENDAR\r\n"
+                .to_string(),
+        );
+        let mut local = Event::init(None);
+        local.set_title("local wombatsync");
+        let start = DateTime::parse_from_rfc3339("2032-04-02T09:00:00Z").unwrap();
+        local.set_duration(start, start + Duration::hours(1));
+        dynamic_process(&user, move |mut cache| cache.insert_events(vec![Box::new(local)]))
+            .await
+            .unwrap();
+        let settings = SyncSettings::new(url.as_str()).with_query("wombatsync");
+
+        remote.lock().unwrap().broken = true;
+        assert!(sync_calendar(&user, &settings).await.is_err());
+        let state_url = url.clone();
+        let state = static_process(&user, move |cache| cache.get_sync_state(state_url.as_str()))
+            .await
+            .unwrap();
+        assert!(state.entries.contains_key(&parse_uid("wombat@stand-in")));
+        assert!(state.sync_token.is_none());
+
+        remote.lock().unwrap().broken = false;
+        let report = sync_calendar(&user, &settings).await.unwrap();
+        assert_eq!((report.pulled, report.pushed), (0, 1));
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+
+use crate::core::processor::{dynamic_process, static_process};
+use crate::model::EventCommonTrait;
+use crate::model::user::UserContext;
+
+pub async fn set_event_tags(user: &UserContext, event_id: u128, tags: Vec<String>) -> Result<()> {
+    dynamic_process(user, move |mut cache| {
+        let tags = tags.iter().map(|tag| tag.as_str()).collect();
+        cache.set_event_tags(event_id, tags)
+    })
+    .await
+}
+
+pub async fn add_event_tags(user: &UserContext, event_id: u128, tags: Vec<String>) -> Result<()> {
+    dynamic_process(user, move |mut cache| {
+        let tags = tags.iter().map(|tag| tag.as_str()).collect();
+        cache.add_event_tags(event_id, tags)
+    })
+    .await
+}
+
+pub async fn remove_event_tags(
+    user: &UserContext,
+    event_id: u128,
+    tags: Vec<String>,
+) -> Result<()> {
+    dynamic_process(user, move |mut cache| {
+        let tags = tags.iter().map(|tag| tag.as_str()).collect();
+        cache.remove_event_tags(event_id, tags)
+    })
+    .await
+}
+
+pub async fn get_all_tags(user: &UserContext) -> Result<Vec<(String, usize)>> {
+    static_process(user, |cache| cache.get_all_tags()).await
+}
+
+// e.g. tags ["clientA", "billable"] with the bounds of March
+pub async fn get_events_by_tags(
+    user: &UserContext,
+    tags: Vec<String>,
+    start: Option<DateTime<FixedOffset>>,
+    end: Option<DateTime<FixedOffset>>,
+) -> Result<Vec<Arc<Box<dyn EventCommonTrait>>>> {
+    static_process(user, move |cache| {
+        let tags = tags.iter().map(|tag| tag.as_str()).collect::<Vec<&str>>();
+        cache.get_events_by_tags(&tags, start, end)
+    })
+    .await
+}
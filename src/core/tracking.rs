@@ -0,0 +1,45 @@
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, Utc};
+
+use crate::core::processor::{dynamic_process, static_process};
+use crate::model::Category;
+use crate::model::tracking::{TrackingGroup, TrackingReport};
+use crate::model::user::UserContext;
+
+pub async fn start_tracking(user: &UserContext, event_id: u128) -> Result<()> {
+    let now = DateTime::from(Utc::now());
+    dynamic_process(user, move |mut cache| cache.start_tracking(event_id, now)).await
+}
+
+pub async fn stop_tracking(user: &UserContext, event_id: u128) -> Result<()> {
+    let now = DateTime::from(Utc::now());
+    dynamic_process(user, move |mut cache| cache.stop_tracking(event_id, now)).await
+}
+
+// returns the id of the event recording the ad hoc work
+pub async fn start_adhoc_tracking(
+    user: &UserContext,
+    title: String,
+    category: String,
+) -> Result<u128> {
+    let now = DateTime::from(Utc::now());
+    dynamic_process(user, move |mut cache| {
+        cache.start_adhoc_tracking(title.as_str(), Category::from(category.as_str()), now)
+    })
+    .await
+}
+
+// events whose tracking was started and not stopped yet
+pub async fn get_running_tracking(user: &UserContext) -> Result<Vec<u128>> {
+    static_process(user, |cache| cache.get_running_tracking()).await
+}
+
+pub async fn get_tracking_report(
+    user: &UserContext,
+    start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>,
+    group: TrackingGroup,
+) -> Result<Vec<TrackingReport>> {
+    let now = DateTime::from(Utc::now());
+    static_process(user, move |cache| cache.get_tracking_report(start, end, now, group)).await
+}
@@ -0,0 +1,9 @@
+use anyhow::Result;
+
+use crate::core::processor::dynamic_process;
+use crate::model::transaction::{Transaction, TransactionResult};
+use crate::model::user::UserContext;
+
+pub async fn commit(user: &UserContext, transaction: Transaction) -> Result<TransactionResult> {
+    dynamic_process(user, move |mut cache| cache.commit_transaction(transaction)).await
+}
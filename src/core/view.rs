@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use crate::common::exception::InternalError;
+use crate::core::processor::{dynamic_process, static_process};
+use crate::ics::export_named_calendar;
+use crate::model::EventCommonTrait;
+use crate::model::user::UserContext;
+use crate::model::view::SavedView;
+use crate::query::parse_query;
+
+pub async fn create_view(user: &UserContext, name: String, query: String) -> Result<SavedView> {
+    let view = SavedView::new(name.as_str(), query.as_str())?;
+    let created = view.clone();
+    dynamic_process(user, move |mut cache| cache.add_or_update_views(vec![view])).await?;
+    Ok(created)
+}
+
+pub async fn delete_view(user: &UserContext, id: u128) -> Result<()> {
+    dynamic_process(user, move |mut cache| cache.delete_view(id)).await
+}
+
+pub async fn get_views(user: &UserContext) -> Result<Vec<SavedView>> {
+    static_process(user, |cache| cache.get_all_views()).await
+}
+
+// views are addressed by id or by name
+pub async fn find_view(user: &UserContext, key: String) -> Result<SavedView> {
+    static_process(user, move |cache| {
+        key.parse::<u128>()
+            .ok()
+            .and_then(|id| cache.get_view(id).ok())
+            .or_else(|| cache.get_view_by_name(key.as_str()).ok())
+    })
+    .await?
+    .ok_or(anyhow!(InternalError::ViewNotFoundError))
+}
+
+// the stored query was checked when the view was saved, so only a missing view fails
+pub async fn query_view(
+    user: &UserContext,
+    id: u128,
+    query: &str,
+) -> Result<Vec<Arc<Box<dyn EventCommonTrait>>>> {
+    let filter = parse_query(query)?;
+    let now = DateTime::from(Utc::now());
+    static_process(user, move |cache| cache.query_view(id, &filter, now)).await?
+}
+
+pub async fn export_view(user: &UserContext, id: u128) -> Result<String> {
+    let view = static_process(user, move |cache| cache.get_view(id)).await??;
+    let events = query_view(user, id, "").await?;
+    Ok(export_named_calendar(Some(view.name.as_str()), &events))
+}
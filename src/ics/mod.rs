@@ -0,0 +1,525 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Result;
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+use uuid::Uuid;
+
+use crate::common::exception::InternalError;
+use crate::model::{Category, EventCommonTrait, ItemCommonTrait, Kind};
+use crate::model::alarm::{Alarm, AlarmTrigger};
+use crate::model::event::Event;
+use crate::persistent::PersistentModel;
+
+mod test;
+
+pub const PRODUCT_ID: &str = "-//break-calendar//break-calendar//EN";
+
+// name, parameters and value of a content line
+type ContentLine = (String, Vec<(String, String)>, String);
+
+const FNV_OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+const FNV_PRIME: u128 = 0x0000000001000000000000000000013b;
+
+// one VEVENT while it is read
+#[derive(Default)]
+struct EventBuilder {
+    uid: Option<String>,
+    start: Option<DateTime<FixedOffset>>,
+    end: Option<DateTime<FixedOffset>>,
+    duration: Option<Duration>,
+    all_day: bool,
+    summary: String,
+    description: String,
+    category: String,
+    kind: String,
+    alarms: Vec<AlarmBuilder>,
+}
+
+#[derive(Default)]
+struct AlarmBuilder {
+    uid: Option<String>,
+    offset: Option<Duration>, // relative to the start, or to the end when related to it
+    related_to_end: bool,
+    time: Option<DateTime<FixedOffset>>,
+    channel: Option<String>,
+}
+
+// RFC 5545 calendar with one VEVENT per stored occurrence
+pub fn export_calendar(events: &[Arc<Box<dyn EventCommonTrait>>]) -> String {
+    export_named_calendar(None, events)
+}
+
+// the name is shown by clients subscribing to the feed
+pub fn export_named_calendar(
+    name: Option<&str>,
+    events: &[Arc<Box<dyn EventCommonTrait>>],
+) -> String {
+    let now = DateTime::from(Utc::now());
+    let lines = events
+        .iter()
+        .flat_map(|event| {
+            let event = event.as_ref().as_ref();
+            export_event(event, event_uid(event).as_str(), now)
+        })
+        .collect();
+    wrap_calendar(name, lines)
+}
+
+// one event under the UID another calendar knows it by
+pub fn export_object(event: &dyn EventCommonTrait, uid: &str) -> String {
+    wrap_calendar(None, export_event(event, uid, DateTime::from(Utc::now())))
+}
+
+fn wrap_calendar(name: Option<&str>, mut events: Vec<String>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODUCT_ID),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    if let Some(name) = name {
+        lines.push(format!("X-WR-CALNAME:{}", escape_text(name)));
+    }
+    lines.append(&mut events);
+    lines.push("END:VCALENDAR".to_string());
+    lines
+        .iter()
+        .map(|line| fold_line(line))
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+pub fn export_event(
+    event: &dyn EventCommonTrait,
+    uid: &str,
+    now: DateTime<FixedOffset>,
+) -> Vec<String> {
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}", uid),
+        format!("DTSTAMP:{}", format_datetime(now)),
+        format!("DTSTART:{}", format_datetime(event.get_start_time())),
+        format!("DTEND:{}", format_datetime(event.get_end_time())),
+        format!("SUMMARY:{}", escape_text(event.get_title())),
+    ];
+    if !event.get_description().is_empty() {
+        lines.push(format!(
+            "DESCRIPTION:{}",
+            escape_text(event.get_description())
+        ));
+    }
+    lines.push(format!(
+        "CATEGORIES:{}",
+        escape_text(event.get_categories().to_string().as_str())
+    ));
+    lines.push(format!("X-BREAK-CALENDAR-KIND:{}", event.get_kind()));
+    if let Some(event) = event.downcast_ref::<Event>() {
+        for alarm in event.get_alarms() {
+            lines.append(&mut export_alarm(alarm, event));
+        }
+    }
+    lines.push("END:VEVENT".to_string());
+    lines
+}
+
+fn export_alarm(alarm: &Alarm, event: &Event) -> Vec<String> {
+    let trigger = match &alarm.trigger {
+        AlarmTrigger::BeforeStart { minutes } => format!("TRIGGER:{}", format_offset(-minutes)),
+        AlarmTrigger::At { .. } => format!(
+            "TRIGGER;VALUE=DATE-TIME:{}",
+            format_datetime(alarm.fire_time(event.get_start_time()))
+        ),
+    };
+    let mut lines = vec![
+        "BEGIN:VALARM".to_string(),
+        format!("UID:{}", format_uid(alarm.get_id())),
+        "ACTION:DISPLAY".to_string(),
+        format!("DESCRIPTION:{}", escape_text(event.get_title())),
+        trigger,
+    ];
+    if let Some(channel) = &alarm.channel {
+        lines.push(format!("X-BREAK-CALENDAR-CHANNEL:{}", escape_text(channel)));
+    }
+    lines.push("END:VALARM".to_string());
+    lines
+}
+
+// the UID the event was read with, or the one of its id
+pub fn event_uid(event: &dyn EventCommonTrait) -> String {
+    event
+        .get_uid()
+        .map(|uid| uid.to_string())
+        .unwrap_or(format_uid(event.get_id()))
+}
+
+pub fn format_uid(id: u128) -> String {
+    Uuid::from_u128(id).hyphenated().to_string()
+}
+
+pub fn format_datetime(time: DateTime<FixedOffset>) -> String {
+    time.naive_utc().format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+// duration relative to the event start, e.g. -PT10M or -P1D
+fn format_offset(minutes: i64) -> String {
+    let sign = if minutes < 0 { "-" } else { "" };
+    let minutes = minutes.abs();
+    if minutes != 0 && minutes % (24 * 60) == 0 {
+        format!("{}P{}D", sign, minutes / (24 * 60))
+    } else if minutes != 0 && minutes % 60 == 0 {
+        format!("{}PT{}H", sign, minutes / 60)
+    } else {
+        format!("{}PT{}M", sign, minutes)
+    }
+}
+
+pub fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+// content lines longer than 75 octets continue on the next line after a space
+fn fold_line(line: &str) -> String {
+    let mut result = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            result.push_str("\r\n ");
+            length = 1;
+        }
+        result.push(c);
+        length += c.len_utf8();
+    }
+    result.push_str("\r\n");
+    result
+}
+
+// events of an iCalendar text in their stored form, at revision 0. recurrence rules
+// are not expanded and times with a TZID are read as UTC, there is no tz database
+pub fn parse_calendar(text: &str) -> Result<Vec<PersistentModel>> {
+    Ok(parse_objects(text)?.into_iter().map(|(_, model)| model).collect())
+}
+
+// events with the UID they were read with, which the id is derived from
+pub fn parse_objects(text: &str) -> Result<Vec<(String, PersistentModel)>> {
+    let mut components: Vec<String> = vec![];
+    let mut events = vec![];
+    let mut event: Option<EventBuilder> = None;
+    let mut alarm: Option<AlarmBuilder> = None;
+    for line in unfold_lines(text) {
+        let (name, params, value) = split_line(line.as_str())?;
+        match name.as_str() {
+            "BEGIN" => {
+                let component = value.to_ascii_uppercase();
+                match (component.as_str(), components.last().map(|c| c.as_str())) {
+                    ("VEVENT", Some("VCALENDAR")) => event = Some(EventBuilder::default()),
+                    ("VALARM", Some("VEVENT")) => alarm = Some(AlarmBuilder::default()),
+                    _ => {}
+                }
+                components.push(component);
+                continue;
+            }
+            "END" => {
+                match (components.pop(), event.as_mut()) {
+                    (Some(component), Some(builder)) if component == "VALARM" => {
+                        builder.alarms.extend(alarm.take())
+                    }
+                    (Some(component), Some(_)) if component == "VEVENT" => {
+                        events.push(event.take().unwrap().build()?)
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            _ => {}
+        }
+        match (components.last().map(|c| c.as_str()), event.as_mut(), alarm.as_mut()) {
+            (Some("VALARM"), Some(_), Some(alarm)) => alarm.read(&name, &params, &value)?,
+            (Some("VEVENT"), Some(event), _) => event.read(&name, &params, &value)?,
+            _ => {}
+        }
+    }
+    if !components.is_empty() {
+        bail!(InternalError::InvalidIcsError {
+            reason: format!("{} is not closed", components.last().unwrap())
+        })
+    }
+    Ok(events)
+}
+
+// UUIDs are read back as exported, other UIDs always map to the same id
+pub fn parse_uid(uid: &str) -> u128 {
+    match Uuid::try_parse(uid) {
+        Ok(id) => id.as_u128(),
+        Err(_) => uid.bytes().fold(FNV_OFFSET, |hash, byte| {
+            (hash ^ byte as u128).wrapping_mul(FNV_PRIME)
+        }),
+    }
+}
+
+impl EventBuilder {
+    fn read(&mut self, name: &str, params: &[(String, String)], value: &str) -> Result<()> {
+        match name {
+            "UID" => self.uid = Some(value.to_string()),
+            "DTSTART" => {
+                self.all_day = is_date(params, value);
+                self.start = Some(parse_datetime(value)?);
+            }
+            "DTEND" => self.end = Some(parse_datetime(value)?),
+            "DURATION" => self.duration = Some(parse_duration(value)?),
+            "SUMMARY" => self.summary = unescape_text(value),
+            "DESCRIPTION" => self.description = unescape_text(value),
+            // only one category is kept
+            "CATEGORIES" => self.category = split_list(value).into_iter().next().unwrap_or_default(),
+            "X-BREAK-CALENDAR-KIND" => self.kind = value.to_string(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn build(self) -> Result<(String, PersistentModel)> {
+        let uid = self.uid.ok_or(anyhow!(InternalError::InvalidIcsError {
+            reason: "event without UID".to_string()
+        }))?;
+        let start = self.start.ok_or(anyhow!(InternalError::InvalidIcsError {
+            reason: format!("event {} without DTSTART", uid)
+        }))?;
+        let end = match (self.end, self.duration) {
+            (Some(end), _) => Some(end),
+            (None, Some(duration)) => start.checked_add_signed(duration),
+            (None, None) if self.all_day => start.checked_add_signed(Duration::days(1)),
+            (None, None) => Some(start),
+        };
+        let end = end.ok_or(anyhow!(InternalError::InvalidIcsError {
+            reason: format!("event {} ends out of time", uid)
+        }))?;
+        let kind = match Kind::from(self.kind.as_str()) {
+            Kind::Task => Kind::Event,
+            kind => kind,
+        };
+        let alarms = self
+            .alarms
+            .into_iter()
+            .filter_map(|alarm| alarm.build(start, end).transpose())
+            .collect::<Result<Vec<Alarm>>>()?;
+        let id = parse_uid(uid.as_str());
+        Ok((uid.clone(), PersistentModel {
+            id,
+            kind: kind.to_string(),
+            title: self.summary,
+            description: self.description,
+            start_time: start.timestamp_millis(),
+            start_time_timezone: start.offset().to_string(),
+            end_time: end.timestamp_millis(),
+            end_time_timezone: end.offset().to_string(),
+            color: String::new(),
+            important_level: String::new(),
+            category: Category::from(self.category.as_str()).to_string(),
+            generator_instance: None,
+            key_result: None,
+            task: None,
+            alarms,
+            actual_start_time: None,
+            actual_start_time_timezone: None,
+            actual_end_time: None,
+            actual_end_time_timezone: None,
+            unplanned: false,
+            calendar: None,
+            tags: Default::default(),
+            uid: Some(uid),
+            href: None,
+            revision: 0,
+        }))
+    }
+}
+
+impl AlarmBuilder {
+    fn read(&mut self, name: &str, params: &[(String, String)], value: &str) -> Result<()> {
+        match name {
+            "UID" => self.uid = Some(value.to_string()),
+            "TRIGGER" if param(params, "VALUE") == Some("DATE-TIME") => {
+                self.time = Some(parse_datetime(value)?)
+            }
+            "TRIGGER" => {
+                self.offset = Some(parse_duration(value)?);
+                self.related_to_end = param(params, "RELATED") == Some("END");
+            }
+            "X-BREAK-CALENDAR-CHANNEL" => self.channel = Some(unescape_text(value)),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // alarms without a trigger are dropped
+    fn build(
+        self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+    ) -> Result<Option<Alarm>> {
+        let trigger = match (self.time, self.offset) {
+            (Some(time), _) => AlarmTrigger::At {
+                time: time.timestamp_millis(),
+                timezone: time.offset().to_string(),
+            },
+            (None, Some(offset)) => {
+                let base = if self.related_to_end { end - start } else { Duration::zero() };
+                let before = base.checked_add(&offset).ok_or(anyhow!(
+                    InternalError::InvalidIcsError {
+                        reason: "alarm out of time".to_string()
+                    }
+                ))?;
+                AlarmTrigger::BeforeStart {
+                    minutes: -before.num_minutes(),
+                }
+            }
+            (None, None) => return Ok(None),
+        };
+        let alarm = Alarm::new(trigger, self.channel);
+        Ok(Some(match self.uid {
+            Some(uid) => alarm.with_id(parse_uid(uid.as_str())),
+            None => alarm,
+        }))
+    }
+}
+
+// folded lines joined back, empty lines dropped
+fn unfold_lines(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in text.split('\n').map(|line| line.trim_end_matches('\r')) {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+// NAME;PARAM=value;PARAM="quoted:value":VALUE, names and parameter names upper cased
+fn split_line(line: &str) -> Result<ContentLine> {
+    let mut quoted = false;
+    let colon = line.char_indices().find(|(_, c)| {
+        if *c == '"' {
+            quoted = !quoted;
+        }
+        *c == ':' && !quoted
+    });
+    let (head, value) = match colon {
+        Some((index, _)) => (&line[..index], &line[index + 1..]),
+        None => bail!(InternalError::InvalidIcsError {
+            reason: format!("line without value {:?}", line)
+        }),
+    };
+    let mut parts = head.split(';');
+    let name = parts.next().unwrap_or_default().to_ascii_uppercase();
+    let params = parts
+        .filter_map(|part| part.split_once('='))
+        .map(|(key, value)| (key.to_ascii_uppercase(), value.trim_matches('"').to_string()))
+        .collect();
+    Ok((name, params, value.to_string()))
+}
+
+fn param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+fn is_date(params: &[(String, String)], value: &str) -> bool {
+    param(params, "VALUE") == Some("DATE") || value.len() == 8
+}
+
+// 20260302T013000Z, floating 20260302T093000 or the day 20260302
+pub fn parse_datetime(value: &str) -> Result<DateTime<FixedOffset>> {
+    let naive = NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), "%Y%m%dT%H%M%S")
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y%m%d").map(|day| day.and_hms_opt(0, 0, 0).unwrap())
+        })
+        .map_err(|_| {
+            anyhow!(InternalError::InvalidIcsError {
+                reason: format!("invalid time {:?}", value)
+            })
+        })?;
+    Ok(DateTime::from(naive.and_utc()))
+}
+
+// e.g. -PT10M, P1D or P1W, months and years have no fixed length and are not allowed
+fn parse_duration(value: &str) -> Result<Duration> {
+    let invalid = || {
+        anyhow!(InternalError::InvalidIcsError {
+            reason: format!("invalid duration {:?}", value)
+        })
+    };
+    let (negative, rest) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let rest = rest.strip_prefix('P').ok_or_else(invalid)?;
+    let mut duration = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => in_time = true,
+            unit => {
+                let amount = number.parse::<i64>().map_err(|_| invalid())?;
+                number.clear();
+                // feeds and sync pulls are read from other servers, so nothing may overflow
+                let part = match (unit, in_time) {
+                    ('W', false) => Duration::try_weeks(amount),
+                    ('D', false) => Duration::try_days(amount),
+                    ('H', true) => Duration::try_hours(amount),
+                    ('M', true) => Duration::try_minutes(amount),
+                    ('S', true) => Duration::try_seconds(amount),
+                    _ => None,
+                };
+                duration = part
+                    .and_then(|part| duration.checked_add(&part))
+                    .ok_or_else(invalid)?;
+            }
+        }
+    }
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+    Ok(if negative { -duration } else { duration })
+}
+
+pub fn unescape_text(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, c == '\\') {
+            (_, true) => match chars.next() {
+                Some('n') | Some('N') => result.push('\n'),
+                Some(escaped) => result.push(escaped),
+                None => {}
+            },
+            (c, false) => result.push(c),
+        }
+    }
+    result
+}
+
+// comma separated values, escaped commas are part of a value
+fn split_list(value: &str) -> Vec<String> {
+    let mut values = vec![];
+    let mut current = String::new();
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            ',' if !escaped => values.push(unescape_text(std::mem::take(&mut current).as_str())),
+            c => current.push(c),
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    values.push(unescape_text(current.as_str()));
+    values
+}
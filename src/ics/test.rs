@@ -0,0 +1,153 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{DateTime, Duration, FixedOffset};
+
+    use crate::common::exception::InternalError;
+    use crate::ics::{escape_text, export_calendar, parse_calendar, parse_uid};
+    use crate::model::{EventCommonTrait, ItemCommonTrait};
+    use crate::model::alarm::{Alarm, AlarmTrigger};
+    use crate::model::event::Event;
+
+    fn unfold(calendar: &str) -> Vec<String> {
+        calendar
+            .replace("\r\n ", "")
+            .split("\r\n")
+            .map(|l| l.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn export_event_with_alarms() {
+        let start: DateTime<FixedOffset> =
+            DateTime::parse_from_rfc3339("2026-03-02T09:30:00+08:00").unwrap();
+        let mut event = Event::init(None);
+        event.set_title("design review, round 2");
+        event.set_duration(start, start + Duration::hours(1));
+        event.add_alarm(Alarm::before_start(10));
+        event.add_alarm(Alarm::before_start(24 * 60));
+        event.add_alarm(Alarm::at(start - Duration::hours(3)));
+        let events: Vec<Arc<Box<dyn EventCommonTrait>>> = vec![Arc::new(Box::new(event))];
+
+        let lines = unfold(export_calendar(&events).as_str());
+
+        assert_eq!(lines.first().unwrap(), "BEGIN:VCALENDAR");
+        assert!(lines.contains(&"DTSTART:20260302T013000Z".to_string()));
+        assert!(lines.contains(&"SUMMARY:design review\\, round 2".to_string()));
+        assert_eq!(lines.iter().filter(|l| *l == "BEGIN:VALARM").count(), 3);
+        assert!(lines.contains(&"TRIGGER:-PT10M".to_string()));
+        assert!(lines.contains(&"TRIGGER:-P1D".to_string()));
+        assert!(lines.contains(&"TRIGGER;VALUE=DATE-TIME:20260301T223000Z".to_string()));
+    }
+
+    #[test]
+    fn occurrence_moves_fixed_time_alarms() {
+        let start: DateTime<FixedOffset> =
+            DateTime::parse_from_rfc3339("2026-03-02T09:30:00Z").unwrap();
+        let mut event = Event::init(None);
+        event.set_duration(start, start + Duration::hours(1));
+        event.add_alarm(Alarm::before_start(10));
+        event.add_alarm(Alarm::at(start - Duration::hours(3)));
+        let next_start = start + Duration::days(7);
+
+        let occurrence = event.occurrence(next_start, next_start + Duration::hours(1));
+
+        assert_ne!(occurrence.get_id(), event.get_id());
+        let fire_times = occurrence
+            .get_alarms()
+            .iter()
+            .map(|a| a.fire_time(occurrence.get_start_time()))
+            .collect::<Vec<DateTime<FixedOffset>>>();
+        assert_eq!(fire_times[0], next_start - Duration::minutes(10));
+        assert_eq!(fire_times[1], next_start - Duration::hours(3));
+    }
+
+    #[test]
+    fn long_lines_are_folded() {
+        let mut event = Event::init(None);
+        event.set_title("a".repeat(200).as_str());
+        event.set_description("line one\nline two; done");
+        let events: Vec<Arc<Box<dyn EventCommonTrait>>> = vec![Arc::new(Box::new(event))];
+        let calendar = export_calendar(&events);
+        assert!(calendar.split("\r\n").all(|l| l.len() <= 75));
+        assert!(unfold(calendar.as_str()).contains(&format!("SUMMARY:{}", "a".repeat(200))));
+        assert_eq!(escape_text("line one\nline two; done"), r"line one\nline two\; done");
+    }
+
+    #[test]
+    fn exported_events_are_parsed_back() {
+        let start: DateTime<FixedOffset> =
+            DateTime::parse_from_rfc3339("2026-03-02T09:30:00Z").unwrap();
+        let mut event = Event::init(None);
+        event.set_title("a".repeat(100).as_str());
+        event.set_description("line one\nline two; done");
+        event.set_duration(start, start + Duration::hours(1));
+        let alarm = Alarm::before_start(10);
+        let alarm_id = alarm.get_id();
+        event.add_alarm(alarm);
+        event.add_alarm(Alarm::at(start - Duration::hours(3)));
+        let id = event.get_id();
+        let events: Vec<Arc<Box<dyn EventCommonTrait>>> = vec![Arc::new(Box::new(event))];
+
+        let parsed = parse_calendar(export_calendar(&events).as_str()).unwrap();
+
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].id, id);
+        assert_eq!(parsed[0].title, "a".repeat(100));
+        assert_eq!(parsed[0].description, "line one\nline two; done");
+        assert_eq!(parsed[0].start_time, start.timestamp_millis());
+        assert_eq!(parsed[0].category, "Default");
+        assert_eq!(parsed[0].alarms[0].get_id(), alarm_id);
+        assert_eq!(parsed[0].alarms[0].trigger, AlarmTrigger::BeforeStart { minutes: 10 });
+        let fire_time = parsed[0].alarms[1].fire_time(start);
+        assert_eq!(fire_time, start - Duration::hours(3));
+    }
+
+    #[test]
+    fn foreign_calendars_are_parsed() {
+        let calendar = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VTIMEZONE\r\nTZID:Europe/Paris\r\n\
+            END:VTIMEZONE\r\nBEGIN:VEVENT\r\nUID:holiday@example.com\r\nDTSTART;VALUE=DATE:20260501\r\n\
+            SUMMARY:Labour\r\n  Day\r\nCATEGORIES:Holiday\\, public,Other\r\nEND:VEVENT\r\n\
+            BEGIN:VEVENT\r\nUID:standup@example.com\r\n\
+            DTSTART;TZID=\"Europe/Paris\";X-LINK=\"http://a\":20260302T090000\r\nDURATION:PT15M\r\n\
+            BEGIN:VALARM\r\nTRIGGER;RELATED=END:-PT5M\r\nEND:VALARM\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+
+        let parsed = parse_calendar(calendar).unwrap();
+
+        let day: DateTime<FixedOffset> = DateTime::parse_from_rfc3339("2026-05-01T00:00:00Z").unwrap();
+        assert_eq!(parsed[0].id, parse_uid("holiday@example.com"));
+        assert_eq!(parsed[0].title, "Labour Day");
+        assert_eq!(parsed[0].category, "Holiday, public");
+        assert_eq!(parsed[0].end_time, (day + Duration::days(1)).timestamp_millis());
+        assert_eq!(parsed[1].end_time - parsed[1].start_time, 15 * 60 * 1000);
+        assert_eq!(parsed[1].alarms[0].trigger, AlarmTrigger::BeforeStart { minutes: -10 });
+        assert!(parse_calendar("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nEND:VEVENT\r\n").is_err());
+        assert!(parse_calendar("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:x\r\nDTSTART:P1M\r\n").is_err());
+    }
+
+    #[test]
+    fn durations_out_of_time_are_rejected() {
+        let event = |duration: &str, trigger: &str| {
+            format!(
+                "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:far@example.com\r\n\
+                 DTSTART:20260302T090000Z\r\nDURATION:{}\r\nBEGIN:VALARM\r\nTRIGGER:{}\r\n\
+                 END:VALARM\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+                duration, trigger
+            )
+        };
+        assert!(parse_calendar(event("PT15M", "-PT5M").as_str()).is_ok());
+        for (duration, trigger) in [
+            ("P99999999999999W", "-PT5M"),
+            ("P9223372036854775807D", "-PT5M"),
+            ("PT15M", "-P99999999999999W"),
+            ("P9999999999W", "-PT5M"),
+        ] {
+            let error = parse_calendar(event(duration, trigger).as_str()).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<InternalError>(),
+                Some(InternalError::InvalidIcsError { .. })
+            ));
+        }
+    }
+}
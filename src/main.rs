@@ -3,7 +3,21 @@ mod model;
 mod cache;
 mod persistent;
 mod api;
+mod caldav;
 mod common;
+mod ics;
+mod notification;
+mod query;
 mod ui;
 
-fn main() {}
+#[tokio::main]
+async fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    match ui::cli::run(args).await {
+        Ok(output) => print!("{}", output),
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use anyhow::bail;
+use anyhow::Result;
+use chrono::{DateTime, Duration, FixedOffset};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::common::exception::InternalError;
+use crate::common::utils::convert_from_string_to_datetime;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Alarm {
+    id: u128,
+    pub trigger: AlarmTrigger,
+    pub channel: Option<String>, // named notification sink, None for the default sinks
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum AlarmTrigger {
+    BeforeStart { minutes: i64 },
+    At { time: i64, timezone: String },
+}
+
+impl Alarm {
+    pub fn new(trigger: AlarmTrigger, channel: Option<String>) -> Self {
+        Alarm {
+            id: Uuid::new_v4().as_u128(),
+            trigger,
+            channel,
+        }
+    }
+
+    pub fn before_start(minutes: i64) -> Self {
+        Alarm::new(AlarmTrigger::BeforeStart { minutes }, None)
+    }
+
+    pub fn at(time: DateTime<FixedOffset>) -> Self {
+        Alarm::new(
+            AlarmTrigger::At {
+                time: time.timestamp_millis(),
+                timezone: time.offset().to_string(),
+            },
+            None,
+        )
+    }
+
+    pub fn get_id(&self) -> u128 {
+        self.id
+    }
+
+    // keeps the id an alarm had when it was exported
+    pub fn with_id(mut self, id: u128) -> Self {
+        self.id = id;
+        self
+    }
+
+    // triggers are read from clients, so they are checked before the alarm is stored
+    pub fn check_valid(&self) -> Result<()> {
+        match &self.trigger {
+            AlarmTrigger::BeforeStart { minutes } => {
+                if Duration::try_minutes(*minutes).is_none() {
+                    bail!(InternalError::InvalidTimeError {
+                        time: format!("{} minutes", minutes)
+                    })
+                }
+            }
+            AlarmTrigger::At { time, timezone } => {
+                convert_from_string_to_datetime(*time, timezone.clone())?;
+            }
+        }
+        Ok(())
+    }
+
+    // an unchecked trigger that can not be read fires with the event
+    pub fn fire_time(&self, event_start: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        match &self.trigger {
+            AlarmTrigger::BeforeStart { minutes } => Duration::try_minutes(*minutes)
+                .and_then(|before| event_start.checked_sub_signed(before))
+                .unwrap_or(event_start),
+            AlarmTrigger::At { time, timezone } => {
+                convert_from_string_to_datetime(*time, timezone.clone()).unwrap_or(event_start)
+            }
+        }
+    }
+
+    // fixed time alarms move together with the occurrence they belong to
+    pub fn shift(&self, delta: Duration) -> Self {
+        let trigger = match &self.trigger {
+            AlarmTrigger::BeforeStart { minutes } => {
+                AlarmTrigger::BeforeStart { minutes: *minutes }
+            }
+            AlarmTrigger::At { time, timezone } => AlarmTrigger::At {
+                time: time.saturating_add(delta.num_milliseconds()),
+                timezone: timezone.clone(),
+            },
+        };
+        Alarm {
+            id: self.id,
+            trigger,
+            channel: self.channel.clone(),
+        }
+    }
+}
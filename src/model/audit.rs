@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+
+use crate::persistent::PersistentModel;
+
+// author of the changes the server makes by itself
+pub const DEFAULT_ACTOR: &str = "local";
+// entries kept, the oldest are dropped first
+pub const AUDIT_LOG_LIMIT: usize = 10000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum AuditAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+// values as persisted json, old is None for a created event and new for a deleted one
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditEntry {
+    pub event_id: u128,
+    pub time: i64,
+    pub actor: String,
+    pub action: AuditAction,
+    pub changes: Vec<FieldChange>,
+}
+
+impl AuditEntry {
+    // None when the stored event did not change
+    pub fn new(
+        event_id: u128,
+        time: i64,
+        actor: &str,
+        before: Option<&PersistentModel>,
+        after: Option<&PersistentModel>,
+    ) -> Option<Self> {
+        let action = match (before, after) {
+            (None, Some(_)) => AuditAction::Created,
+            (Some(_), Some(_)) => AuditAction::Updated,
+            (Some(_), None) => AuditAction::Deleted,
+            (None, None) => return None,
+        };
+        let before = before.map(fields).unwrap_or_default();
+        let after = after.map(fields).unwrap_or_default();
+        let value = |fields: &[(&str, String)], index: usize| {
+            fields.get(index).map(|(_, value)| value.clone())
+        };
+        let changes = (0..before.len().max(after.len()))
+            .map(|index| FieldChange {
+                field: before.get(index).or(after.get(index)).unwrap().0.to_string(),
+                old: value(&before, index),
+                new: value(&after, index),
+            })
+            .filter(|change| change.old != change.new)
+            .collect::<Vec<FieldChange>>();
+        if action == AuditAction::Updated && changes.is_empty() {
+            return None;
+        }
+        Some(AuditEntry {
+            event_id,
+            time,
+            actor: actor.to_string(),
+            action,
+            changes,
+        })
+    }
+}
+
+// stored fields as json text, the generator instance by its id
+fn fields(model: &PersistentModel) -> Vec<(&'static str, String)> {
+    vec![
+        ("kind", to_json(&model.kind)),
+        ("title", to_json(&model.title)),
+        ("description", to_json(&model.description)),
+        ("start_time", to_json(&model.start_time)),
+        ("start_time_timezone", to_json(&model.start_time_timezone)),
+        ("end_time", to_json(&model.end_time)),
+        ("end_time_timezone", to_json(&model.end_time_timezone)),
+        ("color", to_json(&model.color)),
+        ("important_level", to_json(&model.important_level)),
+        ("category", to_json(&model.category)),
+        (
+            "generator_instance",
+            to_json(&model.generator_instance.as_ref().map(|instance| instance.get_id())),
+        ),
+        ("key_result", to_json(&model.key_result)),
+        ("task", to_json(&model.task)),
+        ("alarms", to_json(&model.alarms)),
+        ("actual_start_time", to_json(&model.actual_start_time)),
+        ("actual_start_time_timezone", to_json(&model.actual_start_time_timezone)),
+        ("actual_end_time", to_json(&model.actual_end_time)),
+        ("actual_end_time_timezone", to_json(&model.actual_end_time_timezone)),
+        ("unplanned", to_json(&model.unplanned)),
+        ("calendar", to_json(&model.calendar)),
+        ("tags", to_json(&model.tags)),
+    ]
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
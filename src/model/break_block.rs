@@ -0,0 +1,193 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use uuid::Uuid;
+
+use crate::model::{Category, EventCommonTrait, ImportantLevel, ItemCommonTrait, Kind};
+use crate::model::generator_instance::GeneratorInstance;
+use crate::persistent::PersistentModel;
+
+// rest period inserted by the break planner, it occupies time like an event
+#[derive(Clone)]
+pub struct Break {
+    id: u128,
+    title: String,
+    description: String,
+    start_time: DateTime<FixedOffset>,
+    end_time: DateTime<FixedOffset>,
+    color: String,
+    important_level: String,
+    category: String,
+    generator_instance: Option<u128>,
+    calendar: Option<u128>, // None for the main calendar
+    tags: BTreeSet<String>,
+    uid: Option<String>,  // the UID a client gave it, served back unchanged
+    href: Option<String>, // resource name a CalDAV client put it under
+    revision: u64,
+}
+
+impl ItemCommonTrait for Break {
+    fn get_id(&self) -> u128 {
+        self.id
+    }
+
+    fn get_kind(&self) -> Kind {
+        Kind::Break
+    }
+
+    fn get_title(&self) -> &str {
+        self.title.as_str()
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.title = title.to_string()
+    }
+
+    fn get_description(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn set_description(&mut self, description: &str) {
+        self.description = description.to_string()
+    }
+
+    fn get_color(&self) -> &str {
+        self.color.as_str()
+    }
+
+    fn set_color(&mut self, color: &str) {
+        self.color = color.to_string();
+    }
+
+    fn set_importance(&mut self, important_level: ImportantLevel) {
+        self.important_level = important_level.to_string();
+    }
+
+    fn get_importance(&self) -> ImportantLevel {
+        ImportantLevel::from(self.important_level.as_str())
+    }
+
+    fn get_categories(&self) -> Category {
+        Category::from(self.category.as_str())
+    }
+
+    fn set_categories(&mut self, category: Category) {
+        self.category = category.to_string();
+    }
+}
+
+impl EventCommonTrait for Break {
+    fn get_start_time(&self) -> DateTime<FixedOffset> {
+        self.start_time
+    }
+
+    fn get_end_time(&self) -> DateTime<FixedOffset> {
+        self.end_time
+    }
+
+    fn set_duration(&mut self, start_time: DateTime<FixedOffset>, end_time: DateTime<FixedOffset>) {
+        self.start_time = start_time;
+        self.end_time = end_time;
+    }
+
+    fn set_generator_instance(&mut self, generator_instance: u128) {
+        self.generator_instance = Some(generator_instance);
+    }
+
+    fn get_generator_instance(&self) -> Option<u128> {
+        self.generator_instance
+    }
+
+    fn get_tags(&self) -> &BTreeSet<String> {
+        &self.tags
+    }
+
+    fn set_tags(&mut self, tags: BTreeSet<String>) {
+        self.tags = tags;
+    }
+
+    fn get_calendar(&self) -> Option<u128> {
+        self.calendar
+    }
+
+    fn set_calendar(&mut self, calendar: Option<u128>) {
+        self.calendar = calendar;
+    }
+
+    fn get_uid(&self) -> Option<&str> {
+        self.uid.as_deref()
+    }
+
+    fn set_uid(&mut self, uid: Option<String>) {
+        self.uid = uid;
+    }
+
+    fn get_href(&self) -> Option<&str> {
+        self.href.as_deref()
+    }
+
+    fn set_href(&mut self, href: Option<String>) {
+        self.href = href;
+    }
+
+    fn get_revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn set_revision(&mut self, revision: u64) {
+        self.revision = revision;
+    }
+
+    fn convert_to(&self, generator_instance: Option<GeneratorInstance>) -> PersistentModel {
+        PersistentModel {
+            id: self.id,
+            kind: self.get_kind().to_string(),
+            title: self.title.clone(),
+            description: self.description.clone(),
+            start_time: self.start_time.timestamp_millis(),
+            start_time_timezone: self.start_time.offset().to_string(),
+            end_time: self.end_time.timestamp_millis(),
+            end_time_timezone: self.end_time.offset().to_string(),
+            color: self.color.clone(),
+            important_level: self.important_level.clone(),
+            category: self.category.clone(),
+            generator_instance,
+            key_result: None,
+            task: None,
+            alarms: vec![],
+            actual_start_time: None,
+            actual_start_time_timezone: None,
+            actual_end_time: None,
+            actual_end_time_timezone: None,
+            unplanned: false,
+            calendar: self.calendar,
+            tags: self.tags.clone(),
+            uid: self.uid.clone(),
+            href: self.href.clone(),
+            revision: self.revision,
+        }
+    }
+}
+
+impl Break {
+    pub fn init(id: Option<u128>) -> Self {
+        let uuid = Uuid::new_v4().as_u128();
+        let now = DateTime::from(Utc::now());
+        Break {
+            id: id.unwrap_or(uuid),
+            title: "Break".to_string(),
+            description: "".to_string(),
+            start_time: now,
+            end_time: now,
+            color: "".to_string(),
+            important_level: ImportantLevel::Medium.to_string(),
+            category: Category::Default.to_string(),
+            generator_instance: None,
+            calendar: None,
+            tags: BTreeSet::new(),
+            uid: None,
+            href: None,
+            revision: 0,
+        }
+    }
+}
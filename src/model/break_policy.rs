@@ -0,0 +1,262 @@
+use anyhow::bail;
+use anyhow::Result;
+use chrono::{DateTime, Duration, FixedOffset, NaiveTime, TimeZone};
+
+use crate::common::exception::InternalError;
+
+// built through new and with_lunch, which check the lengths the analysis divides by
+#[derive(Debug, Clone)]
+pub struct BreakPolicy {
+    max_work_minutes: i64, // longest consecutive work before a break is due
+    break_minutes: i64,    // a gap at least this long between events counts as a break
+    max_meeting_streak: Option<usize>,
+    lunch: Option<LunchWindow>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LunchWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub minutes: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BreakViolation {
+    LongWorkStreak {
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+    },
+    MeetingStreak {
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        count: usize,
+    },
+    NoLunchBreak {
+        window_start: DateTime<FixedOffset>,
+        window_end: DateTime<FixedOffset>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedBreak {
+    pub title: String,
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BreakReport {
+    pub violations: Vec<BreakViolation>,
+    pub planned: Vec<PlannedBreak>, // breaks that fit into free gaps of the day
+}
+
+pub type TimeBlock = (DateTime<FixedOffset>, DateTime<FixedOffset>);
+
+// start, end and number of events of a run of consecutive work
+type WorkStreak = (DateTime<FixedOffset>, DateTime<FixedOffset>, usize);
+
+impl Default for BreakPolicy {
+    fn default() -> Self {
+        BreakPolicy {
+            max_work_minutes: 90,
+            break_minutes: 10,
+            max_meeting_streak: Some(4),
+            lunch: Some(LunchWindow {
+                start: NaiveTime::from_hms_opt(11, 30, 0).unwrap(),
+                end: NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
+                minutes: 30,
+            }),
+        }
+    }
+}
+
+impl BreakPolicy {
+    // without a meeting streak limit or lunch window
+    pub fn new(max_work_minutes: i64, break_minutes: i64) -> Result<Self> {
+        check_minutes("max_work_minutes", max_work_minutes)?;
+        check_minutes("break_minutes", break_minutes)?;
+        Ok(BreakPolicy {
+            max_work_minutes,
+            break_minutes,
+            max_meeting_streak: None,
+            lunch: None,
+        })
+    }
+
+    pub fn with_meeting_streak(mut self, max_meeting_streak: Option<usize>) -> Self {
+        self.max_meeting_streak = max_meeting_streak;
+        self
+    }
+
+    pub fn get_max_meeting_streak(&self) -> Option<usize> {
+        self.max_meeting_streak
+    }
+
+    pub fn get_lunch(&self) -> Option<&LunchWindow> {
+        self.lunch.as_ref()
+    }
+
+    pub fn with_lunch(mut self, lunch: Option<LunchWindow>) -> Result<Self> {
+        if let Some(lunch) = &lunch {
+            check_minutes("lunch minutes", lunch.minutes)?;
+            if lunch.start >= lunch.end {
+                bail!(InternalError::InvalidBreakPolicyError {
+                    reason: format!("lunch window {} to {} is empty", lunch.start, lunch.end)
+                })
+            }
+        }
+        self.lunch = lunch;
+        Ok(self)
+    }
+
+    // work are the events of the day, breaks the existing rest blocks,
+    // occupied every timed item, planned breaks never overlap any of them
+    pub fn analyse(
+        &self,
+        day_start: DateTime<FixedOffset>,
+        work: &[TimeBlock],
+        breaks: &[TimeBlock],
+        occupied: &[TimeBlock],
+    ) -> BreakReport {
+        let day_end = day_start + Duration::days(1);
+        let break_length = Duration::minutes(self.break_minutes);
+        let mut report = BreakReport::default();
+        let mut busy = occupied.to_vec();
+        let mut rests = breaks.to_vec();
+        for (start, end, count) in self.work_streaks(work) {
+            let too_long =
+                end.signed_duration_since(start) > Duration::minutes(self.max_work_minutes);
+            if too_long {
+                report
+                    .violations
+                    .push(BreakViolation::LongWorkStreak { start, end });
+            }
+            let too_many = self.max_meeting_streak.is_some_and(|max| count > max);
+            if too_many {
+                report
+                    .violations
+                    .push(BreakViolation::MeetingStreak { start, end, count });
+            }
+            // one break is due for every full interval of work, each goes into the first
+            // free gap from its mark on, events themselves are never moved
+            let mut marks = vec![];
+            if too_long {
+                let minutes = end.signed_duration_since(start).num_minutes();
+                marks.extend(
+                    (1..=minutes / self.max_work_minutes)
+                        .map(|i| start + Duration::minutes(self.max_work_minutes * i)),
+                );
+            } else if too_many {
+                marks.push(end);
+            }
+            for mark in marks {
+                let Some(break_start) = find_free_slot(mark, day_end, break_length, &busy) else {
+                    continue;
+                };
+                // a rest already taken between the mark and the free gap pays for the mark
+                let taken = rests.iter().position(|(rest_start, _)| {
+                    rest_start.ge(&mark) && rest_start.le(&break_start)
+                });
+                if let Some(index) = taken {
+                    rests.remove(index);
+                } else {
+                    busy.push((break_start, break_start + break_length));
+                    report.planned.push(PlannedBreak {
+                        title: "Break".to_string(),
+                        start: break_start,
+                        end: break_start + break_length,
+                    });
+                }
+            }
+        }
+        if let Some(lunch) = &self.lunch {
+            let window_start = at_time(day_start, lunch.start);
+            let window_end = at_time(day_start, lunch.end);
+            let lunch_length = Duration::minutes(lunch.minutes);
+            let has_lunch = breaks.iter().any(|(start, end)| {
+                end.min(&window_end)
+                    .signed_duration_since(*start.max(&window_start))
+                    .ge(&lunch_length)
+            });
+            if !has_lunch {
+                match find_free_slot(window_start, window_end, lunch_length, &busy) {
+                    Some(start) => {
+                        busy.push((start, start + lunch_length));
+                        report.planned.push(PlannedBreak {
+                            title: "Lunch".to_string(),
+                            start,
+                            end: start + lunch_length,
+                        });
+                    }
+                    None => report.violations.push(BreakViolation::NoLunchBreak {
+                        window_start,
+                        window_end,
+                    }),
+                }
+            }
+        }
+        report.planned.sort_by_key(|b| b.start);
+        report
+    }
+
+    // consecutive events, a gap shorter than a break does not end the streak
+    fn work_streaks(&self, work: &[TimeBlock]) -> Vec<WorkStreak> {
+        let mut work = work.to_vec();
+        work.sort_by_key(|(start, _)| *start);
+        let mut streaks: Vec<WorkStreak> = vec![];
+        for (start, end) in work {
+            match streaks.last_mut() {
+                Some(last)
+                    if start.signed_duration_since(last.1)
+                        < Duration::minutes(self.break_minutes) =>
+                {
+                    last.1 = last.1.max(end);
+                    last.2 += 1;
+                }
+                _ => streaks.push((start, end, 1)),
+            }
+        }
+        streaks
+    }
+}
+
+fn check_minutes(name: &str, minutes: i64) -> Result<()> {
+    if minutes <= 0 || Duration::try_minutes(minutes).is_none() {
+        bail!(InternalError::InvalidBreakPolicyError {
+            reason: format!("{} must be a positive number of minutes, not {}", name, minutes)
+        })
+    }
+    Ok(())
+}
+
+fn at_time(day_start: DateTime<FixedOffset>, time: NaiveTime) -> DateTime<FixedOffset> {
+    day_start
+        .timezone()
+        .from_local_datetime(&day_start.date_naive().and_time(time))
+        .unwrap()
+}
+
+fn find_free_slot(
+    from: DateTime<FixedOffset>,
+    to: DateTime<FixedOffset>,
+    length: Duration,
+    busy: &[TimeBlock],
+) -> Option<DateTime<FixedOffset>> {
+    let mut busy = busy.to_vec();
+    busy.sort_by_key(|(start, _)| *start);
+    let mut candidate = from;
+    for (busy_start, busy_end) in busy {
+        if busy_end.le(&candidate) {
+            continue;
+        }
+        if busy_start.ge(&(candidate + length)) {
+            break;
+        }
+        candidate = busy_end;
+    }
+    if (candidate + length).le(&to) {
+        Some(candidate)
+    } else {
+        None
+    }
+}
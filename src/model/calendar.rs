@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::model::category::ConflictBehavior;
+
+// name events without a calendar are queried by, no calendar may take it
+pub const MAIN_CALENDAR: &str = "main";
+
+// named calendar an event belongs to, e.g. Work, Personal or Team, events without one
+// are in the main calendar
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Calendar {
+    id: u128,
+    pub name: String,
+    pub color: String,
+    pub visible: bool, // hidden calendars only show in queries naming them
+    pub conflict: ConflictBehavior, // when its events overlap an event they conflict with
+    pub isolated: bool, // its events only conflict with each other
+}
+
+impl Calendar {
+    pub fn new(name: &str, color: &str) -> Self {
+        Calendar {
+            id: Uuid::new_v4().as_u128(),
+            name: name.to_string(),
+            color: color.to_string(),
+            visible: true,
+            conflict: ConflictBehavior::Reject,
+            isolated: false,
+        }
+    }
+
+    pub fn get_id(&self) -> u128 {
+        self.id
+    }
+}
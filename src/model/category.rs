@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::model::{Category, EventCommonTrait, ImportantLevel};
+
+// user defined category, events reference it by id through Category::Custom
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserCategory {
+    id: u128,
+    pub name: String,
+    pub color: String,
+    pub important_level: String,
+    #[serde(default)]
+    pub conflict: ConflictBehavior,
+}
+
+// what inserting an event of the category does when it overlaps another event
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum ConflictBehavior {
+    #[default]
+    Reject, // fail with a conflict error
+    Skip,  // drop the conflicting event and report it as skipped
+    Allow, // keep both events
+}
+
+impl UserCategory {
+    pub fn new(name: &str, color: &str, important_level: ImportantLevel) -> Self {
+        UserCategory {
+            id: Uuid::new_v4().as_u128(),
+            name: name.to_string(),
+            color: color.to_string(),
+            important_level: important_level.to_string(),
+            conflict: ConflictBehavior::Reject,
+        }
+    }
+
+    pub fn get_id(&self) -> u128 {
+        self.id
+    }
+
+    pub fn get_importance(&self) -> ImportantLevel {
+        ImportantLevel::from(self.important_level.as_str())
+    }
+
+    // put the event in this category and take over its color and importance
+    pub fn apply_defaults(&self, event: &mut dyn EventCommonTrait) {
+        event.set_categories(Category::Custom(self.id));
+        event.set_color(self.color.as_str());
+        event.set_importance(self.get_importance());
+    }
+}
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+use crate::model::audit::AuditAction;
+
+// changes kept for subscribers catching up, older cursors cannot be resumed
+pub const CHANGE_LOG_LIMIT: usize = 1000;
+
+// one stored change of an event, cursors increase by one with every change
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChangeEvent {
+    pub cursor: u64,
+    pub event_id: u128,
+    pub action: AuditAction,
+    pub revision: u64, // of the event after the change, or before a delete
+    pub time: i64,
+}
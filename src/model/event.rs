@@ -1,7 +1,10 @@
+use std::collections::BTreeSet;
+
 use chrono::{DateTime, FixedOffset, Utc};
 use uuid::Uuid;
 
-use crate::model::{Category, EventCommonTrait, ImportantLevel, Kind};
+use crate::model::{Category, EventCommonTrait, ImportantLevel, ItemCommonTrait, Kind};
+use crate::model::alarm::Alarm;
 use crate::model::generator_instance::GeneratorInstance;
 use crate::persistent::PersistentModel;
 
@@ -16,9 +19,20 @@ pub struct Event {
     important_level: String,
     category: String,
     generator_instance: Option<u128>,
+    key_result: Option<u128>,
+    task: Option<u128>,
+    alarms: Vec<Alarm>,
+    actual_start_time: Option<DateTime<FixedOffset>>, // tracked time, planned time is start/end
+    actual_end_time: Option<DateTime<FixedOffset>>,
+    unplanned: bool, // tracked ad hoc, takes no planned time and never conflicts
+    calendar: Option<u128>, // None for the main calendar
+    tags: BTreeSet<String>,
+    uid: Option<String>,  // the UID a client gave it, served back unchanged
+    href: Option<String>, // resource name a CalDAV client put it under
+    revision: u64, // bumped by the cache on every stored change
 }
 
-impl EventCommonTrait for Event {
+impl ItemCommonTrait for Event {
     fn get_id(&self) -> u128 {
         self.id
     }
@@ -43,19 +57,6 @@ impl EventCommonTrait for Event {
         self.description = description.to_string();
     }
 
-    fn get_start_time(&self) -> DateTime<FixedOffset> {
-        self.start_time.clone()
-    }
-
-    fn get_end_time(&self) -> DateTime<FixedOffset> {
-        self.end_time.clone()
-    }
-
-    fn set_duration(&mut self, start_time: DateTime<FixedOffset>, end_time: DateTime<FixedOffset>) {
-        self.start_time = start_time;
-        self.end_time = end_time;
-    }
-
     fn get_color(&self) -> &str {
         self.color.as_str()
     }
@@ -79,6 +80,21 @@ impl EventCommonTrait for Event {
     fn set_categories(&mut self, category: Category) {
         self.category = category.to_string();
     }
+}
+
+impl EventCommonTrait for Event {
+    fn get_start_time(&self) -> DateTime<FixedOffset> {
+        self.start_time.clone()
+    }
+
+    fn get_end_time(&self) -> DateTime<FixedOffset> {
+        self.end_time.clone()
+    }
+
+    fn set_duration(&mut self, start_time: DateTime<FixedOffset>, end_time: DateTime<FixedOffset>) {
+        self.start_time = start_time;
+        self.end_time = end_time;
+    }
 
     fn set_generator_instance(&mut self, generator_instance: u128) {
         self.generator_instance = Some(generator_instance);
@@ -88,6 +104,50 @@ impl EventCommonTrait for Event {
         self.generator_instance
     }
 
+    fn get_tags(&self) -> &BTreeSet<String> {
+        &self.tags
+    }
+
+    fn set_tags(&mut self, tags: BTreeSet<String>) {
+        self.tags = tags;
+    }
+
+    fn get_calendar(&self) -> Option<u128> {
+        self.calendar
+    }
+
+    fn set_calendar(&mut self, calendar: Option<u128>) {
+        self.calendar = calendar;
+    }
+
+    fn get_uid(&self) -> Option<&str> {
+        self.uid.as_deref()
+    }
+
+    fn set_uid(&mut self, uid: Option<String>) {
+        self.uid = uid;
+    }
+
+    fn get_href(&self) -> Option<&str> {
+        self.href.as_deref()
+    }
+
+    fn set_href(&mut self, href: Option<String>) {
+        self.href = href;
+    }
+
+    fn get_revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn set_revision(&mut self, revision: u64) {
+        self.revision = revision;
+    }
+
+    fn is_blocking(&self) -> bool {
+        !self.unplanned
+    }
+
     fn convert_to(&self, generator_instance: Option<GeneratorInstance>) -> PersistentModel {
         PersistentModel {
             id: self.id,
@@ -102,16 +162,28 @@ impl EventCommonTrait for Event {
             important_level: self.important_level.clone(),
             category: self.category.clone(),
             generator_instance,
+            key_result: self.key_result,
+            task: self.task,
+            alarms: self.alarms.clone(),
+            actual_start_time: self.actual_start_time.map(|t| t.timestamp_millis()),
+            actual_start_time_timezone: self.actual_start_time.map(|t| t.offset().to_string()),
+            actual_end_time: self.actual_end_time.map(|t| t.timestamp_millis()),
+            actual_end_time_timezone: self.actual_end_time.map(|t| t.offset().to_string()),
+            unplanned: self.unplanned,
+            calendar: self.calendar,
+            tags: self.tags.clone(),
+            uid: self.uid.clone(),
+            href: self.href.clone(),
+            revision: self.revision,
         }
     }
 }
 
 impl Event {
     pub fn init(id: Option<u128>) -> Self {
-        let uuid = Uuid::new_v4().as_u128();
         let now = DateTime::from(Utc::now());
         Event {
-            id: if id.is_some() { id.unwrap() } else { uuid },
+            id: id.unwrap_or_else(|| Uuid::new_v4().as_u128()),
             title: String::new(),
             description: String::new(),
             start_time: now,
@@ -120,6 +192,17 @@ impl Event {
             important_level: "".to_string(),
             category: "".to_string(),
             generator_instance: None,
+            key_result: None,
+            task: None,
+            alarms: vec![],
+            actual_start_time: None,
+            actual_end_time: None,
+            unplanned: false,
+            calendar: None,
+            tags: BTreeSet::new(),
+            uid: None,
+            href: None,
+            revision: 0,
         }
     }
 
@@ -138,6 +221,84 @@ impl Event {
             important_level: self.important_level.to_string(),
             category: self.category.clone(),
             generator_instance: self.generator_instance,
+            key_result: self.key_result,
+            task: self.task,
+            alarms: self.alarms.clone(),
+            actual_start_time: self.actual_start_time,
+            actual_end_time: self.actual_end_time,
+            unplanned: self.unplanned,
+            calendar: self.calendar,
+            tags: self.tags.clone(),
+            uid: if is_new { None } else { self.uid.clone() },
+            href: if is_new { None } else { self.href.clone() },
+            revision: if is_new { 0 } else { self.revision },
         }
     }
+
+    // new occurrence of the same series at another time, alarms follow the occurrence
+    pub fn occurrence(
+        &self,
+        start_time: DateTime<FixedOffset>,
+        end_time: DateTime<FixedOffset>,
+    ) -> Self {
+        let delta = start_time.signed_duration_since(self.start_time);
+        let mut event = self.self_clone(true);
+        event.set_duration(start_time, end_time);
+        event.set_actual_duration(None, None);
+        event.alarms = self.alarms.iter().map(|a| a.shift(delta)).collect();
+        event
+    }
+
+    pub fn get_key_result(&self) -> Option<u128> {
+        self.key_result
+    }
+
+    pub fn set_key_result(&mut self, key_result: Option<u128>) {
+        self.key_result = key_result;
+    }
+
+    pub fn get_task(&self) -> Option<u128> {
+        self.task
+    }
+
+    pub fn set_task(&mut self, task: Option<u128>) {
+        self.task = task;
+    }
+
+    pub fn get_alarms(&self) -> &Vec<Alarm> {
+        &self.alarms
+    }
+
+    pub fn set_alarms(&mut self, alarms: Vec<Alarm>) {
+        self.alarms = alarms;
+    }
+
+    pub fn add_alarm(&mut self, alarm: Alarm) {
+        self.alarms.push(alarm);
+    }
+
+    pub fn get_actual_start_time(&self) -> Option<DateTime<FixedOffset>> {
+        self.actual_start_time
+    }
+
+    pub fn get_actual_end_time(&self) -> Option<DateTime<FixedOffset>> {
+        self.actual_end_time
+    }
+
+    pub fn set_actual_duration(
+        &mut self,
+        actual_start_time: Option<DateTime<FixedOffset>>,
+        actual_end_time: Option<DateTime<FixedOffset>>,
+    ) {
+        self.actual_start_time = actual_start_time;
+        self.actual_end_time = actual_end_time;
+    }
+
+    pub fn is_unplanned(&self) -> bool {
+        self.unplanned
+    }
+
+    pub fn set_unplanned(&mut self, unplanned: bool) {
+        self.unplanned = unplanned;
+    }
 }
@@ -0,0 +1,157 @@
+use std::collections::VecDeque;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::model::calendar::Calendar;
+use crate::model::category::UserCategory;
+use crate::model::generator_instance::GeneratorInstance;
+use crate::model::objective::{KeyResult, Objective};
+use crate::model::view::SavedView;
+use crate::persistent::{PersistentModel, PersistentTaskModel};
+
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+// state of one stored item before and after a mutation, None when it did not exist,
+// undo puts back before and redo after
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Change {
+    Event {
+        id: u128,
+        before: Option<Box<PersistentModel>>,
+        after: Option<Box<PersistentModel>>,
+    },
+    Instance {
+        id: u128,
+        before: Option<GeneratorInstance>,
+        after: Option<GeneratorInstance>,
+    },
+    Objective {
+        id: u128,
+        before: Option<Objective>,
+        after: Option<Objective>,
+    },
+    KeyResult {
+        id: u128,
+        before: Option<KeyResult>,
+        after: Option<KeyResult>,
+    },
+    Task {
+        id: u128,
+        before: Option<Box<PersistentTaskModel>>,
+        after: Option<Box<PersistentTaskModel>>,
+    },
+    Category {
+        id: u128,
+        before: Option<UserCategory>,
+        after: Option<UserCategory>,
+    },
+    View {
+        id: u128,
+        before: Option<SavedView>,
+        after: Option<SavedView>,
+    },
+    Calendar {
+        id: u128,
+        before: Option<Calendar>,
+        after: Option<Calendar>,
+    },
+}
+
+// all changes of one dynamic process, undone and redone together
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryStep {
+    pub time: i64,
+    pub changes: Vec<Change>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct History {
+    pub limit: usize,
+    pub undo: VecDeque<HistoryStep>,
+    pub redo: Vec<HistoryStep>,
+    #[serde(skip)]
+    pub pending: Vec<Change>,
+    #[serde(skip)]
+    pub replaying: bool, // changes made by undo and redo are not recorded again
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History {
+            limit: DEFAULT_HISTORY_LIMIT,
+            undo: VecDeque::new(),
+            redo: vec![],
+            pending: vec![],
+            replaying: false,
+        }
+    }
+}
+
+impl Change {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Change::Event { .. } => "event",
+            Change::Instance { .. } => "instance",
+            Change::Objective { .. } => "objective",
+            Change::KeyResult { .. } => "key result",
+            Change::Task { .. } => "task",
+            Change::Category { .. } => "category",
+            Change::View { .. } => "view",
+            Change::Calendar { .. } => "calendar",
+        }
+    }
+}
+
+impl HistoryStep {
+    // e.g. "12 event, 1 instance"
+    pub fn summary(&self) -> String {
+        let mut counts: Vec<(&str, usize)> = vec![];
+        for change in &self.changes {
+            match counts.iter_mut().find(|(kind, _)| *kind == change.kind()) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((change.kind(), 1)),
+            }
+        }
+        counts
+            .iter()
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+impl History {
+    pub fn record(&mut self, change: Change) {
+        if !self.replaying {
+            self.pending.push(change);
+        }
+    }
+
+    // closes the pending changes as one step, a new step makes the redo steps stale
+    pub fn commit(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        self.undo.push_back(HistoryStep {
+            time: Utc::now().timestamp_millis(),
+            changes: std::mem::take(&mut self.pending),
+        });
+        self.redo.clear();
+        self.truncate();
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        while self.undo.len() > self.limit {
+            self.undo.pop_front();
+        }
+        // the next step to redo is the last one
+        let excess = self.redo.len().saturating_sub(self.limit);
+        self.redo.drain(..excess);
+    }
+}
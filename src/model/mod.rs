@@ -1,37 +1,76 @@
+use std::collections::BTreeSet;
 use std::fmt::Display;
 
 use chrono::{DateTime, FixedOffset, Offset, TimeZone};
 use downcast_rs::{Downcast, impl_downcast};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::common::utils::{MAX_EVENT_TIMESTAMP, MIN_EVENT_TIMESTAMP};
 use crate::model::generator_instance::GeneratorInstance;
 use crate::persistent::PersistentModel;
 
+pub mod alarm;
+pub mod audit;
+pub mod break_block;
+pub mod break_policy;
+pub mod calendar;
+pub mod category;
+pub mod change_feed;
 pub mod event;
 pub mod generator_instance;
+pub mod history;
+pub mod notification;
+pub mod objective;
 pub mod reminder;
+pub mod search;
+pub mod subscription;
+pub mod sync;
+pub mod task;
+pub mod tracking;
+pub mod transaction;
+pub mod user;
+pub mod view;
 
-pub trait EventCommonTrait: Downcast + Send + Sync {
+// what every stored item has, tasks as well as the time blocks below
+pub trait ItemCommonTrait {
     fn get_id(&self) -> u128;
     fn get_kind(&self) -> Kind;
     fn get_title(&self) -> &str;
     fn set_title(&mut self, title: &str);
     fn get_description(&self) -> &str;
     fn set_description(&mut self, description: &str);
-    fn get_start_time(&self) -> DateTime<FixedOffset>;
-    fn get_end_time(&self) -> DateTime<FixedOffset>;
-    fn set_duration(&mut self, start_time: DateTime<FixedOffset>, end_time: DateTime<FixedOffset>);
     fn get_color(&self) -> &str;
     fn set_color(&mut self, color: &str);
     fn set_importance(&mut self, important_level: ImportantLevel);
     fn get_importance(&self) -> ImportantLevel;
     fn get_categories(&self) -> Category;
     fn set_categories(&mut self, category: Category);
+}
+
+pub trait EventCommonTrait: ItemCommonTrait + Downcast + Send + Sync {
+    fn get_start_time(&self) -> DateTime<FixedOffset>;
+    fn get_end_time(&self) -> DateTime<FixedOffset>;
+    fn set_duration(&mut self, start_time: DateTime<FixedOffset>, end_time: DateTime<FixedOffset>);
     fn set_generator_instance(&mut self, generator_instance_id: u128);
     fn get_generator_instance(&self) -> Option<u128>;
+    fn get_tags(&self) -> &BTreeSet<String>;
+    fn set_tags(&mut self, tags: BTreeSet<String>);
+    fn get_calendar(&self) -> Option<u128>;
+    fn set_calendar(&mut self, calendar: Option<u128>);
+    fn get_uid(&self) -> Option<&str>;
+    fn set_uid(&mut self, uid: Option<String>);
+    fn get_href(&self) -> Option<&str>;
+    fn set_href(&mut self, href: Option<String>);
+    fn get_revision(&self) -> u64;
+    fn set_revision(&mut self, revision: u64);
     fn convert_to(&self, generator_instance: Option<GeneratorInstance>) -> PersistentModel;
 
+    // whether the item occupies its time, only blocking items conflict with each other
+    fn is_blocking(&self) -> bool {
+        true
+    }
+
     fn check_valid(&self) -> bool {
         self.get_start_time().lt(&self.get_end_time())
             && self.get_start_time().naive_utc().ge(&MIN_EVENT_TIMESTAMP)
@@ -40,6 +79,8 @@ pub trait EventCommonTrait: Downcast + Send + Sync {
 }
 impl_downcast!(EventCommonTrait);
 
+// declared from low to high, so levels compare by importance
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub enum ImportantLevel {
     Low,
     Medium,
@@ -47,9 +88,12 @@ pub enum ImportantLevel {
     VeryHigh,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum Category {
     Default,
     Other,
+    Custom(u128),    // id of a user defined category
+    Unknown(String), // kept as read so it is written back unchanged
 }
 
 impl Display for ImportantLevel {
@@ -81,6 +125,8 @@ impl Display for Category {
         let str = match self {
             Category::Default => "Default".to_string(),
             Category::Other => "Other".to_string(),
+            Category::Custom(id) => Uuid::from_u128(*id).hyphenated().to_string(),
+            Category::Unknown(s) => s.clone(),
         };
         write!(f, "{}", str)
     }
@@ -89,16 +135,22 @@ impl Display for Category {
 impl From<&str> for Category {
     fn from(s: &str) -> Self {
         match s {
-            "Default" => Category::Default,
+            "Default" | "" => Category::Default,
             "Other" => Category::Other,
-            _ => Category::Default,
+            _ => match Uuid::try_parse(s) {
+                Ok(id) => Category::Custom(id.as_u128()),
+                Err(_) => Category::Unknown(s.to_string()),
+            },
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Kind {
     Event,
     Reminder,
+    Task,
+    Break,
 }
 
 impl Display for Kind {
@@ -106,6 +158,8 @@ impl Display for Kind {
         let str = match self {
             Kind::Event => "Event".to_string(),
             Kind::Reminder => "Reminder".to_string(),
+            Kind::Task => "Task".to_string(),
+            Kind::Break => "Break".to_string(),
         };
         write!(f, "{}", str)
     }
@@ -116,6 +170,8 @@ impl From<&str> for Kind {
         match s {
             "Event" => Kind::Event,
             "Reminder" => Kind::Reminder,
+            "Task" => Kind::Task,
+            "Break" => Kind::Break,
             _ => Kind::Event,
         }
     }
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+// one delivery of a reminder or event alarm, this is what the sinks receive
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Notification {
+    pub source_id: u128,
+    pub alarm_id: Option<u128>,
+    pub channel: Option<String>,
+    pub kind: String,
+    pub title: String,
+    pub description: String,
+    pub fire_time: i64,
+    #[serde(default)]
+    pub user: String, // whose it is, set by the scheduler firing it
+}
+
+// firing state of one reminder or alarm, persisted so a restart never fires it twice
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotificationRecord {
+    pub source_id: u128,
+    #[serde(default)]
+    pub alarm_id: Option<u128>,
+    pub fired_time: Option<i64>,
+    pub snoozed_until: Option<i64>,
+    pub dismissed: bool,
+}
+
+impl NotificationRecord {
+    pub fn new(source_id: u128, alarm_id: Option<u128>) -> Self {
+        NotificationRecord {
+            source_id,
+            alarm_id,
+            fired_time: None,
+            snoozed_until: None,
+            dismissed: false,
+        }
+    }
+
+    // once fired, a record is only due again when its snooze expires
+    pub fn is_due_again(&self, now: i64) -> bool {
+        !self.dismissed && self.snoozed_until.is_some_and(|until| until <= now)
+    }
+}
@@ -0,0 +1,186 @@
+use anyhow::bail;
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::common::exception::InternalError;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Objective {
+    id: u128,
+    pub title: String,
+    pub description: String,
+    pub owner: String,
+    pub period: Quarter,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KeyResult {
+    id: u128,
+    objective_id: u128,
+    pub title: String,
+    pub owner: String,
+    pub unit: String,
+    pub start_value: f64,
+    pub target_value: f64,
+    pub current_value: f64,
+}
+
+// only built through new, stored quarters are checked the same way when they are read
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(try_from = "QuarterFields")]
+pub struct Quarter {
+    year: i32,
+    quarter: u32, // 1 to 4
+}
+
+#[derive(Deserialize)]
+struct QuarterFields {
+    year: i32,
+    quarter: u32,
+}
+
+impl Objective {
+    pub fn new(title: &str, owner: &str, period: Quarter) -> Self {
+        Objective {
+            id: Uuid::new_v4().as_u128(),
+            title: title.to_string(),
+            description: String::new(),
+            owner: owner.to_string(),
+            period,
+        }
+    }
+
+    pub fn get_id(&self) -> u128 {
+        self.id
+    }
+
+    // objective progress is the average progress of all its key results
+    pub fn progress(key_results: &[KeyResult]) -> f64 {
+        if key_results.is_empty() {
+            return 0.0;
+        }
+        key_results.iter().map(|k| k.progress()).sum::<f64>() / key_results.len() as f64
+    }
+}
+
+impl KeyResult {
+    pub fn new(objective_id: u128, title: &str, start_value: f64, target_value: f64) -> Self {
+        KeyResult {
+            id: Uuid::new_v4().as_u128(),
+            objective_id,
+            title: title.to_string(),
+            owner: String::new(),
+            unit: String::new(),
+            start_value,
+            target_value,
+            current_value: start_value,
+        }
+    }
+
+    pub fn get_id(&self) -> u128 {
+        self.id
+    }
+
+    pub fn get_objective_id(&self) -> u128 {
+        self.objective_id
+    }
+
+    // progress in range [0, 1], works for both increasing and decreasing targets
+    pub fn progress(&self) -> f64 {
+        let total = self.target_value - self.start_value;
+        if total == 0.0 {
+            return if self.current_value == self.target_value {
+                1.0
+            } else {
+                0.0
+            };
+        }
+        ((self.current_value - self.start_value) / total).clamp(0.0, 1.0)
+    }
+}
+
+impl TryFrom<QuarterFields> for Quarter {
+    type Error = anyhow::Error;
+
+    fn try_from(fields: QuarterFields) -> Result<Self> {
+        Quarter::new(fields.year, fields.quarter)
+    }
+}
+
+impl Quarter {
+    // the quarter and the first day after it must both be dates chrono can hold
+    pub fn new(year: i32, quarter: u32) -> Result<Self> {
+        let next_year = year.checked_add(1).and_then(|next| NaiveDate::from_ymd_opt(next, 1, 1));
+        if !(1..=4).contains(&quarter)
+            || NaiveDate::from_ymd_opt(year, 1, 1).is_none()
+            || next_year.is_none()
+        {
+            bail!(InternalError::InvalidQuarterError { year, quarter })
+        }
+        Ok(Quarter { year, quarter })
+    }
+
+    pub fn from_date(date: NaiveDate) -> Self {
+        Quarter {
+            year: date.year(),
+            quarter: date.month0() / 3 + 1,
+        }
+    }
+
+    pub fn start_date(&self) -> NaiveDate {
+        NaiveDate::from_ymd_opt(self.year, (self.quarter - 1) * 3 + 1, 1).unwrap()
+    }
+
+    pub fn end_date(&self) -> NaiveDate {
+        let next = if self.quarter == 4 {
+            NaiveDate::from_ymd_opt(self.year + 1, 1, 1)
+        } else {
+            NaiveDate::from_ymd_opt(self.year, self.quarter * 3 + 1, 1)
+        };
+        next.unwrap().pred_opt().unwrap()
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        date.ge(&self.start_date()) && date.le(&self.end_date())
+    }
+}
+
+// time linked to a key result inside a report range, split at the report moment
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct KeyResultTimeReport {
+    pub key_result_id: u128,
+    pub title: String,
+    pub planned_seconds: i64,
+    pub spent_seconds: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ObjectiveTimeReport {
+    pub objective_id: u128,
+    pub title: String,
+    pub planned_seconds: i64,
+    pub spent_seconds: i64,
+    pub key_results: Vec<KeyResultTimeReport>,
+}
+
+impl KeyResultTimeReport {
+    pub fn planned_hours(&self) -> f64 {
+        self.planned_seconds as f64 / 3600.0
+    }
+
+    pub fn spent_hours(&self) -> f64 {
+        self.spent_seconds as f64 / 3600.0
+    }
+}
+
+impl ObjectiveTimeReport {
+    pub fn planned_hours(&self) -> f64 {
+        self.planned_seconds as f64 / 3600.0
+    }
+
+    pub fn spent_hours(&self) -> f64 {
+        self.spent_seconds as f64 / 3600.0
+    }
+}
@@ -1,7 +1,9 @@
+use std::collections::BTreeSet;
+
 use chrono::{DateTime, FixedOffset, Utc};
 use uuid::Uuid;
 
-use crate::model::{Category, EventCommonTrait, ImportantLevel, Kind};
+use crate::model::{Category, EventCommonTrait, ImportantLevel, ItemCommonTrait, Kind};
 use crate::model::generator_instance::GeneratorInstance;
 use crate::persistent::PersistentModel;
 
@@ -15,9 +17,14 @@ pub struct Reminder {
     important_level: String,
     category: String,
     generator_instance: Option<u128>,
+    calendar: Option<u128>, // None for the main calendar
+    tags: BTreeSet<String>,
+    uid: Option<String>,  // the UID a client gave it, served back unchanged
+    href: Option<String>, // resource name a CalDAV client put it under
+    revision: u64,
 }
 
-impl EventCommonTrait for Reminder {
+impl ItemCommonTrait for Reminder {
     fn get_id(&self) -> u128 {
         self.id
     }
@@ -42,19 +49,6 @@ impl EventCommonTrait for Reminder {
         self.description = description.to_string()
     }
 
-    fn get_start_time(&self) -> DateTime<FixedOffset> {
-        self.start_time
-    }
-
-    fn get_end_time(&self) -> DateTime<FixedOffset> {
-        self.end_time
-    }
-
-    fn set_duration(&mut self, start_time: DateTime<FixedOffset>, end_time: DateTime<FixedOffset>) {
-        self.start_time = start_time;
-        self.end_time = end_time;
-    }
-
     fn get_color(&self) -> &str {
         self.color.as_str()
     }
@@ -63,7 +57,6 @@ impl EventCommonTrait for Reminder {
         self.color = color.to_string();
     }
 
-
     fn set_importance(&mut self, important_level: ImportantLevel) {
         self.important_level = important_level.to_string();
     }
@@ -79,6 +72,21 @@ impl EventCommonTrait for Reminder {
     fn set_categories(&mut self, category: Category) {
         self.category = category.to_string();
     }
+}
+
+impl EventCommonTrait for Reminder {
+    fn get_start_time(&self) -> DateTime<FixedOffset> {
+        self.start_time
+    }
+
+    fn get_end_time(&self) -> DateTime<FixedOffset> {
+        self.end_time
+    }
+
+    fn set_duration(&mut self, start_time: DateTime<FixedOffset>, end_time: DateTime<FixedOffset>) {
+        self.start_time = start_time;
+        self.end_time = end_time;
+    }
 
     fn set_generator_instance(&mut self, generator_instance: u128) {
         self.generator_instance = Some(generator_instance);
@@ -88,6 +96,46 @@ impl EventCommonTrait for Reminder {
         self.generator_instance
     }
 
+    fn get_tags(&self) -> &BTreeSet<String> {
+        &self.tags
+    }
+
+    fn set_tags(&mut self, tags: BTreeSet<String>) {
+        self.tags = tags;
+    }
+
+    fn get_calendar(&self) -> Option<u128> {
+        self.calendar
+    }
+
+    fn set_calendar(&mut self, calendar: Option<u128>) {
+        self.calendar = calendar;
+    }
+
+    fn get_uid(&self) -> Option<&str> {
+        self.uid.as_deref()
+    }
+
+    fn set_uid(&mut self, uid: Option<String>) {
+        self.uid = uid;
+    }
+
+    fn get_href(&self) -> Option<&str> {
+        self.href.as_deref()
+    }
+
+    fn set_href(&mut self, href: Option<String>) {
+        self.href = href;
+    }
+
+    fn get_revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn set_revision(&mut self, revision: u64) {
+        self.revision = revision;
+    }
+
     fn convert_to(&self, generator_instance: Option<GeneratorInstance>) -> PersistentModel {
         PersistentModel {
             id: self.id,
@@ -102,16 +150,28 @@ impl EventCommonTrait for Reminder {
             important_level: self.important_level.clone(),
             category: self.category.clone(),
             generator_instance,
+            key_result: None,
+            task: None,
+            alarms: vec![],
+            actual_start_time: None,
+            actual_start_time_timezone: None,
+            actual_end_time: None,
+            actual_end_time_timezone: None,
+            unplanned: false,
+            calendar: self.calendar,
+            tags: self.tags.clone(),
+            uid: self.uid.clone(),
+            href: self.href.clone(),
+            revision: self.revision,
         }
     }
 }
 
 impl Reminder {
     pub fn init(id: Option<u128>) -> Self {
-        let uuid = Uuid::new_v4().as_u128();
         let now = DateTime::from(Utc::now());
         Reminder {
-            id: if id.is_some() { id.unwrap() } else { uuid },
+            id: id.unwrap_or_else(|| Uuid::new_v4().as_u128()),
             title: "".to_string(),
             description: "".to_string(),
             start_time: now,
@@ -120,6 +180,11 @@ impl Reminder {
             important_level: ImportantLevel::Low.to_string(),
             category: Category::Default.to_string(),
             generator_instance: None,
+            calendar: None,
+            tags: BTreeSet::new(),
+            uid: None,
+            href: None,
+            revision: 0,
         }
     }
     pub fn self_clone(&self, is_new: bool) -> Self {
@@ -137,6 +202,11 @@ impl Reminder {
             important_level: self.important_level.clone(),
             category: self.category.clone(),
             generator_instance: self.generator_instance.clone(),
+            calendar: self.calendar,
+            tags: self.tags.clone(),
+            uid: if is_new { None } else { self.uid.clone() },
+            href: if is_new { None } else { self.href.clone() },
+            revision: if is_new { 0 } else { self.revision },
         }
     }
 }
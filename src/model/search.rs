@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::model::{EventCommonTrait, Kind};
+
+// every word of text has to match the start of a word in title, description or tags
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    pub text: String,
+    pub start: Option<DateTime<FixedOffset>>,
+    pub end: Option<DateTime<FixedOffset>>,
+    pub kinds: Vec<Kind>, // empty for all kinds
+    pub limit: Option<usize>,
+}
+
+#[derive(Clone)]
+pub struct SearchHit {
+    pub event: Arc<Box<dyn EventCommonTrait>>,
+    pub score: f64,
+}
+
+impl SearchQuery {
+    pub fn new(text: &str) -> Self {
+        SearchQuery {
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const DEFAULT_REFRESH_MINUTES: i64 = 60;
+
+// read-only calendar fed from an iCalendar url or file, e.g. public holidays. its
+// events are shown next to our own but never stored or synced with them
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Subscription {
+    id: u128,
+    pub name: String,
+    pub source: String, // http(s) or webcal url, anything else is a local path
+    #[serde(default)]
+    pub local: bool, // whether local paths and hosts of our own network may be read
+    pub refresh_minutes: i64,
+    pub count_conflicts: bool, // whether our events may not overlap the feed ones
+    pub refreshed_time: Option<i64>, // of the last attempt, failed or not
+    pub error: Option<String>,       // of the last attempt, the previous content is kept
+    pub content: String,             // as last fetched, so the feed is shown offline
+}
+
+impl Subscription {
+    pub fn new(name: &str, source: &str) -> Self {
+        Subscription {
+            id: Uuid::new_v4().as_u128(),
+            name: name.to_string(),
+            source: source.to_string(),
+            local: false,
+            refresh_minutes: DEFAULT_REFRESH_MINUTES,
+            count_conflicts: false,
+            refreshed_time: None,
+            error: None,
+            content: String::new(),
+        }
+    }
+
+    pub fn get_id(&self) -> u128 {
+        self.id
+    }
+
+    // now in milliseconds
+    pub fn is_due(&self, now: i64) -> bool {
+        self.refreshed_time
+            .is_none_or(|time| now - time >= self.refresh_minutes * 60 * 1000)
+    }
+}
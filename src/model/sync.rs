@@ -0,0 +1,65 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::exception::InternalError;
+
+// what a sync does with an event changed on both sides since the last sync
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum ConflictPolicy {
+    #[default]
+    KeepLocal,
+    KeepRemote,
+    Skip, // leave both sides as they are and report the event
+}
+
+// the remote object an event was last synced with
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SyncEntry {
+    pub href: String,
+    pub uid: String, // as the remote knows it, kept when pushing back
+    pub etag: Option<String>, // None while the remote does not hold the event
+    pub revision: u64,         // of the local event when it was last synced
+}
+
+// what is known of one remote collection, per event and as a whole
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SyncState {
+    pub url: String,
+    pub sync_token: Option<String>,
+    pub entries: BTreeMap<u128, SyncEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SyncReport {
+    pub pulled: usize,
+    pub pushed: usize,
+    pub deleted_local: usize,
+    pub deleted_remote: usize,
+    pub conflicts: Vec<u128>,
+}
+
+impl SyncState {
+    pub fn new(url: &str) -> Self {
+        SyncState {
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = InternalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep-local" | "KeepLocal" => Ok(ConflictPolicy::KeepLocal),
+            "keep-remote" | "KeepRemote" => Ok(ConflictPolicy::KeepRemote),
+            "skip" | "Skip" => Ok(ConflictPolicy::Skip),
+            _ => Err(InternalError::InvalidConflictPolicyError {
+                policy: s.to_string()
+            }),
+        }
+    }
+}
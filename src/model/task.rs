@@ -0,0 +1,220 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::model::{Category, ImportantLevel, ItemCommonTrait, Kind};
+use crate::persistent::PersistentTaskModel;
+
+// task is not a time block, it only has an optional due time,
+// so it lives beside the events instead of in the per day index
+#[derive(Clone)]
+pub struct Task {
+    id: u128,
+    title: String,
+    description: String,
+    due_time: Option<DateTime<FixedOffset>>,
+    estimate_minutes: Option<i64>,
+    status: TaskStatus,
+    completed_time: Option<DateTime<FixedOffset>>,
+    checklist: Vec<ChecklistItem>,
+    color: String,
+    important_level: String,
+    category: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskStatus {
+    Todo,
+    InProgress,
+    Done,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChecklistItem {
+    pub title: String,
+    pub done: bool,
+}
+
+impl Task {
+    pub fn init(id: Option<u128>) -> Self {
+        Task {
+            id: id.unwrap_or(Uuid::new_v4().as_u128()),
+            title: String::new(),
+            description: String::new(),
+            due_time: None,
+            estimate_minutes: None,
+            status: TaskStatus::Todo,
+            completed_time: None,
+            checklist: vec![],
+            color: String::new(),
+            important_level: ImportantLevel::Medium.to_string(),
+            category: Category::Default.to_string(),
+        }
+    }
+
+    pub fn get_due_time(&self) -> Option<DateTime<FixedOffset>> {
+        self.due_time
+    }
+
+    pub fn set_due_time(&mut self, due_time: Option<DateTime<FixedOffset>>) {
+        self.due_time = due_time;
+    }
+
+    pub fn get_estimate_minutes(&self) -> Option<i64> {
+        self.estimate_minutes
+    }
+
+    pub fn set_estimate_minutes(&mut self, estimate_minutes: Option<i64>) {
+        self.estimate_minutes = estimate_minutes;
+    }
+
+    pub fn get_status(&self) -> TaskStatus {
+        self.status
+    }
+
+    // completion time follows the status, it is only kept while the task is done
+    pub fn set_status(&mut self, status: TaskStatus) {
+        if status == TaskStatus::Done && self.status != TaskStatus::Done {
+            self.completed_time = Some(DateTime::from(Utc::now()));
+        }
+        if status != TaskStatus::Done {
+            self.completed_time = None;
+        }
+        self.status = status;
+    }
+
+    pub fn get_completed_time(&self) -> Option<DateTime<FixedOffset>> {
+        self.completed_time
+    }
+
+    pub fn get_checklist(&self) -> &Vec<ChecklistItem> {
+        &self.checklist
+    }
+
+    pub fn add_checklist_item(&mut self, title: &str) {
+        self.checklist.push(ChecklistItem {
+            title: title.to_string(),
+            done: false,
+        });
+    }
+
+    pub fn set_checklist_item_done(&mut self, index: usize, done: bool) -> bool {
+        match self.checklist.get_mut(index) {
+            Some(item) => {
+                item.done = done;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_overdue(&self, now: DateTime<FixedOffset>) -> bool {
+        self.status != TaskStatus::Done && self.due_time.is_some_and(|due| due.lt(&now))
+    }
+
+    pub fn convert_to(&self) -> PersistentTaskModel {
+        PersistentTaskModel {
+            id: self.id,
+            title: self.title.clone(),
+            description: self.description.clone(),
+            due_time: self.due_time.map(|t| t.timestamp_millis()),
+            due_time_timezone: self.due_time.map(|t| t.offset().to_string()),
+            estimate_minutes: self.estimate_minutes,
+            status: self.status.to_string(),
+            completed_time: self.completed_time.map(|t| t.timestamp_millis()),
+            completed_time_timezone: self.completed_time.map(|t| t.offset().to_string()),
+            checklist: self.checklist.clone(),
+            color: self.color.clone(),
+            important_level: self.important_level.clone(),
+            category: self.category.clone(),
+        }
+    }
+
+    // only used when loading, status setter would overwrite the completion time
+    pub(crate) fn restore_completion(
+        &mut self,
+        status: TaskStatus,
+        completed_time: Option<DateTime<FixedOffset>>,
+    ) {
+        self.status = status;
+        self.completed_time = completed_time;
+    }
+
+    pub(crate) fn restore_checklist(&mut self, checklist: Vec<ChecklistItem>) {
+        self.checklist = checklist;
+    }
+}
+
+impl ItemCommonTrait for Task {
+    fn get_id(&self) -> u128 {
+        self.id
+    }
+
+    fn get_kind(&self) -> Kind {
+        Kind::Task
+    }
+
+    fn get_title(&self) -> &str {
+        self.title.as_str()
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.title = title.to_string();
+    }
+
+    fn get_description(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn set_description(&mut self, description: &str) {
+        self.description = description.to_string();
+    }
+
+    fn get_color(&self) -> &str {
+        self.color.as_str()
+    }
+
+    fn set_color(&mut self, color: &str) {
+        self.color = color.to_string();
+    }
+
+    fn set_importance(&mut self, important_level: ImportantLevel) {
+        self.important_level = important_level.to_string();
+    }
+
+    fn get_importance(&self) -> ImportantLevel {
+        ImportantLevel::from(self.important_level.as_str())
+    }
+
+    fn get_categories(&self) -> Category {
+        Category::from(self.category.as_str())
+    }
+
+    fn set_categories(&mut self, category: Category) {
+        self.category = category.to_string();
+    }
+}
+
+impl Display for TaskStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            TaskStatus::Todo => "Todo".to_string(),
+            TaskStatus::InProgress => "InProgress".to_string(),
+            TaskStatus::Done => "Done".to_string(),
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl From<&str> for TaskStatus {
+    fn from(s: &str) -> Self {
+        match s {
+            "Todo" => TaskStatus::Todo,
+            "InProgress" => TaskStatus::InProgress,
+            "Done" => TaskStatus::Done,
+            _ => TaskStatus::Todo,
+        }
+    }
+}
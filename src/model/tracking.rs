@@ -0,0 +1,51 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::exception::InternalError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TrackingGroup {
+    Day,
+    Category,
+    Importance,
+}
+
+impl FromStr for TrackingGroup {
+    type Err = InternalError;
+
+    fn from_str(group: &str) -> Result<Self, Self::Err> {
+        match group {
+            "day" | "Day" => Ok(TrackingGroup::Day),
+            "category" | "Category" => Ok(TrackingGroup::Category),
+            "importance" | "Importance" => Ok(TrackingGroup::Importance),
+            _ => Err(InternalError::InvalidTrackingGroupError {
+                group: group.to_string(),
+            }),
+        }
+    }
+}
+
+// key is the local date, category or importance the row is grouped by
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TrackingReport {
+    pub key: String,
+    pub planned_seconds: i64,
+    pub actual_seconds: i64,
+    pub unplanned_seconds: i64, // part of actual tracked ad hoc, without a planned window
+}
+
+impl TrackingReport {
+    pub fn planned_hours(&self) -> f64 {
+        self.planned_seconds as f64 / 3600.0
+    }
+
+    pub fn actual_hours(&self) -> f64 {
+        self.actual_seconds as f64 / 3600.0
+    }
+
+    // positive when more time was spent than planned
+    pub fn difference_seconds(&self) -> i64 {
+        self.actual_seconds - self.planned_seconds
+    }
+}
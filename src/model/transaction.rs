@@ -0,0 +1,53 @@
+use serde::Serialize;
+
+use crate::model::EventCommonTrait;
+
+pub enum Operation {
+    Insert(Box<dyn EventCommonTrait>),
+    Update(Box<dyn EventCommonTrait>),
+    Delete(u128),
+}
+
+// staged writes committed together by the cache, or not at all
+#[derive(Default)]
+pub struct Transaction {
+    operations: Vec<Operation>,
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct TransactionResult {
+    pub inserted: Vec<u128>,
+    pub updated: Vec<u128>,
+    pub deleted: Vec<u128>,
+}
+
+impl Transaction {
+    pub fn new() -> Self {
+        Transaction { operations: vec![] }
+    }
+
+    // fails on commit when an event with the same id exists
+    pub fn insert(&mut self, event: Box<dyn EventCommonTrait>) -> &mut Self {
+        self.operations.push(Operation::Insert(event));
+        self
+    }
+
+    // fails on commit when the event does not exist
+    pub fn update(&mut self, event: Box<dyn EventCommonTrait>) -> &mut Self {
+        self.operations.push(Operation::Update(event));
+        self
+    }
+
+    pub fn delete(&mut self, event_id: u128) -> &mut Self {
+        self.operations.push(Operation::Delete(event_id));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    pub fn into_operations(self) -> Vec<Operation> {
+        self.operations
+    }
+}
@@ -0,0 +1,59 @@
+use anyhow::bail;
+use anyhow::Result;
+
+use crate::common::exception::InternalError;
+
+// acts for whoever did not say who they are, and owns the data of single user setups
+pub const DEFAULT_USER: &str = "default";
+
+// who a core call acts for, every user has a cache and a storage of their own. calls the
+// server makes by itself, e.g. firing reminders, are background calls and do not keep
+// the data of the user loaded
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserContext {
+    name: String,
+    background: bool,
+}
+
+impl UserContext {
+    // names become file names, so they are kept to letters, digits, - _ and .
+    pub fn new(name: &str) -> Result<Self> {
+        let valid = !name.is_empty()
+            && name.len() <= 64
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            bail!(InternalError::InvalidUserError {
+                user: name.to_string()
+            })
+        }
+        Ok(UserContext {
+            name: name.to_string(),
+            background: false,
+        })
+    }
+
+    pub fn default_user() -> Self {
+        UserContext {
+            name: DEFAULT_USER.to_string(),
+            background: false,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn background(&self) -> Self {
+        UserContext {
+            name: self.name.clone(),
+            background: true,
+        }
+    }
+
+    pub fn is_background(&self) -> bool {
+        self.background
+    }
+}
@@ -0,0 +1,29 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::query::parse_query;
+
+// named query acting as a virtual calendar, the query is kept as text so relative
+// dates like this-month move with time
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SavedView {
+    id: u128,
+    pub name: String,
+    pub query: String,
+}
+
+impl SavedView {
+    pub fn new(name: &str, query: &str) -> Result<Self> {
+        parse_query(query)?;
+        Ok(SavedView {
+            id: Uuid::new_v4().as_u128(),
+            name: name.to_string(),
+            query: query.to_string(),
+        })
+    }
+
+    pub fn get_id(&self) -> u128 {
+        self.id
+    }
+}
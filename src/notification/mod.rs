@@ -0,0 +1,157 @@
+use std::process::Command;
+
+use anyhow::{anyhow, bail};
+use anyhow::Result;
+use tracing::info;
+
+use crate::common::exception::InternalError;
+use crate::model::notification::Notification;
+
+mod test;
+
+// sinks are called from a blocking thread, so they may do blocking io
+pub trait NotificationSink: Send + Sync {
+    fn notify(&self, notification: &Notification) -> Result<()>;
+}
+
+// a sink set up from the configuration, for the alarms of its channel or for all the others
+pub struct SinkConfig {
+    pub channel: Option<String>,
+    pub sink: Box<dyn NotificationSink>,
+}
+
+pub struct LogSink;
+
+pub struct StdoutSink;
+
+pub struct WebhookSink {
+    url: String,
+}
+
+// runs a program with the notification passed as environment variables
+pub struct CommandSink {
+    program: String,
+    args: Vec<String>,
+}
+
+impl NotificationSink for Box<dyn NotificationSink> {
+    fn notify(&self, notification: &Notification) -> Result<()> {
+        self.as_ref().notify(notification)
+    }
+}
+
+impl NotificationSink for LogSink {
+    fn notify(&self, notification: &Notification) -> Result<()> {
+        info!(
+            "{} notification: {} ({})",
+            notification.kind, notification.title, notification.source_id
+        );
+        Ok(())
+    }
+}
+
+impl NotificationSink for StdoutSink {
+    fn notify(&self, notification: &Notification) -> Result<()> {
+        println!("[{}] {}", notification.kind, notification.title);
+        if !notification.description.is_empty() {
+            println!("{}", notification.description);
+        }
+        Ok(())
+    }
+}
+
+impl WebhookSink {
+    pub fn new(url: &str) -> Self {
+        WebhookSink {
+            url: url.to_string(),
+        }
+    }
+}
+
+impl NotificationSink for WebhookSink {
+    fn notify(&self, notification: &Notification) -> Result<()> {
+        let response = reqwest::blocking::Client::new()
+            .post(self.url.as_str())
+            .json(notification)
+            .send()
+            .map_err(|e| InternalError::NotificationDeliveryError {
+                reason: e.to_string(),
+            })?;
+        if !response.status().is_success() {
+            bail!(InternalError::NotificationDeliveryError {
+                reason: format!("webhook responded {}", response.status())
+            })
+        }
+        Ok(())
+    }
+}
+
+impl CommandSink {
+    pub fn new(program: &str, args: Vec<String>) -> Self {
+        CommandSink {
+            program: program.to_string(),
+            args,
+        }
+    }
+}
+
+impl NotificationSink for CommandSink {
+    fn notify(&self, notification: &Notification) -> Result<()> {
+        let status = Command::new(self.program.as_str())
+            .args(&self.args)
+            .env("BREAK_CALENDAR_SOURCE_ID", notification.source_id.to_string())
+            .env("BREAK_CALENDAR_KIND", notification.kind.as_str())
+            .env("BREAK_CALENDAR_TITLE", notification.title.as_str())
+            .env("BREAK_CALENDAR_DESCRIPTION", notification.description.as_str())
+            .env("BREAK_CALENDAR_FIRE_TIME", notification.fire_time.to_string())
+            .status()
+            .map_err(|e| InternalError::NotificationDeliveryError {
+                reason: e.to_string(),
+            })?;
+        if !status.success() {
+            bail!(InternalError::NotificationDeliveryError {
+                reason: format!("{} exited with {}", self.program, status)
+            })
+        }
+        Ok(())
+    }
+}
+
+// sinks separated by ;, each a kind with an optional @channel and the target of webhooks and
+// commands, e.g. stdout;webhook@phone:https://example.com/hook;command:notify-send -u low
+pub fn parse_sinks(sinks: &str) -> Result<Vec<SinkConfig>> {
+    sinks
+        .split(';')
+        .map(|sink| sink.trim())
+        .filter(|sink| !sink.is_empty())
+        .map(parse_sink)
+        .collect()
+}
+
+fn parse_sink(sink: &str) -> Result<SinkConfig> {
+    let invalid = || {
+        anyhow!(InternalError::InvalidNotificationSinkError {
+            sink: sink.to_string()
+        })
+    };
+    let (kind, target) = match sink.split_once(':') {
+        Some((kind, target)) => (kind, Some(target.trim()).filter(|t| !t.is_empty())),
+        None => (sink, None),
+    };
+    let (kind, channel) = match kind.split_once('@') {
+        Some((_, "")) => return Err(invalid()),
+        Some((kind, channel)) => (kind, Some(channel.to_string())),
+        None => (kind, None),
+    };
+    let sink: Box<dyn NotificationSink> = match (kind, target) {
+        ("stdout", None) => Box::new(StdoutSink),
+        ("webhook", Some(url)) => Box::new(WebhookSink::new(url)),
+        ("command", Some(command)) => {
+            let mut words = command.split_whitespace().map(|word| word.to_string());
+            let program = words.next().ok_or_else(invalid)?;
+            Box::new(CommandSink::new(program.as_str(), words.collect()))
+        }
+        _ => return Err(invalid()),
+    };
+    Ok(SinkConfig { channel, sink })
+}
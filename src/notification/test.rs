@@ -0,0 +1,76 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use crate::model::notification::Notification;
+    use crate::notification::{CommandSink, NotificationSink, parse_sinks, WebhookSink};
+
+    fn notification() -> Notification {
+        Notification {
+            source_id: 42,
+            alarm_id: None,
+            channel: None,
+            kind: "Reminder".to_string(),
+            title: "stand up".to_string(),
+            description: "".to_string(),
+            fire_time: 0,
+            user: String::new(),
+        }
+    }
+
+    #[test]
+    fn command_sink_passes_notification_in_environment() {
+        let dir = tempdir().unwrap();
+        let output = dir.path().join("output");
+        let sink = CommandSink::new(
+            "sh",
+            vec![
+                "-c".to_string(),
+                format!(
+                    "echo \"$BREAK_CALENDAR_SOURCE_ID $BREAK_CALENDAR_TITLE\" > {}",
+                    output.to_str().unwrap()
+                ),
+            ],
+        );
+        sink.notify(&notification()).unwrap();
+        assert_eq!(fs::read_to_string(output).unwrap().trim(), "42 stand up");
+    }
+
+    #[test]
+    fn command_sink_fails_on_non_zero_exit() {
+        let sink = CommandSink::new("sh", vec!["-c".to_string(), "exit 3".to_string()]);
+        assert!(sink.notify(&notification()).is_err());
+    }
+
+    #[test]
+    fn webhook_sink_fails_when_unreachable() {
+        let sink = WebhookSink::new("http://127.0.0.1:1/hook");
+        assert!(sink.notify(&notification()).is_err());
+    }
+
+    #[test]
+    fn sinks_are_parsed_with_their_channels() {
+        let dir = tempdir().unwrap();
+        let output = dir.path().join("output");
+        let sinks = parse_sinks(
+            format!(
+                "stdout; webhook@phone:http://127.0.0.1:1/hook;command:touch {}",
+                output.to_str().unwrap()
+            )
+            .as_str(),
+        )
+        .unwrap();
+        let channels = sinks.iter().map(|s| s.channel.as_deref()).collect::<Vec<Option<&str>>>();
+        assert_eq!(channels, vec![None, Some("phone"), None]);
+        assert!(sinks[1].sink.notify(&notification()).is_err());
+        sinks[2].sink.notify(&notification()).unwrap();
+        assert!(output.exists());
+
+        assert!(parse_sinks("").unwrap().is_empty());
+        for sink in ["pager", "webhook", "command: ", "stdout:x", "stdout@"] {
+            assert!(parse_sinks(sink).is_err(), "{}", sink);
+        }
+    }
+}
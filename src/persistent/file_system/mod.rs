@@ -1,9 +1,9 @@
+use std::fs;
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::Path;
 use std::rc::Rc;
 
-use anyhow::bail;
 use anyhow::Result;
 use flate2::Compression;
 use flate2::read::GzDecoder;
@@ -13,73 +13,133 @@ use tracing::error;
 use crate::cache::Cache;
 use crate::common::exception::InternalError::DataPersistenceError;
 use crate::model::EventCommonTrait;
-use crate::persistent::{Persistent, PersistentModel};
+use crate::persistent::{Persistent, PersistentData, PersistentModel};
 
 mod test;
 
 pub const DEFAULT_FILE_NAME: &str = "metadata";
+// every user but the default one keeps a directory of their own in it
+pub const USERS_DIRECTORY: &str = "users";
 
 pub struct FilePersistenceSystem;
 
 impl FilePersistenceSystem {
     pub async fn save(cache: &Cache, file_path: Option<String>) -> Result<()> {
         let file_name = file_path.unwrap_or(DEFAULT_FILE_NAME.to_string());
-        let file = check_if_file_exists(file_name)?;
         let event_cache = cache
             .get_all_raw_events()
             .iter()
             .map(|e| {
-                e.convert_to(
-                    e.get_generator_instance()
-                        .map_or(None, |id| cache.get_instances(id)),
-                )
+                let instance = e.get_generator_instance().and_then(|id| cache.get_instances(id));
+                e.convert_to(instance)
             })
             .collect::<Vec<PersistentModel>>();
-        let cache = serde_json::to_vec(&event_cache).unwrap();
+        let data = PersistentData {
+            events: event_cache,
+            objectives: cache.get_all_objectives(),
+            key_results: cache.get_all_key_results(),
+            tasks: cache.get_all_tasks().iter().map(|t| t.convert_to()).collect(),
+            notifications: cache.get_all_notification_records(),
+            categories: cache.get_all_categories(),
+            views: cache.get_all_views(),
+            history: cache.get_history(),
+            audit: cache.get_audit_log(),
+            revision: cache.get_revision(),
+            changes: cache.get_change_log(),
+            sync: cache.get_sync_states(),
+            subscriptions: cache.get_all_subscriptions(),
+            calendars: cache.get_all_calendars(),
+        };
+        let cache = serde_json::to_vec(&data).map_err(|_| DataPersistenceError)?;
 
+        if let Some(directory) = Path::new(file_name.as_str()).parent() {
+            fs::create_dir_all(directory).map_err(|error| {
+                error!("Directory create error: {}", error);
+                DataPersistenceError
+            })?;
+        }
+        // written aside and renamed, so a failed save leaves the previous file intact
+        let temporary_name = format!("{}.tmp", file_name);
+        let file = File::create(temporary_name.as_str()).map_err(|error| {
+            error!("File create error: {}", error);
+            DataPersistenceError
+        })?;
         let mut encoder = GzEncoder::new(file, Compression::default());
-        encoder
-            .write_all(&*cache)
-            .map_err(|_| DataPersistenceError)?;
+        encoder.write_all(&cache).map_err(|_| DataPersistenceError)?;
         encoder.finish().map_err(|_| DataPersistenceError)?;
+        fs::rename(temporary_name.as_str(), file_name.as_str()).map_err(|error| {
+            error!("File rename error: {}", error);
+            let _ = fs::remove_file(temporary_name.as_str());
+            DataPersistenceError
+        })?;
         Ok(())
     }
 
     pub async fn load(file_path: Option<String>) -> Result<Cache> {
         let file_name = file_path.unwrap_or(DEFAULT_FILE_NAME.to_string());
-        let file = check_if_file_exists(file_name)?;
+        let file = File::open(file_name.as_str()).map_err(|error| {
+            error!("File read error: {}", error);
+            DataPersistenceError
+        })?;
         let mut decoder = GzDecoder::new(file);
         let mut cache = Vec::new();
         decoder
             .read_to_end(&mut cache)
             .map_err(|_| DataPersistenceError)?;
-        let cache: Vec<PersistentModel> =
-            serde_json::from_slice(&*cache).map_err(|_| DataPersistenceError)?;
-        let instance_vec = cache
+        let data = parse_data(&cache)?;
+        let instance_vec = data
+            .events
             .iter()
             .filter_map(|e| e.generator_instance.clone())
             .collect();
-        let event_cache: Vec<Box<dyn EventCommonTrait>> =
-            cache.iter().map(|e| e.convert_to()).collect();
+        let (task_models, event_models): (Vec<_>, Vec<_>) =
+            data.events.iter().partition(|e| e.is_task());
+        let event_cache = event_models
+            .iter()
+            .map(|e| e.convert_to())
+            .collect::<Result<Vec<Box<dyn EventCommonTrait>>>>()?;
         let mut cache = Cache::init();
-        cache.insert_events(event_cache).unwrap();
+        cache.add_or_update_categories(data.categories);
+        cache.add_or_update_calendars(data.calendars)?;
+        // stored events are kept even when a conflict rule was tightened after they were
+        cache.restore_events(event_cache)?;
         cache.add_or_update_instances(instance_vec);
+        cache.add_or_update_objectives(data.objectives);
+        cache.add_or_update_key_results(data.key_results)?;
+        cache.add_or_update_tasks(
+            data.tasks
+                .iter()
+                .map(|t| t.convert_to())
+                .collect::<Result<Vec<_>>>()?,
+        );
+        cache.add_or_update_tasks(
+            task_models
+                .iter()
+                .map(|t| t.convert_to_task())
+                .collect::<Result<Vec<_>>>()?,
+        );
+        cache.add_or_update_notification_records(data.notifications);
+        cache.add_or_update_views(data.views)?;
+        // loading itself is neither an undoable nor an audited change
+        cache.restore_history(data.history);
+        cache.restore_audit_log(data.audit);
+        cache.set_revision(data.revision);
+        cache.restore_change_log(data.changes);
+        cache.restore_sync_states(data.sync);
+        cache.restore_subscriptions(data.subscriptions);
         Ok(cache)
     }
 }
 
-fn check_if_file_exists(file_name: String) -> Result<File> {
-    if !Path::new(file_name.as_str()).exists() {
-        let file = File::create(file_name.as_str());
-        if file.is_ok() {
-            return Ok(file.unwrap());
-        }
-    } else {
-        let file = File::open(file_name.as_str());
-        if file.is_ok() {
-            return Ok(file.unwrap());
-        }
+// files written before PersistentData only contain the event list
+fn parse_data(content: &[u8]) -> Result<PersistentData> {
+    if let Ok(data) = serde_json::from_slice::<PersistentData>(content) {
+        return Ok(data);
     }
-    error!("File read or create error");
-    bail!(DataPersistenceError)
+    let events: Vec<PersistentModel> =
+        serde_json::from_slice(content).map_err(|_| DataPersistenceError)?;
+    Ok(PersistentData {
+        events,
+        ..Default::default()
+    })
 }
@@ -2,13 +2,23 @@
 mod tests {
     use std::fs;
     use std::io::Write;
+    use std::path::Path;
 
-    use chrono::{DateTime, Utc};
+    use chrono::{DateTime, Duration, Utc};
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
     use tempfile::tempdir;
 
     use crate::cache::Cache;
     use crate::model::event::Event;
-    use crate::model::EventCommonTrait;
+    use crate::model::{Category, EventCommonTrait, ImportantLevel, ItemCommonTrait, Kind};
+    use crate::model::calendar::Calendar;
+    use crate::model::category::{ConflictBehavior, UserCategory};
+    use crate::model::objective::{KeyResult, Objective, Quarter};
+    use crate::model::subscription::Subscription;
+    use crate::model::sync::{SyncEntry, SyncState};
+    use crate::model::task::{Task, TaskStatus};
+    use crate::model::view::SavedView;
     use crate::persistent::file_system::{DEFAULT_FILE_NAME, FilePersistenceSystem};
 
     #[tokio::test]
@@ -41,19 +51,359 @@ mod tests {
 
     #[tokio::test]
     async
-    fn save_fails_when_cannot_write_to_file() {
+    fn save_load_objectives_and_linked_events() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join(DEFAULT_FILE_NAME);
+        let file_path = Some(file_path.to_str().unwrap().to_string());
+        let mut cache = Cache::init();
+        let objective = Objective::new("Ship v1", "team", Quarter::new(2026, 3).unwrap());
+        let objective_id = objective.get_id();
+        cache.add_or_update_objectives(vec![objective]);
+        let mut key_result = KeyResult::new(objective_id, "Close bugs", 0.0, 10.0);
+        key_result.current_value = 4.0;
+        let key_result_id = key_result.get_id();
+        cache.add_or_update_key_results(vec![key_result]).unwrap();
+        let mut event = Event::init(None);
+        let id = event.get_id();
+        event.set_duration(DateTime::from(Utc::now()), DateTime::from(Utc::now()));
+        event.set_key_result(Some(key_result_id));
+        cache.insert_events(vec![Box::new(event)]).unwrap();
+        FilePersistenceSystem::save(&cache, file_path.clone()).await.unwrap();
+
+        let loaded_cache = FilePersistenceSystem::load(file_path).await.unwrap();
+
+        assert_eq!(loaded_cache.get_objective(objective_id).unwrap().title, "Ship v1");
+        assert_eq!(loaded_cache.get_key_result(key_result_id).unwrap().current_value, 4.0);
+        assert_eq!(
+            loaded_cache.get_events_by_id::<Event>(id).unwrap().get_key_result(),
+            Some(key_result_id)
+        );
+    }
+
+    #[tokio::test]
+    async
+    fn save_load_tasks() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join(DEFAULT_FILE_NAME);
+        let file_path = Some(file_path.to_str().unwrap().to_string());
+        let mut cache = Cache::init();
+        let mut task = Task::init(None);
+        let id = task.get_id();
+        let due_time = DateTime::from(Utc::now());
+        task.set_title("review pr");
+        task.set_due_time(Some(due_time));
+        task.set_estimate_minutes(Some(30));
+        task.add_checklist_item("read diff");
+        task.set_checklist_item_done(0, true);
+        task.set_status(TaskStatus::Done);
+        let completed_time = task.get_completed_time();
+        cache.add_or_update_tasks(vec![task]);
+        FilePersistenceSystem::save(&cache, file_path.clone()).await.unwrap();
+
+        let loaded_cache = FilePersistenceSystem::load(file_path).await.unwrap();
+
+        let task = loaded_cache.get_task(id).unwrap();
+        assert_eq!(task.get_title(), "review pr");
+        assert_eq!(task.get_due_time().unwrap().timestamp_millis(), due_time.timestamp_millis());
+        assert_eq!(task.get_estimate_minutes(), Some(30));
+        assert_eq!(task.get_status(), TaskStatus::Done);
+        assert_eq!(
+            task.get_completed_time().map(|t| t.timestamp_millis()),
+            completed_time.map(|t| t.timestamp_millis())
+        );
+        assert!(task.get_checklist()[0].done);
+    }
+
+    #[tokio::test]
+    async
+    fn load_tasks_written_as_events() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join(DEFAULT_FILE_NAME);
+        let mut event = Event::init(None);
+        let id = event.get_id();
+        let now = DateTime::from(Utc::now());
+        event.set_title("review pr");
+        event.set_duration(now, now + Duration::hours(1));
+        let mut model = event.convert_to(None);
+        model.kind = Kind::Task.to_string();
+        let file = fs::File::create(&file_path).unwrap();
+        let mut encoder = GzEncoder::new(file, Compression::default());
+        encoder.write_all(&serde_json::to_vec(&vec![model]).unwrap()).unwrap();
+        encoder.finish().unwrap();
+        let file_path = Some(file_path.to_str().unwrap().to_string());
+
+        let loaded_cache = FilePersistenceSystem::load(file_path).await.unwrap();
+
+        let task = loaded_cache.get_task(id).unwrap();
+        assert_eq!(task.get_title(), "review pr");
+        assert_eq!(
+            task.get_due_time().unwrap().timestamp_millis(),
+            (now + Duration::hours(1)).timestamp_millis()
+        );
+        assert!(loaded_cache.get_events_by_id::<Event>(id).is_err());
+    }
+
+    #[tokio::test]
+    async
+    fn save_load_tracked_times() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join(DEFAULT_FILE_NAME);
+        let file_path = Some(file_path.to_str().unwrap().to_string());
+        let mut cache = Cache::init();
+        let now = DateTime::from(Utc::now());
+        let tracked_id = cache.start_adhoc_tracking("Hotfix", Category::Other, now).unwrap();
+        cache.stop_tracking(tracked_id, now + Duration::minutes(20)).unwrap();
+        let running_id = cache.start_adhoc_tracking("Review", Category::Other, now).unwrap();
+        FilePersistenceSystem::save(&cache, file_path.clone()).await.unwrap();
+
+        let loaded_cache = FilePersistenceSystem::load(file_path).await.unwrap();
+
+        let tracked = loaded_cache.get_events_by_id::<Event>(tracked_id).unwrap();
+        assert_eq!(
+            tracked.get_actual_end_time().unwrap().timestamp_millis(),
+            (now + Duration::minutes(20)).timestamp_millis()
+        );
+        assert!(tracked.is_unplanned());
+        assert_eq!(loaded_cache.get_running_tracking(), vec![running_id]);
+    }
+
+    #[tokio::test]
+    async
+    fn save_load_categories_and_tags() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join(DEFAULT_FILE_NAME);
+        let file_path = Some(file_path.to_str().unwrap().to_string());
+        let mut cache = Cache::init();
+        let mut focus = UserCategory::new("Focus", "#0044ff", ImportantLevel::High);
+        focus.conflict = ConflictBehavior::Allow;
+        let focus_id = focus.get_id();
+        cache.add_or_update_categories(vec![focus.clone()]);
+        let now = DateTime::from(Utc::now());
+        let mut meeting = Event::init(None);
+        meeting.set_duration(now, now + Duration::hours(1));
+        meeting.set_categories(Category::from("Work"));
+        let meeting_id = meeting.get_id();
+        let mut deep_work = Event::init(None);
+        deep_work.set_duration(now, now + Duration::hours(2));
+        focus.apply_defaults(&mut deep_work);
+        cache.insert_events(vec![Box::new(deep_work), Box::new(meeting)]).unwrap();
+        cache.set_event_tags(meeting_id, vec!["#ClientA"]).unwrap();
+        let view = SavedView::new("Client A", "tag:clientA").unwrap();
+        cache.add_or_update_views(vec![view.clone()]).unwrap();
+        FilePersistenceSystem::save(&cache, file_path.clone()).await.unwrap();
+
+        // overlapping events load again, whatever the category allows
+        let loaded_cache = FilePersistenceSystem::load(file_path).await.unwrap();
+
+        assert_eq!(loaded_cache.get_category(focus_id).unwrap().conflict, ConflictBehavior::Allow);
+        assert_eq!(loaded_cache.get_all_events::<Event>().len(), 2);
+        let meeting = loaded_cache.get_events_by_id::<Event>(meeting_id).unwrap();
+        assert_eq!(meeting.get_categories().to_string(), "Work");
+        assert!(meeting.get_tags().contains("clienta"));
+        assert_eq!(loaded_cache.get_events_by_tags(&["clientA"], None, None).len(), 1);
+        assert_eq!(loaded_cache.get_view(view.get_id()).unwrap().query, "tag:clientA");
+    }
+
+    #[tokio::test]
+    async
+    fn save_load_history() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join(DEFAULT_FILE_NAME);
+        let file_path = Some(file_path.to_str().unwrap().to_string());
+        let mut cache = Cache::init();
+        let now = DateTime::from(Utc::now());
+        let mut event = Event::init(None);
+        event.set_duration(now, now + Duration::hours(1));
+        let id = event.get_id();
+        cache.insert_events(vec![Box::new(event)]).unwrap();
+        cache.commit_history_step();
+        cache.delete_event(id).unwrap();
+        FilePersistenceSystem::save(&cache, file_path.clone()).await.unwrap();
+
+        // a later process can still undo the delete
+        let mut loaded_cache = FilePersistenceSystem::load(file_path).await.unwrap();
+
+        assert_eq!(loaded_cache.get_undo_steps().len(), 2);
+        assert_eq!(loaded_cache.get_event_audit(id).len(), 2);
+        assert_eq!(loaded_cache.get_revision(), cache.get_revision());
+        loaded_cache.undo().unwrap();
+        assert_eq!(loaded_cache.get_events_by_id::<Event>(id).unwrap().get_revision(), 2);
+        loaded_cache.undo().unwrap();
+        assert!(loaded_cache.get_all_events::<Event>().is_empty());
+    }
+
+    #[tokio::test]
+    async
+    fn save_load_sync_states() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join(DEFAULT_FILE_NAME);
+        let file_path = Some(file_path.to_str().unwrap().to_string());
+        let mut cache = Cache::init();
+        let mut state = SyncState::new("https://dav.example.com/cal/");
+        state.sync_token = Some("7".to_string());
+        state.entries.insert(1, SyncEntry {
+            href: "/cal/a.ics".to_string(),
+            uid: "a@example.com".to_string(),
+            etag: Some("\"3\"".to_string()),
+            revision: 2,
+        });
+        cache.set_sync_state(state);
+        FilePersistenceSystem::save(&cache, file_path.clone()).await.unwrap();
+
+        let loaded_cache = FilePersistenceSystem::load(file_path).await.unwrap();
+
+        let state = loaded_cache.get_sync_state("https://dav.example.com/cal/");
+        assert_eq!(state.sync_token.as_deref(), Some("7"));
+        assert_eq!(state.entries[&1].etag.as_deref(), Some("\"3\""));
+    }
+
+    #[tokio::test]
+    async
+    fn save_load_subscriptions_with_their_feed() {
         let dir = tempdir().unwrap();
         let file_path = dir.path().join(DEFAULT_FILE_NAME);
-        fs::write(&file_path, "unwritable").unwrap();
-        let mut permissions = fs::metadata(&file_path).unwrap().permissions();
-        permissions.set_readonly(true);
-        fs::set_permissions(&file_path, permissions).unwrap();
         let file_path = Some(file_path.to_str().unwrap().to_string());
+        let mut cache = Cache::init();
+        let mut subscription = Subscription::new("team", "/srv/feeds/team.ics");
+        subscription.content = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:offsite\r\n\
+            DTSTART:20260601T090000Z\r\nDTEND:20260601T170000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n"
+            .to_string();
+        cache.add_subscription(subscription.clone()).unwrap();
+        FilePersistenceSystem::save(&cache, file_path.clone()).await.unwrap();
+
+        let loaded_cache = FilePersistenceSystem::load(file_path).await.unwrap();
+
+        assert_eq!(loaded_cache.get_subscription(subscription.get_id()).unwrap().name, "team");
+        assert_eq!(loaded_cache.get_feed_events().len(), 1);
+        assert!(loaded_cache.get_all_raw_events().is_empty());
+    }
+
+    #[tokio::test]
+    async
+    fn save_load_calendars_before_their_events() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join(DEFAULT_FILE_NAME);
+        let file_path = Some(file_path.to_str().unwrap().to_string());
+        let mut cache = Cache::init();
+        let mut team = Calendar::new("Team", "#00ff00");
+        team.conflict = ConflictBehavior::Allow;
+        cache.add_or_update_calendars(vec![team.clone()]).unwrap();
+        let start_time = DateTime::from(Utc::now());
+        let mut first = Event::init(None);
+        first.set_duration(start_time, start_time + Duration::hours(1));
+        first.set_calendar(Some(team.get_id()));
+        let mut second = Event::init(None);
+        second.set_duration(start_time, start_time + Duration::hours(1));
+        let second_id = second.get_id();
+        cache.insert_events(vec![Box::new(first), Box::new(second)]).unwrap();
+        FilePersistenceSystem::save(&cache, file_path.clone()).await.unwrap();
+
+        // the overlap is only allowed once the calendar is known
+        let loaded_cache = FilePersistenceSystem::load(file_path).await.unwrap();
+
+        assert_eq!(loaded_cache.get_all_calendars()[0].conflict, ConflictBehavior::Allow);
+        assert_eq!(loaded_cache.get_all_raw_events().len(), 2);
+        let second = loaded_cache.get_events_by_id::<Event>(second_id).unwrap();
+        assert_eq!(second.get_calendar(), None);
+    }
+
+    #[tokio::test]
+    async
+    fn load_keeps_events_of_rules_tightened_after_saving() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join(DEFAULT_FILE_NAME);
+        let file_path = Some(file_path.to_str().unwrap().to_string());
+        let mut cache = Cache::init();
+        let mut team = Calendar::new("Team", "#00ff00");
+        team.conflict = ConflictBehavior::Allow;
+        cache.add_or_update_calendars(vec![team.clone()]).unwrap();
+        let start_time = DateTime::from(Utc::now());
+        let mut first = Event::init(None);
+        first.set_duration(start_time, start_time + Duration::hours(1));
+        first.set_calendar(Some(team.get_id()));
+        let mut second = Event::init(None);
+        second.set_duration(start_time, start_time + Duration::hours(1));
+        second.set_calendar(Some(team.get_id()));
+        cache.insert_events(vec![Box::new(first), Box::new(second)]).unwrap();
+        team.conflict = ConflictBehavior::Reject;
+        cache.add_or_update_calendars(vec![team.clone()]).unwrap();
+        FilePersistenceSystem::save(&cache, file_path.clone()).await.unwrap();
+
+        let mut loaded_cache = FilePersistenceSystem::load(file_path.clone()).await.unwrap();
+        assert_eq!(loaded_cache.get_all_raw_events().len(), 2);
+
+        // the events fall back to the rules of the main calendar
+        loaded_cache.delete_calendar(team.get_id()).unwrap();
+        FilePersistenceSystem::save(&loaded_cache, file_path.clone()).await.unwrap();
+        let loaded_cache = FilePersistenceSystem::load(file_path).await.unwrap();
+        assert_eq!(loaded_cache.get_all_raw_events().len(), 2);
+    }
+
+    #[tokio::test]
+    async
+    fn load_keeps_events_of_categories_tightened_or_deleted_after_saving() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join(DEFAULT_FILE_NAME);
+        let file_path = Some(file_path.to_str().unwrap().to_string());
+        let mut cache = Cache::init();
+        let mut focus = UserCategory::new("Focus", "#0044ff", ImportantLevel::High);
+        focus.conflict = ConflictBehavior::Allow;
+        cache.add_or_update_categories(vec![focus.clone()]);
+        let now = DateTime::from(Utc::now());
+        let mut first = Event::init(None);
+        first.set_duration(now, now + Duration::hours(1));
+        focus.apply_defaults(&mut first);
+        let mut second = Event::init(None);
+        second.set_duration(now, now + Duration::hours(1));
+        focus.apply_defaults(&mut second);
+        cache.insert_events(vec![Box::new(first), Box::new(second)]).unwrap();
+        focus.conflict = ConflictBehavior::Reject;
+        cache.add_or_update_categories(vec![focus.clone()]);
+        FilePersistenceSystem::save(&cache, file_path.clone()).await.unwrap();
+
+        let mut loaded_cache = FilePersistenceSystem::load(file_path.clone()).await.unwrap();
+        assert_eq!(loaded_cache.get_all_events::<Event>().len(), 2);
+
+        loaded_cache.delete_category(focus.get_id()).unwrap();
+        FilePersistenceSystem::save(&loaded_cache, file_path.clone()).await.unwrap();
+        let loaded_cache = FilePersistenceSystem::load(file_path).await.unwrap();
+        assert_eq!(loaded_cache.get_all_events::<Event>().len(), 2);
+    }
+
+    #[tokio::test]
+    async
+    fn save_fails_when_cannot_write_to_file() {
+        let dir = tempdir().unwrap();
+        // a directory in the way cannot be replaced, whatever the permissions
+        let file_path = dir.path().join(DEFAULT_FILE_NAME);
+        fs::create_dir(&file_path).unwrap();
+        fs::write(file_path.join("content"), "unwritable").unwrap();
+        let file_path = file_path.to_str().unwrap().to_string();
 
         let cache = Cache::init();
-        let result = FilePersistenceSystem::save(&cache, file_path).await;
+        let result = FilePersistenceSystem::save(&cache, Some(file_path.clone())).await;
 
         assert!(result.is_err());
+        assert!(!Path::new(format!("{}.tmp", file_path).as_str()).exists());
+    }
+
+    #[tokio::test]
+    async
+    fn save_replaces_existing_file() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join(DEFAULT_FILE_NAME);
+        let file_path = Some(file_path.to_str().unwrap().to_string());
+        let mut cache = Cache::init();
+        FilePersistenceSystem::save(&cache, file_path.clone()).await.unwrap();
+        let mut event = Event::init(None);
+        let now = DateTime::from(Utc::now());
+        event.set_duration(now, now + Duration::hours(1));
+        cache.insert_events(vec![Box::new(event)]).unwrap();
+
+        FilePersistenceSystem::save(&cache, file_path.clone()).await.unwrap();
+
+        let loaded_cache = FilePersistenceSystem::load(file_path).await.unwrap();
+        assert_eq!(loaded_cache.get_all_events::<Event>().len(), 1);
     }
 
     #[tokio::test]
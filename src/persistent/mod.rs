@@ -1,28 +1,112 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use crate::cache::Cache;
+use crate::common::exception::InternalError;
 use crate::common::utils::convert_from_string_to_datetime;
-use crate::model::{Category, EventCommonTrait, ImportantLevel, Kind};
+use crate::model::{Category, EventCommonTrait, ImportantLevel, ItemCommonTrait, Kind};
+use crate::model::alarm::Alarm;
+use crate::model::audit::AuditEntry;
+use crate::model::break_block::Break;
+use crate::model::calendar::Calendar;
+use crate::model::category::UserCategory;
+use crate::model::change_feed::ChangeEvent;
 use crate::model::event::Event;
 use crate::model::generator_instance::GeneratorInstance;
+use crate::model::history::History;
+use crate::model::notification::NotificationRecord;
+use crate::model::objective::{KeyResult, Objective};
 use crate::model::reminder::Reminder;
-use crate::persistent::file_system::FilePersistenceSystem;
+use crate::model::subscription::Subscription;
+use crate::model::sync::SyncState;
+use crate::model::task::{ChecklistItem, Task, TaskStatus};
+use crate::model::view::SavedView;
+use crate::model::user::{DEFAULT_USER, UserContext};
+use crate::persistent::file_system::{DEFAULT_FILE_NAME, FilePersistenceSystem, USERS_DIRECTORY};
 
 mod file_system;
 
-pub struct Persistent;
+pub struct Persistent {
+    file_path: String,
+}
 
 impl Persistent {
     pub fn init() -> Self {
-        Persistent
+        Persistent {
+            file_path: path_of(storage_root().join(DEFAULT_FILE_NAME)),
+        }
+    }
+    // the default user keeps the file single user setups always had
+    pub fn for_user(user: &UserContext) -> Self {
+        if user.get_name() == DEFAULT_USER {
+            return Persistent::init();
+        }
+        let directory = storage_root().join(USERS_DIRECTORY).join(user.get_name());
+        Persistent {
+            file_path: path_of(directory.join(DEFAULT_FILE_NAME)),
+        }
+    }
+    // everyone with saved data, whether loaded or not
+    pub fn stored_users() -> Vec<UserContext> {
+        let root = storage_root();
+        let mut users = std::fs::read_dir(root.join(USERS_DIRECTORY))
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|entry| entry.path().join(DEFAULT_FILE_NAME).is_file())
+                    .filter_map(|entry| entry.file_name().into_string().ok())
+                    .filter_map(|name| UserContext::new(name.as_str()).ok())
+                    .collect::<Vec<UserContext>>()
+            })
+            .unwrap_or_default();
+        if root.join(DEFAULT_FILE_NAME).is_file() {
+            users.push(UserContext::default_user());
+        }
+        users
+    }
+    #[cfg(test)]
+    pub fn get_file_path(&self) -> &str {
+        self.file_path.as_str()
     }
     pub async fn save(&self, cache: &Cache) -> Result<()> {
-        FilePersistenceSystem::save(cache, None).await
+        FilePersistenceSystem::save(cache, Some(self.file_path.clone())).await
     }
+    // a user without saved data starts empty, any other failure is returned
     pub async fn load(&self) -> Result<Cache> {
-        FilePersistenceSystem::load(None).await
+        let exists = Path::new(self.file_path.as_str())
+            .try_exists()
+            .map_err(|_| InternalError::DataPersistenceError)?;
+        if !exists {
+            return Ok(Cache::init());
+        }
+        FilePersistenceSystem::load(Some(self.file_path.clone())).await
+    }
+}
+
+// the data of every user is kept under the working directory
+#[cfg(not(test))]
+fn storage_root() -> PathBuf {
+    PathBuf::new()
+}
+
+// tests keep theirs in a directory of their own, away from the data of a real setup
+#[cfg(test)]
+fn storage_root() -> PathBuf {
+    lazy_static::lazy_static! {
+        static ref TEST_ROOT: tempfile::TempDir = tempfile::Builder::new()
+            .prefix("break-calendar-test")
+            .tempdir()
+            .unwrap();
     }
+    TEST_ROOT.path().to_path_buf()
+}
+
+fn path_of(path: PathBuf) -> String {
+    path.to_string_lossy().into_owned()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,10 +123,88 @@ pub struct PersistentModel {
     pub important_level: String,
     pub category: String,
     pub generator_instance: Option<GeneratorInstance>,
+    #[serde(default)]
+    pub key_result: Option<u128>,
+    #[serde(default)]
+    pub task: Option<u128>,
+    #[serde(default)]
+    pub alarms: Vec<Alarm>,
+    #[serde(default)]
+    pub actual_start_time: Option<i64>,
+    #[serde(default)]
+    pub actual_start_time_timezone: Option<String>,
+    #[serde(default)]
+    pub actual_end_time: Option<i64>,
+    #[serde(default)]
+    pub actual_end_time_timezone: Option<String>,
+    #[serde(default)]
+    pub unplanned: bool,
+    #[serde(default)]
+    pub calendar: Option<u128>,
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    #[serde(default)]
+    pub uid: Option<String>,
+    #[serde(default)]
+    pub href: Option<String>,
+    #[serde(default)]
+    pub revision: u64,
+}
+
+// whole content of the calendar file, events plus every non-event record
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PersistentData {
+    pub events: Vec<PersistentModel>,
+    #[serde(default)]
+    pub objectives: Vec<Objective>,
+    #[serde(default)]
+    pub key_results: Vec<KeyResult>,
+    #[serde(default)]
+    pub tasks: Vec<PersistentTaskModel>,
+    #[serde(default)]
+    pub notifications: Vec<NotificationRecord>,
+    #[serde(default)]
+    pub categories: Vec<UserCategory>,
+    #[serde(default)]
+    pub views: Vec<SavedView>,
+    #[serde(default)]
+    pub history: History,
+    #[serde(default)]
+    pub audit: Vec<AuditEntry>,
+    #[serde(default)]
+    pub revision: u64,
+    #[serde(default)]
+    pub changes: Vec<ChangeEvent>,
+    #[serde(default)]
+    pub sync: Vec<SyncState>,
+    #[serde(default)]
+    pub subscriptions: Vec<Subscription>,
+    #[serde(default)]
+    pub calendars: Vec<Calendar>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistentTaskModel {
+    pub id: u128,
+    pub title: String,
+    pub description: String,
+    pub due_time: Option<i64>,
+    pub due_time_timezone: Option<String>,
+    pub estimate_minutes: Option<i64>,
+    pub status: String,
+    pub completed_time: Option<i64>,
+    pub completed_time_timezone: Option<String>,
+    pub checklist: Vec<ChecklistItem>,
+    pub color: String,
+    pub important_level: String,
+    pub category: String,
 }
 
 impl PersistentModel {
-    pub fn convert_to(&self) -> Box<dyn EventCommonTrait> {
+    pub fn convert_to(&self) -> Result<Box<dyn EventCommonTrait>> {
+        for alarm in &self.alarms {
+            alarm.check_valid()?;
+        }
         match Kind::from(self.kind.clone().as_str()) {
             Kind::Event => {
                 let mut event = Event::init(Some(self.id));
@@ -52,8 +214,8 @@ impl PersistentModel {
                     convert_from_string_to_datetime(
                         self.start_time,
                         self.start_time_timezone.clone(),
-                    ),
-                    convert_from_string_to_datetime(self.end_time, self.end_time_timezone.clone()),
+                    )?,
+                    convert_from_string_to_datetime(self.end_time, self.end_time_timezone.clone())?,
                 );
                 event.set_color(self.color.as_str());
                 event.set_importance(ImportantLevel::from(self.important_level.clone().as_str()));
@@ -61,7 +223,26 @@ impl PersistentModel {
                 if self.generator_instance.is_some() {
                     event.set_generator_instance(self.generator_instance.clone().unwrap().get_id());
                 }
-                Box::new(event)
+                event.set_key_result(self.key_result);
+                event.set_task(self.task);
+                event.set_alarms(self.alarms.clone());
+                event.set_calendar(self.calendar);
+                event.set_tags(self.tags.clone());
+                event.set_uid(self.uid.clone());
+                event.set_href(self.href.clone());
+                event.set_revision(self.revision);
+                event.set_actual_duration(
+                    self.actual_start_time
+                        .zip(self.actual_start_time_timezone.clone())
+                        .map(|(time, timezone)| convert_from_string_to_datetime(time, timezone))
+                        .transpose()?,
+                    self.actual_end_time
+                        .zip(self.actual_end_time_timezone.clone())
+                        .map(|(time, timezone)| convert_from_string_to_datetime(time, timezone))
+                        .transpose()?,
+                );
+                event.set_unplanned(self.unplanned);
+                Ok(Box::new(event))
             }
             Kind::Reminder => {
                 let mut reminder = Reminder::init(Some(self.id));
@@ -71,19 +252,100 @@ impl PersistentModel {
                     convert_from_string_to_datetime(
                         self.start_time,
                         self.start_time_timezone.clone(),
-                    ),
-                    convert_from_string_to_datetime(self.end_time, self.end_time_timezone.clone()),
+                    )?,
+                    convert_from_string_to_datetime(self.end_time, self.end_time_timezone.clone())?,
                 );
                 reminder.set_color(self.color.as_str());
                 reminder
                     .set_importance(ImportantLevel::from(self.important_level.clone().as_str()));
                 reminder.set_categories(Category::from(self.category.clone().as_str()));
+                reminder.set_calendar(self.calendar);
+                reminder.set_tags(self.tags.clone());
+                reminder.set_uid(self.uid.clone());
+                reminder.set_href(self.href.clone());
+                reminder.set_revision(self.revision);
                 if self.generator_instance.is_some() {
                     reminder
                         .set_generator_instance(self.generator_instance.clone().unwrap().get_id());
                 }
-                Box::new(reminder)
+                Ok(Box::new(reminder))
             }
+            Kind::Break => {
+                let mut rest = Break::init(Some(self.id));
+                rest.set_title(self.title.as_str());
+                rest.set_description(self.description.as_str());
+                rest.set_duration(
+                    convert_from_string_to_datetime(
+                        self.start_time,
+                        self.start_time_timezone.clone(),
+                    )?,
+                    convert_from_string_to_datetime(self.end_time, self.end_time_timezone.clone())?,
+                );
+                rest.set_color(self.color.as_str());
+                rest.set_importance(ImportantLevel::from(self.important_level.as_str()));
+                rest.set_categories(Category::from(self.category.as_str()));
+                rest.set_calendar(self.calendar);
+                rest.set_tags(self.tags.clone());
+                rest.set_uid(self.uid.clone());
+                rest.set_href(self.href.clone());
+                rest.set_revision(self.revision);
+                Ok(Box::new(rest))
+            }
+            // tasks are no time block, they are read with convert_to_task instead
+            Kind::Task => bail!(InternalError::InvalidKindError {
+                kind: self.kind.clone()
+            }),
+        }
+    }
+
+    pub fn is_task(&self) -> bool {
+        Kind::from(self.kind.as_str()) == Kind::Task
+    }
+
+    // a task written in the event form keeps its end as due time
+    pub fn convert_to_task(&self) -> Result<Task> {
+        if !self.is_task() {
+            bail!(InternalError::InvalidKindError {
+                kind: self.kind.clone()
+            })
         }
+        let mut task = Task::init(Some(self.id));
+        task.set_title(self.title.as_str());
+        task.set_description(self.description.as_str());
+        task.set_due_time(Some(convert_from_string_to_datetime(
+            self.end_time,
+            self.end_time_timezone.clone(),
+        )?));
+        task.set_color(self.color.as_str());
+        task.set_importance(ImportantLevel::from(self.important_level.as_str()));
+        task.set_categories(Category::from(self.category.as_str()));
+        Ok(task)
+    }
+}
+
+impl PersistentTaskModel {
+    pub fn convert_to(&self) -> Result<Task> {
+        let mut task = Task::init(Some(self.id));
+        task.set_title(self.title.as_str());
+        task.set_description(self.description.as_str());
+        task.set_due_time(
+            self.due_time
+                .zip(self.due_time_timezone.clone())
+                .map(|(time, timezone)| convert_from_string_to_datetime(time, timezone))
+                        .transpose()?,
+        );
+        task.set_estimate_minutes(self.estimate_minutes);
+        task.restore_completion(
+            TaskStatus::from(self.status.as_str()),
+            self.completed_time
+                .zip(self.completed_time_timezone.clone())
+                .map(|(time, timezone)| convert_from_string_to_datetime(time, timezone))
+                        .transpose()?,
+        );
+        task.restore_checklist(self.checklist.clone());
+        task.set_color(self.color.as_str());
+        task.set_importance(ImportantLevel::from(self.important_level.as_str()));
+        task.set_categories(Category::from(self.category.as_str()));
+        Ok(task)
     }
 }
@@ -0,0 +1,436 @@
+use anyhow::bail;
+use anyhow::Result;
+use chrono::{Datelike, DateTime, Days, FixedOffset, Months, NaiveDate, Utc};
+
+use crate::common::exception::InternalError;
+use crate::model::{ImportantLevel, Kind};
+
+mod test;
+
+// query is a list of terms joined by AND, e.g.
+// kind:event category:Work importance>=High after:2026-01-01 "design review"
+// terms can be combined with OR, negated with NOT or a leading -, and grouped with ( )
+// dates are rfc 3339 times, days like 2026-01-01 or periods relative to now: today,
+// yesterday, tomorrow, this-week, this-month and this-year, all in utc
+// events of subscribed feeds match feed:<name> or feed:*, so -feed:* leaves them out
+// events of hidden calendars only show when the query names their calendar, e.g.
+// calendar:team, events without a calendar are in calendar:main
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    All,
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+    Text(String), // every word starts a word of title, description or tags
+    Condition(Condition),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Kind(Comparison, Kind),
+    Category(Comparison, String),
+    Importance(Comparison, ImportantLevel),
+    Tag(Comparison, String),
+    Title(Comparison, String), // substring, case insensitive
+    Description(Comparison, String),
+    Color(Comparison, String),
+    Feed(Comparison, String), // name of the subscription the event was read from
+    Calendar(Comparison, String),
+    Start(Comparison, DateTime<FixedOffset>),
+    End(Comparison, DateTime<FixedOffset>),
+    Duration(Comparison, i64), // minutes
+}
+
+impl Filter {
+    // calendars the filter names anywhere, negated or not
+    pub fn calendar_names(&self) -> Vec<&str> {
+        match self {
+            Filter::And(filters) | Filter::Or(filters) => {
+                filters.iter().flat_map(|f| f.calendar_names()).collect()
+            }
+            Filter::Not(filter) => filter.calendar_names(),
+            Filter::Condition(Condition::Calendar(_, name)) => vec![name.as_str()],
+            _ => vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LeftParen,
+    RightParen,
+    Minus,
+    Word(String),
+    Quoted(String),
+    Field {
+        field: String,
+        comparison: Comparison,
+        value: String,
+    },
+}
+
+impl Comparison {
+    pub fn compare<T: PartialOrd>(&self, left: &T, right: &T) -> bool {
+        match self {
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+        }
+    }
+
+    fn is_equality(&self) -> bool {
+        matches!(self, Comparison::Equal | Comparison::NotEqual)
+    }
+}
+
+pub fn parse_query(query: &str) -> Result<Filter> {
+    parse_query_at(query, DateTime::from(Utc::now()))
+}
+
+// relative dates are resolved against now, so a saved query is parsed again on every use
+pub fn parse_query_at(query: &str, now: DateTime<FixedOffset>) -> Result<Filter> {
+    let tokens = tokenize_query(query)?;
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        end: query.chars().count(),
+        now,
+    };
+    if parser.tokens.is_empty() {
+        return Ok(Filter::All);
+    }
+    let filter = parser.parse_or()?;
+    if let Some((position, _)) = parser.tokens.get(parser.position) {
+        bail!(query_error(*position, "unexpected )"));
+    }
+    Ok(filter)
+}
+
+fn query_error(position: usize, reason: &str) -> InternalError {
+    InternalError::QueryParseError {
+        position,
+        reason: reason.to_string(),
+    }
+}
+
+// tokens with the char position they start at, for error messages
+fn tokenize_query(query: &str) -> Result<Vec<(usize, Token)>> {
+    let chars = query.chars().collect::<Vec<char>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        match chars[i] {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push((start, Token::LeftParen));
+                i += 1;
+            }
+            ')' => {
+                tokens.push((start, Token::RightParen));
+                i += 1;
+            }
+            '-' => {
+                tokens.push((start, Token::Minus));
+                i += 1;
+            }
+            '"' => {
+                let (text, next) = read_quoted(&chars, i)?;
+                tokens.push((start, Token::Quoted(text)));
+                i = next;
+            }
+            _ => {
+                let mut word = String::new();
+                while i < chars.len() && is_word_char(chars[i]) {
+                    word.push(chars[i]);
+                    i += 1;
+                }
+                let comparison = read_comparison(&chars, &mut i);
+                match comparison {
+                    Some(comparison) if !word.is_empty() => {
+                        let value = if i < chars.len() && chars[i] == '"' {
+                            let (text, next) = read_quoted(&chars, i)?;
+                            i = next;
+                            text
+                        } else {
+                            let mut value = String::new();
+                            while i < chars.len()
+                                && !chars[i].is_whitespace()
+                                && chars[i] != '('
+                                && chars[i] != ')'
+                            {
+                                value.push(chars[i]);
+                                i += 1;
+                            }
+                            value
+                        };
+                        if value.is_empty() {
+                            bail!(query_error(start, "missing value"));
+                        }
+                        tokens.push((
+                            start,
+                            Token::Field {
+                                field: word.to_lowercase(),
+                                comparison,
+                                value,
+                            },
+                        ));
+                    }
+                    Some(_) => bail!(query_error(start, "missing field name")),
+                    None if word.is_empty() => {
+                        bail!(query_error(start, "unexpected character"))
+                    }
+                    None => tokens.push((start, Token::Word(word))),
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '(' | ')' | '"' | ':' | '=' | '!' | '<' | '>')
+}
+
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize)> {
+    let mut text = String::new();
+    let mut i = start + 1;
+    while i < chars.len() && chars[i] != '"' {
+        text.push(chars[i]);
+        i += 1;
+    }
+    if i >= chars.len() {
+        bail!(query_error(start, "unterminated quote"));
+    }
+    Ok((text, i + 1))
+}
+
+fn read_comparison(chars: &[char], i: &mut usize) -> Option<Comparison> {
+    let next = chars.get(*i + 1).copied();
+    let (comparison, length) = match (chars.get(*i).copied()?, next) {
+        (':', _) | ('=', _) => (Comparison::Equal, 1),
+        ('!', Some('=')) => (Comparison::NotEqual, 2),
+        ('>', Some('=')) => (Comparison::GreaterOrEqual, 2),
+        ('<', Some('=')) => (Comparison::LessOrEqual, 2),
+        ('>', _) => (Comparison::Greater, 1),
+        ('<', _) => (Comparison::Less, 1),
+        _ => return None,
+    };
+    *i += length;
+    Some(comparison)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    end: usize, // position reported for errors at the end of the query
+    now: DateTime<FixedOffset>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn current_position(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map_or(self.end, |(position, _)| *position)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word == keyword)
+    }
+
+    fn parse_or(&mut self) -> Result<Filter> {
+        let mut filters = vec![self.parse_and()?];
+        while self.is_keyword("OR") {
+            self.position += 1;
+            filters.push(self.parse_and()?);
+        }
+        Ok(if filters.len() == 1 {
+            filters.remove(0)
+        } else {
+            Filter::Or(filters)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Filter> {
+        let mut filters = vec![self.parse_unary()?];
+        loop {
+            if self.is_keyword("AND") {
+                self.position += 1;
+            }
+            match self.peek() {
+                None | Some(Token::RightParen) => break,
+                _ if self.is_keyword("OR") => break,
+                _ => filters.push(self.parse_unary()?),
+            }
+        }
+        Ok(if filters.len() == 1 {
+            filters.remove(0)
+        } else {
+            Filter::And(filters)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Filter> {
+        if self.is_keyword("NOT") || self.peek() == Some(&Token::Minus) {
+            self.position += 1;
+            return Ok(Filter::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Filter> {
+        let position = self.current_position();
+        let token = match self.tokens.get(self.position) {
+            Some((_, token)) => token.clone(),
+            None => bail!(query_error(position, "unexpected end of query")),
+        };
+        self.position += 1;
+        match token {
+            Token::LeftParen => {
+                let filter = self.parse_or()?;
+                if self.peek() != Some(&Token::RightParen) {
+                    bail!(query_error(self.current_position(), "missing )"));
+                }
+                self.position += 1;
+                Ok(filter)
+            }
+            Token::RightParen => bail!(query_error(position, "unexpected )")),
+            Token::Minus => bail!(query_error(position, "unexpected -")),
+            Token::Word(word) => Ok(Filter::Text(word)),
+            Token::Quoted(text) => Ok(Filter::Text(text)),
+            Token::Field {
+                field,
+                comparison,
+                value,
+            } => parse_condition(position, field.as_str(), comparison, value.as_str(), self.now),
+        }
+    }
+}
+
+fn parse_condition(
+    position: usize,
+    field: &str,
+    comparison: Comparison,
+    value: &str,
+    now: DateTime<FixedOffset>,
+) -> Result<Filter> {
+    let equality_only = |condition: Condition| {
+        if comparison.is_equality() {
+            Ok(Filter::Condition(condition))
+        } else {
+            bail!(query_error(position, "only : and != compare this field"))
+        }
+    };
+    match field {
+        "kind" => {
+            let kind = match value.to_lowercase().as_str() {
+                "event" => Kind::Event,
+                "reminder" => Kind::Reminder,
+                "break" => Kind::Break,
+                _ => bail!(query_error(position, "unknown kind")),
+            };
+            equality_only(Condition::Kind(comparison, kind))
+        }
+        "category" => equality_only(Condition::Category(comparison, value.to_string())),
+        "tag" => equality_only(Condition::Tag(comparison, value.to_string())),
+        "title" => equality_only(Condition::Title(comparison, value.to_string())),
+        "description" => equality_only(Condition::Description(comparison, value.to_string())),
+        "color" => equality_only(Condition::Color(comparison, value.to_string())),
+        "feed" => equality_only(Condition::Feed(comparison, value.to_string())),
+        "calendar" => equality_only(Condition::Calendar(comparison, value.to_string())),
+        "importance" => {
+            let level = match value.to_lowercase().as_str() {
+                "low" => ImportantLevel::Low,
+                "medium" => ImportantLevel::Medium,
+                "high" => ImportantLevel::High,
+                "veryhigh" => ImportantLevel::VeryHigh,
+                _ => bail!(query_error(position, "unknown importance")),
+            };
+            Ok(Filter::Condition(Condition::Importance(comparison, level)))
+        }
+        "duration" => match value.parse::<i64>() {
+            Ok(minutes) => Ok(Filter::Condition(Condition::Duration(comparison, minutes))),
+            Err(_) => bail!(query_error(position, "duration is a number of minutes")),
+        },
+        "start" | "end" | "after" | "before" | "on" => {
+            let (time, period_end) = match parse_time(value, now) {
+                Some(time) => time,
+                None => bail!(query_error(position, "invalid date")),
+            };
+            let condition = |comparison, time| match field {
+                "end" => Filter::Condition(Condition::End(comparison, time)),
+                _ => Filter::Condition(Condition::Start(comparison, time)),
+            };
+            match (field, comparison) {
+                ("after", Comparison::Equal) => Ok(condition(Comparison::GreaterOrEqual, time)),
+                ("before", Comparison::Equal) => Ok(condition(Comparison::Less, time)),
+                ("after" | "before", _) => {
+                    bail!(query_error(position, "after and before only take :"))
+                }
+                // a day or period stands for all of its time
+                ("on", Comparison::Equal) | (_, Comparison::Equal) if period_end.is_some() => {
+                    Ok(Filter::And(vec![
+                        condition(Comparison::GreaterOrEqual, time),
+                        condition(Comparison::Less, period_end.unwrap()),
+                    ]))
+                }
+                ("on", _) => bail!(query_error(position, "on only takes a date")),
+                _ => Ok(condition(comparison, time)),
+            }
+        }
+        _ => bail!(query_error(position, "unknown field")),
+    }
+}
+
+// start of the time and the end when it is a day or period
+fn parse_time(
+    value: &str,
+    now: DateTime<FixedOffset>,
+) -> Option<(DateTime<FixedOffset>, Option<DateTime<FixedOffset>>)> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some((time, None));
+    }
+    let today = now.naive_utc().date();
+    let (start, end) = match value.to_lowercase().as_str() {
+        "today" => (today, today.checked_add_days(Days::new(1))?),
+        "yesterday" => (today.pred_opt()?, today),
+        "tomorrow" => (today.succ_opt()?, today.checked_add_days(Days::new(2))?),
+        "this-week" => {
+            let monday = today.checked_sub_days(Days::new(
+                today.weekday().num_days_from_monday() as u64,
+            ))?;
+            (monday, monday.checked_add_days(Days::new(7))?)
+        }
+        "this-month" => {
+            let first = today.with_day(1)?;
+            (first, first.checked_add_months(Months::new(1))?)
+        }
+        "this-year" => {
+            let first = NaiveDate::from_ymd_opt(today.year(), 1, 1)?;
+            (first, first.checked_add_months(Months::new(12))?)
+        }
+        _ => {
+            let day = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+            (day, day.checked_add_days(Days::new(1))?)
+        }
+    };
+    let midnight = |day: NaiveDate| DateTime::from(day.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    Some((midnight(start), Some(midnight(end))))
+}
@@ -0,0 +1,130 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, FixedOffset};
+
+    use crate::cache::Cache;
+    use crate::model::{EventCommonTrait, ImportantLevel, ItemCommonTrait, Kind};
+    use crate::model::category::UserCategory;
+    use crate::model::event::Event;
+    use crate::model::reminder::Reminder;
+    use crate::query::{Comparison, Condition, Filter, parse_query, parse_query_at};
+
+    #[test]
+    fn parse_terms_into_filter() {
+        let filter = parse_query(r#"kind:event importance>=High after:2026-01-01 "design review""#)
+            .unwrap();
+        let after: DateTime<FixedOffset> = DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z").unwrap();
+        assert_eq!(
+            filter,
+            Filter::And(vec![
+                Filter::Condition(Condition::Kind(Comparison::Equal, Kind::Event)),
+                Filter::Condition(Condition::Importance(
+                    Comparison::GreaterOrEqual,
+                    ImportantLevel::High
+                )),
+                Filter::Condition(Condition::Start(Comparison::GreaterOrEqual, after)),
+                Filter::Text("design review".to_string()),
+            ])
+        );
+        assert_eq!(
+            parse_query("tag:a OR -(tag:b AND NOT title:\"x y\")").unwrap(),
+            Filter::Or(vec![
+                Filter::Condition(Condition::Tag(Comparison::Equal, "a".to_string())),
+                Filter::Not(Box::new(Filter::And(vec![
+                    Filter::Condition(Condition::Tag(Comparison::Equal, "b".to_string())),
+                    Filter::Not(Box::new(Filter::Condition(Condition::Title(
+                        Comparison::Equal,
+                        "x y".to_string()
+                    )))),
+                ]))),
+            ])
+        );
+        assert_eq!(parse_query("  ").unwrap(), Filter::All);
+    }
+
+    #[test]
+    fn relative_dates_resolve_against_now() {
+        let now: DateTime<FixedOffset> = DateTime::parse_from_rfc3339("2026-03-18T15:00:00+01:00").unwrap();
+        let time = |s: &str| DateTime::parse_from_rfc3339(s).unwrap();
+        let range = |start: &str, end: &str| {
+            Filter::And(vec![
+                Filter::Condition(Condition::Start(Comparison::GreaterOrEqual, time(start))),
+                Filter::Condition(Condition::Start(Comparison::Less, time(end))),
+            ])
+        };
+        assert_eq!(
+            parse_query_at("on:this-month", now).unwrap(),
+            range("2026-03-01T00:00:00Z", "2026-04-01T00:00:00Z")
+        );
+        assert_eq!(
+            parse_query_at("start:this-week", now).unwrap(),
+            range("2026-03-16T00:00:00Z", "2026-03-23T00:00:00Z")
+        );
+        assert_eq!(
+            parse_query_at("on:tomorrow", now).unwrap(),
+            range("2026-03-19T00:00:00Z", "2026-03-20T00:00:00Z")
+        );
+        assert_eq!(
+            parse_query_at("before:this-year", now).unwrap(),
+            Filter::Condition(Condition::Start(Comparison::Less, time("2026-01-01T00:00:00Z")))
+        );
+    }
+
+    #[test]
+    fn parse_errors_report_position() {
+        for (query, position) in [
+            ("kind:task", 0),
+            ("title:a (tag:b", 14),
+            ("tag:a )", 6),
+            ("\"open", 0),
+            ("color>red", 0),
+            ("start:someday", 0),
+            ("tag:a unknown:1", 6),
+        ] {
+            let error = parse_query(query).unwrap_err().to_string();
+            assert!(error.starts_with(format!("invalid query at {}", position).as_str()), "{}", error);
+        }
+    }
+
+    #[test]
+    fn evaluate_filter_against_cache() {
+        let mut cache = Cache::init();
+        let work = UserCategory::new("Work", "#ff0000", ImportantLevel::High);
+        cache.add_or_update_categories(vec![work.clone()]);
+        let day: DateTime<FixedOffset> = DateTime::parse_from_rfc3339("2026-02-02T00:00:00Z").unwrap();
+        let mut review = Event::init(None);
+        let review_id = review.get_id();
+        review.set_title("Design review");
+        work.apply_defaults(&mut review);
+        review.set_duration(day + Duration::hours(9), day + Duration::hours(10));
+        let mut standup = Event::init(None);
+        let standup_id = standup.get_id();
+        standup.set_title("Standup");
+        standup.set_importance(ImportantLevel::Low);
+        standup.set_duration(day + Duration::days(1), day + Duration::days(1) + Duration::minutes(15));
+        let mut reminder = Reminder::init(None);
+        let reminder_id = reminder.get_id();
+        reminder.set_title("Review expenses");
+        reminder.set_duration(day, day);
+        cache
+            .insert_events(vec![Box::new(review), Box::new(standup), Box::new(reminder)])
+            .unwrap();
+        cache.set_event_tags(standup_id, vec!["team"]).unwrap();
+
+        let ids = |query: &str| {
+            cache
+                .query_events(&parse_query(query).unwrap())
+                .iter()
+                .map(|e| e.get_id())
+                .collect::<Vec<u128>>()
+        };
+        assert_eq!(ids("kind:event category:work importance>=High \"design rev\""), vec![review_id]);
+        assert_eq!(ids("review"), vec![reminder_id, review_id]);
+        assert_eq!(ids("kind!=reminder"), vec![review_id, standup_id]);
+        assert_eq!(ids("on:2026-02-03 OR tag:team"), vec![standup_id]);
+        assert_eq!(ids("duration<30 -kind:reminder"), vec![standup_id]);
+        assert_eq!(ids("before:2026-02-02T09:00:00Z"), vec![reminder_id]);
+        assert_eq!(ids("color:#FF0000 OR title:expenses"), vec![reminder_id, review_id]);
+        assert_eq!(ids("").len(), 3);
+    }
+}
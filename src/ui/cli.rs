@@ -0,0 +1,553 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail};
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+use tokio::select;
+
+use crate::api::{DEFAULT_ADDRESS, start_server, TrustedProxies};
+use crate::common::exception::InternalError;
+use crate::common::utils::parse_time;
+use crate::core::audit::{get_audit_between, get_event_audit};
+use crate::core::break_planner::{analyse_breaks, plan_breaks};
+use crate::core::calendar::{
+    delete_calendar, find_calendar, get_calendars, move_event, save_calendar,
+};
+use crate::core::event::repeat_event;
+use crate::core::focus::{FocusLink, FocusPhase, FocusSession, FocusSettings, start_focus};
+use crate::core::history::{get_history, redo, set_history_limit, undo};
+use crate::core::query::query_events;
+use crate::core::scheduler::{dismiss_reminder, snooze_reminder};
+use crate::core::search::search_events;
+use crate::core::subscription::{
+    find_subscription, get_subscriptions, refresh_subscription, subscribe_feed, unsubscribe_feed,
+};
+use crate::core::sync::{SyncSettings, sync_calendar};
+use crate::core::tag::{
+    add_event_tags, get_all_tags, get_events_by_tags, remove_event_tags, set_event_tags,
+};
+use crate::core::tracking::{
+    get_running_tracking, get_tracking_report, start_adhoc_tracking, start_tracking, stop_tracking,
+};
+use crate::core::view::{create_view, delete_view, export_view, find_view, get_views, query_view};
+use crate::model::EventCommonTrait;
+use crate::model::audit::AuditEntry;
+use crate::model::break_policy::{BreakPolicy, BreakReport, BreakViolation};
+use crate::model::calendar::Calendar;
+use crate::model::category::ConflictBehavior;
+use crate::model::generator_instance::RepeatStrategy;
+use crate::model::history::HistoryStep;
+use crate::model::search::{SearchHit, SearchQuery};
+use crate::model::sync::{ConflictPolicy, SyncReport};
+use crate::model::tracking::{TrackingGroup, TrackingReport};
+use crate::model::user::UserContext;
+use crate::notification::parse_sinks;
+
+// the user whose data the commands work on, the default one unless set
+pub const USER_VARIABLE: &str = "BREAK_CALENDAR_USER";
+// comma separated addresses of the proxies the server takes the user header from
+pub const TRUSTED_PROXIES_VARIABLE: &str = "BREAK_CALENDAR_TRUSTED_PROXIES";
+// where the server sends notifications besides its log and the live streams
+pub const NOTIFICATION_SINKS_VARIABLE: &str = "BREAK_CALENDAR_NOTIFICATION_SINKS";
+
+pub const USAGE: &str = "usage:
+  break-calendar query <query>           list events matching a query, e.g. kind:event tag:a
+  break-calendar serve [address]         start the http api and caldav at /dav/, default 127.0.0.1:8080
+  break-calendar views                   list saved views
+  break-calendar view-add <name> <query> save a query as a view
+  break-calendar view <name> [query]     list events of a view, optionally narrowed
+  break-calendar view-export <name>      print the view as an .ics feed
+  break-calendar view-delete <name>      delete a saved view
+  break-calendar calendars               list calendars
+  break-calendar calendar-add <name> [color] [hidden] [isolated] [allow|skip]
+                                         add a calendar, hidden ones only show in queries
+                                         naming them, isolated ones only conflict with
+                                         themselves, allow and skip are their conflict rule
+  break-calendar calendar-delete <name>  delete a calendar, its events keep their rules
+  break-calendar move <event id> <calendar>
+                                         move an event to a calendar, main for no calendar
+  break-calendar repeat <event id> <days> <until> [skip-weekends|skip-weekdays]
+                                         repeat an event every days until an RFC 3339 time,
+                                         occurrences that conflict are left out
+  break-calendar subscriptions           list subscribed feeds and their last refresh error
+  break-calendar subscribe <name> <source> [minutes] [conflicts]
+                                         subscribe to a read-only .ics feed by url or path,
+                                         refreshed every minutes, default 60, with conflicts
+                                         our events may not overlap it
+  break-calendar refresh <name>          fetch a subscribed feed again
+  break-calendar unsubscribe <name>      remove a subscribed feed and its events
+  break-calendar undo                    undo the last change
+  break-calendar redo                    redo the last undone change
+  break-calendar history [limit]         list undoable changes, optionally keep at most limit
+  break-calendar audit <event id>        list the field changes of an event
+  break-calendar audit <start> <end>     list the field changes between two RFC 3339 times
+  break-calendar snooze <id> <until> [alarm id]
+                                         fire a reminder, or the alarm of an event, again at
+                                         an RFC 3339 time
+  break-calendar dismiss <id> [alarm id] stop a reminder or alarm from firing again
+  break-calendar breaks <time> [work minutes] [break minutes]
+                                         list break rule violations and the breaks that fit
+                                         on the day of an RFC 3339 time, by default a break of
+                                         10 minutes is due after 90 minutes of work
+  break-calendar breaks-plan <time> [work minutes] [break minutes]
+                                         insert those breaks into the free gaps of the day
+  break-calendar track [event id]        start tracking the actual time of an event, without
+                                         one list the events being tracked
+  break-calendar track-stop <event id>   stop tracking an event
+  break-calendar track-adhoc <title> [category]
+                                         track work that was not planned, as a new event
+  break-calendar tracking <start> <end> [day|category|importance]
+                                         compare planned and actual time between two RFC 3339
+                                         times, by day unless grouped otherwise
+  break-calendar tags                    list tags and how many events carry them
+  break-calendar tag <event id> [tag...] replace the tags of an event, none to clear them
+  break-calendar tag-add <event id> <tag...>
+                                         add tags to an event
+  break-calendar tag-remove <event id> <tag...>
+                                         remove tags from an event
+  break-calendar search <text> [start] [end]
+                                         list events whose title, description or tags
+                                         start with every word of text, best matches first
+  break-calendar tagged <tags> [start] [end]
+                                         list events carrying all comma separated tags,
+                                         optionally between two RFC 3339 times
+  break-calendar focus [minutes] [cycles] [link]
+                                         run a focus timer, default 4 sessions of 25 minutes,
+                                         recording each as an event linked to task:<id> or
+                                         key-result:<id>, ctrl-c stops it
+  break-calendar sync <url> [policy] [query]
+                                         sync with a remote caldav collection, conflicts are
+                                         keep-local, keep-remote or skip, local events matching
+                                         the query are pushed, CALDAV_USERNAME and
+                                         CALDAV_PASSWORD are the credentials
+
+commands work on the data of the user in BREAK_CALENDAR_USER, or of the default user,
+while the server acts for the user an authenticating proxy sets in X-Forwarded-User. the
+header is only accepted from the proxy addresses in BREAK_CALENDAR_TRUSTED_PROXIES, e.g.
+127.0.0.1 for a proxy on the same machine, and rejected from anyone else. besides its log
+and live streams the server sends notifications to the sinks in
+BREAK_CALENDAR_NOTIFICATION_SINKS, separated by ;, each stdout, webhook:<url> or
+command:<program> [args], with @<channel> after the kind for the alarms of a channel only
+";
+
+// output to print on success
+pub async fn run(args: Vec<String>) -> Result<String> {
+    let user = match std::env::var(USER_VARIABLE) {
+        Ok(name) => UserContext::new(name.as_str())?,
+        Err(_) => UserContext::default_user(),
+    };
+    match args.first().map(|command| command.as_str()) {
+        Some("query") => {
+            let events = query_events(&user, args[1..].join(" ").as_str()).await?;
+            Ok(format_events(&events))
+        }
+        Some("serve") => {
+            let address = args.get(1).map_or(DEFAULT_ADDRESS, |address| address.as_str());
+            let proxies = std::env::var(TRUSTED_PROXIES_VARIABLE).unwrap_or_default();
+            let sinks = std::env::var(NOTIFICATION_SINKS_VARIABLE).unwrap_or_default();
+            let (proxies, sinks) =
+                (parse_trusted_proxies(proxies.as_str())?, parse_sinks(sinks.as_str())?);
+            start_server(address, proxies, sinks).await?;
+            Ok(String::new())
+        }
+        Some("views") => Ok(get_views(&user)
+            .await?
+            .iter()
+            .map(|view| format!("{}  {}\n", view.name, view.query))
+            .collect()),
+        Some("view-add") if args.len() >= 3 => {
+            let view = create_view(&user, args[1].clone(), args[2..].join(" ")).await?;
+            Ok(format!("saved view {}\n", view.name))
+        }
+        Some("view") if args.len() >= 2 => {
+            let view = find_view(&user, args[1].clone()).await?;
+            let events = query_view(&user, view.get_id(), args[2..].join(" ").as_str()).await?;
+            Ok(format_events(&events))
+        }
+        Some("view-export") if args.len() == 2 => {
+            export_view(&user, find_view(&user, args[1].clone()).await?.get_id()).await
+        }
+        Some("view-delete") if args.len() == 2 => {
+            let view = find_view(&user, args[1].clone()).await?;
+            delete_view(&user, view.get_id()).await?;
+            Ok(format!("deleted view {}\n", view.name))
+        }
+        Some("calendars") => Ok(get_calendars(&user)
+            .await?
+            .iter()
+            .map(|c| {
+                let visibility = if c.visible { "shown" } else { "hidden" };
+                format!("{}  {}  {}  {:?}\n", c.name, c.color, visibility, c.conflict)
+            })
+            .collect()),
+        Some("calendar-add") if args.len() >= 2 => {
+            let mut calendar = Calendar::new(args[1].as_str(), "");
+            for option in &args[2..] {
+                match option.as_str() {
+                    "hidden" => calendar.visible = false,
+                    "isolated" => calendar.isolated = true,
+                    "allow" => calendar.conflict = ConflictBehavior::Allow,
+                    "skip" => calendar.conflict = ConflictBehavior::Skip,
+                    color => calendar.color = color.to_string(),
+                }
+            }
+            Ok(format!("added calendar {}\n", save_calendar(&user, calendar).await?.name))
+        }
+        Some("calendar-delete") if args.len() == 2 => {
+            let calendar = find_calendar(&user, args[1].clone()).await?;
+            delete_calendar(&user, calendar.get_id()).await?;
+            Ok(format!("deleted calendar {}\n", calendar.name))
+        }
+        Some("move") if args.len() == 3 => {
+            move_event(&user, args[1].parse()?, args[2].clone()).await?;
+            Ok(format!("moved to {}\n", args[2]))
+        }
+        Some("repeat") if (4..=5).contains(&args.len()) => {
+            let strategy = RepeatStrategy {
+                repeat_gap_day: args[2].parse()?,
+                repeat_gap_month: 0,
+                repeat_gap_year: 0,
+                skip_weekday: args.get(4).is_some_and(|skip| skip == "skip-weekdays"),
+                skip_weekend: args.get(4).is_some_and(|skip| skip == "skip-weekends"),
+                skip_holiday: false,
+                start_day: 0,
+                until_day: parse_time(args[3].as_str())?.timestamp_millis(),
+            };
+            let occurrences = repeat_event(&user, args[1].parse()?, strategy, false).await?;
+            Ok(format!("repeated {} times\n", occurrences.len()))
+        }
+        Some("subscriptions") => Ok(get_subscriptions(&user)
+            .await?
+            .iter()
+            .map(|s| match &s.error {
+                Some(error) => format!("{}  {}  {}\n", s.name, s.source, error),
+                None => format!("{}  {}\n", s.name, s.source),
+            })
+            .collect()),
+        Some("subscribe") if args.len() >= 3 => {
+            let refresh_minutes = args.get(3).map(|minutes| minutes.parse()).transpose()?;
+            let count_conflicts = args.get(4).is_some_and(|flag| flag == "conflicts");
+            let (name, source) = (args[1].clone(), args[2].clone());
+            let subscription =
+                subscribe_feed(&user, name, source, refresh_minutes, count_conflicts, true)
+                    .await?;
+            Ok(format!("subscribed to {}\n", subscription.name))
+        }
+        Some("refresh") if args.len() == 2 => {
+            let subscription = find_subscription(&user, args[1].clone()).await?;
+            let events = refresh_subscription(&user, subscription.get_id()).await?;
+            Ok(format!("{} holds {} events\n", subscription.name, events))
+        }
+        Some("unsubscribe") if args.len() == 2 => {
+            let subscription = find_subscription(&user, args[1].clone()).await?;
+            unsubscribe_feed(&user, subscription.get_id()).await?;
+            Ok(format!("unsubscribed from {}\n", subscription.name))
+        }
+        Some("undo") => Ok(format!("undid {}\n", undo(&user).await?.summary())),
+        Some("redo") => Ok(format!("redid {}\n", redo(&user).await?.summary())),
+        Some("history") => {
+            if let Some(limit) = args.get(1) {
+                set_history_limit(&user, limit.parse()?).await?;
+            }
+            let (undo, redo) = get_history(&user).await?;
+            Ok(format_history(&undo, &redo))
+        }
+        Some("audit") if args.len() == 2 => {
+            Ok(format_audit(&get_event_audit(&user, args[1].parse()?).await?))
+        }
+        Some("audit") if args.len() == 3 => {
+            let (start, end) = (parse_time(args[1].as_str())?, parse_time(args[2].as_str())?);
+            Ok(format_audit(&get_audit_between(&user, start, end).await?))
+        }
+        Some("snooze") if (3..=4).contains(&args.len()) => {
+            let alarm_id = args.get(3).map(|id| id.parse()).transpose()?;
+            let until = parse_time(args[2].as_str())?;
+            snooze_reminder(&user, args[1].parse()?, alarm_id, until).await?;
+            Ok(format!("snoozed until {}\n", args[2]))
+        }
+        Some("dismiss") if (2..=3).contains(&args.len()) => {
+            let alarm_id = args.get(2).map(|id| id.parse()).transpose()?;
+            dismiss_reminder(&user, args[1].parse()?, alarm_id).await?;
+            Ok("dismissed\n".to_string())
+        }
+        Some("sync") if args.len() >= 2 => {
+            let policy = args.get(2).map(|p| p.parse::<ConflictPolicy>()).transpose()?;
+            let mut settings =
+                SyncSettings::new(args[1].as_str()).with_policy(policy.unwrap_or_default());
+            if args.len() > 3 {
+                settings = settings.with_query(args[3..].join(" ").as_str());
+            }
+            if let Ok(username) = std::env::var("CALDAV_USERNAME") {
+                let password = std::env::var("CALDAV_PASSWORD").unwrap_or_default();
+                settings = settings.with_credentials(username.as_str(), password.as_str());
+            }
+            Ok(format_sync_report(&sync_calendar(&user, &settings).await?))
+        }
+        Some(command @ ("breaks" | "breaks-plan")) if (2..=4).contains(&args.len()) => {
+            let day = parse_time(args[1].as_str())?;
+            let mut policy = BreakPolicy::default();
+            if args.len() > 2 {
+                let rest = args.get(3).map(|minutes| minutes.parse()).transpose()?;
+                policy = BreakPolicy::new(args[2].parse()?, rest.unwrap_or(10))?
+                    .with_meeting_streak(policy.get_max_meeting_streak())
+                    .with_lunch(policy.get_lunch().cloned())?;
+            }
+            let report = if command == "breaks" {
+                analyse_breaks(&user, day, policy).await?
+            } else {
+                plan_breaks(&user, day, policy).await?
+            };
+            Ok(format_break_report(&report))
+        }
+        Some("track") if args.len() == 1 => Ok(get_running_tracking(&user)
+            .await?
+            .iter()
+            .map(|id| format!("{}\n", id))
+            .collect()),
+        Some("track") if args.len() == 2 => {
+            start_tracking(&user, args[1].parse()?).await?;
+            Ok(format!("tracking {}\n", args[1]))
+        }
+        Some("track-stop") if args.len() == 2 => {
+            stop_tracking(&user, args[1].parse()?).await?;
+            Ok(format!("stopped tracking {}\n", args[1]))
+        }
+        Some("track-adhoc") if (2..=3).contains(&args.len()) => {
+            let category = args.get(2).cloned().unwrap_or_default();
+            let id = start_adhoc_tracking(&user, args[1].clone(), category).await?;
+            Ok(format!("tracking {}\n", id))
+        }
+        Some("tracking") if (3..=4).contains(&args.len()) => {
+            let (start, end) = (parse_time(args[1].as_str())?, parse_time(args[2].as_str())?);
+            let group = args.get(3).map(|g| g.parse::<TrackingGroup>()).transpose()?;
+            let reports =
+                get_tracking_report(&user, start, end, group.unwrap_or(TrackingGroup::Day)).await?;
+            Ok(format_tracking_reports(&reports))
+        }
+        Some("tags") => Ok(get_all_tags(&user)
+            .await?
+            .iter()
+            .map(|(tag, count)| format!("#{}  {}\n", tag, count))
+            .collect()),
+        Some("tag") if args.len() >= 2 => {
+            set_event_tags(&user, args[1].parse()?, args[2..].to_vec()).await?;
+            Ok(format!("tagged {}\n", args[1]))
+        }
+        Some("tag-add") if args.len() >= 3 => {
+            add_event_tags(&user, args[1].parse()?, args[2..].to_vec()).await?;
+            Ok(format!("tagged {}\n", args[1]))
+        }
+        Some("tag-remove") if args.len() >= 3 => {
+            remove_event_tags(&user, args[1].parse()?, args[2..].to_vec()).await?;
+            Ok(format!("untagged {}\n", args[1]))
+        }
+        Some("search") if (2..=4).contains(&args.len()) => {
+            let mut query = SearchQuery::new(args[1].as_str());
+            query.start = args.get(2).map(|time| parse_time(time.as_str())).transpose()?;
+            query.end = args.get(3).map(|time| parse_time(time.as_str())).transpose()?;
+            Ok(format_search_hits(&search_events(&user, query).await?))
+        }
+        Some("tagged") if (2..=4).contains(&args.len()) => {
+            let tags = args[1].split(',').map(|tag| tag.trim().to_string()).collect();
+            let start = args.get(2).map(|time| parse_time(time.as_str())).transpose()?;
+            let end = args.get(3).map(|time| parse_time(time.as_str())).transpose()?;
+            Ok(format_events(&get_events_by_tags(&user, tags, start, end).await?))
+        }
+        Some("focus") => {
+            let mut settings = FocusSettings::default();
+            if let Some(minutes) = args.get(1) {
+                settings.work = Duration::from_secs(minutes.parse::<u64>()? * 60);
+            }
+            if let Some(cycles) = args.get(2) {
+                settings.cycles = cycles.parse()?;
+            }
+            if let Some(link) = args.get(3) {
+                settings.link = link.parse::<FocusLink>()?;
+            }
+            let mut handle = start_focus(&user, settings).await?;
+            // phases are printed as they start, the sessions once the timer is done
+            loop {
+                select! {
+                    phase = handle.next_phase() => {
+                        println!("{}", format_focus_phase(phase));
+                        if phase == FocusPhase::Finished {
+                            break;
+                        }
+                    }
+                    _ = tokio::signal::ctrl_c() => break,
+                }
+            }
+            Ok(format_focus_sessions(&handle.stop().await?))
+        }
+        Some("help") | None => Ok(USAGE.to_string()),
+        Some(command) => bail!("unknown command {}\n{}", command, USAGE),
+    }
+}
+
+// none when not set, so the user header is not accepted from anyone
+pub fn parse_trusted_proxies(proxies: &str) -> Result<TrustedProxies> {
+    let addresses = proxies
+        .split(',')
+        .map(|proxy| proxy.trim())
+        .filter(|proxy| !proxy.is_empty())
+        .map(|proxy| {
+            proxy.parse::<IpAddr>().map_err(|_| {
+                anyhow!(InternalError::InvalidTrustedProxyError {
+                    proxy: proxy.to_string()
+                })
+            })
+        })
+        .collect::<Result<Vec<IpAddr>>>()?;
+    Ok(TrustedProxies(addresses))
+}
+
+pub fn format_break_report(report: &BreakReport) -> String {
+    let time = |time: &DateTime<FixedOffset>| time.format("%H:%M").to_string();
+    let mut output = String::new();
+    for violation in &report.violations {
+        let line = match violation {
+            BreakViolation::LongWorkStreak { start, end } => {
+                format!("work without a break from {} to {}", time(start), time(end))
+            }
+            BreakViolation::MeetingStreak { start, end, count } => {
+                format!("{} events in a row from {} to {}", count, time(start), time(end))
+            }
+            BreakViolation::NoLunchBreak {
+                window_start,
+                window_end,
+            } => format!("no lunch between {} and {}", time(window_start), time(window_end)),
+        };
+        output.push_str(format!("{}\n", line).as_str());
+    }
+    for planned in &report.planned {
+        let line = format!("{}  {}  {}\n", time(&planned.start), time(&planned.end), planned.title);
+        output.push_str(line.as_str());
+    }
+    output
+}
+
+// one line per group, the difference is how much longer it took than planned
+pub fn format_tracking_reports(reports: &[TrackingReport]) -> String {
+    reports
+        .iter()
+        .map(|report| {
+            format!(
+                "{}  planned {:.2}h  actual {:.2}h  {:+}m\n",
+                report.key,
+                report.planned_hours(),
+                report.actual_hours(),
+                report.difference_seconds() / 60
+            )
+        })
+        .collect()
+}
+
+pub fn format_focus_phase(phase: FocusPhase) -> String {
+    match phase {
+        FocusPhase::Work { cycle } => format!("session {} started", cycle),
+        FocusPhase::ShortBreak { cycle } => format!("short break after session {}", cycle),
+        FocusPhase::LongBreak { cycle } => format!("long break after session {}", cycle),
+        FocusPhase::Finished => "focus timer finished".to_string(),
+    }
+}
+
+pub fn format_focus_sessions(sessions: &[FocusSession]) -> String {
+    sessions
+        .iter()
+        .map(|session| {
+            format!(
+                "session {}  {}  {}  {}\n",
+                session.cycle,
+                session.start_time.format("%Y-%m-%d %H:%M"),
+                session.end_time.format("%H:%M"),
+                session.event_id
+            )
+        })
+        .collect()
+}
+
+// redo steps above the undo steps, the next one to undo marked
+pub fn format_history(undo: &[HistoryStep], redo: &[HistoryStep]) -> String {
+    let line = |step: &HistoryStep, mark: &str| {
+        format!(
+            "{} {}  {}\n",
+            mark,
+            DateTime::from_timestamp_millis(step.time)
+                .unwrap_or_default()
+                .format("%Y-%m-%d %H:%M:%S"),
+            step.summary()
+        )
+    };
+    let mut output: String = redo.iter().rev().map(|step| line(step, " ")).collect();
+    for (index, step) in undo.iter().enumerate() {
+        output.push_str(line(step, if index == 0 { ">" } else { " " }).as_str());
+    }
+    output
+}
+
+pub fn format_sync_report(report: &SyncReport) -> String {
+    let mut output = format!(
+        "pulled {}, pushed {}, deleted {} here and {} remotely\n",
+        report.pulled, report.pushed, report.deleted_local, report.deleted_remote
+    );
+    for id in &report.conflicts {
+        output.push_str(format!("conflict {}\n", id).as_str());
+    }
+    output
+}
+
+// one line per entry followed by one indented line per changed field
+pub fn format_audit(entries: &[AuditEntry]) -> String {
+    let mut output = String::new();
+    for entry in entries {
+        output.push_str(
+            format!(
+                "{}  {}  {:?}  {}\n",
+                DateTime::from_timestamp_millis(entry.time)
+                    .unwrap_or_default()
+                    .format("%Y-%m-%d %H:%M:%S"),
+                entry.actor,
+                entry.action,
+                entry.event_id
+            )
+            .as_str(),
+        );
+        for change in &entry.changes {
+            let value = |value: &Option<String>| value.clone().unwrap_or("-".to_string());
+            output.push_str(
+                format!("    {}: {} -> {}\n", change.field, value(&change.old), value(&change.new))
+                    .as_str(),
+            );
+        }
+    }
+    output
+}
+
+// one line per event: start, end, kind, title and tags
+pub fn format_search_hits(hits: &[SearchHit]) -> String {
+    hits.iter()
+        .map(|hit| format!("{:.2}  {}", hit.score, format_events(std::slice::from_ref(&hit.event))))
+        .collect()
+}
+
+pub fn format_events(events: &[Arc<Box<dyn EventCommonTrait>>]) -> String {
+    events
+        .iter()
+        .map(|event| {
+            let mut line = format!(
+                "{}  {}  {:<8}  {}",
+                event.get_start_time().format("%Y-%m-%d %H:%M %:z"),
+                event.get_end_time().format("%Y-%m-%d %H:%M %:z"),
+                event.get_kind().to_string(),
+                event.get_title()
+            );
+            for tag in event.get_tags() {
+                line.push_str(format!("  #{}", tag).as_str());
+            }
+            line.push('\n');
+            line
+        })
+        .collect()
+}
@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{DateTime, Duration, FixedOffset};
+
+    use crate::common::exception::InternalError;
+    use crate::core::processor::dynamic_process;
+    use crate::model::{EventCommonTrait, ItemCommonTrait};
+    use crate::model::event::Event;
+    use crate::model::user::UserContext;
+    use crate::model::tracking::TrackingReport;
+    use crate::ui::cli::{format_events, format_tracking_reports, parse_trusted_proxies, run, USAGE};
+
+    #[test]
+    fn format_one_line_per_event() {
+        let start: DateTime<FixedOffset> = DateTime::parse_from_rfc3339("2026-03-02T09:30:00+08:00").unwrap();
+        let mut event = Event::init(None);
+        event.set_title("Design review");
+        event.set_duration(start, start + Duration::hours(1));
+        event.set_tags(["billable".to_string()].into_iter().collect());
+        let events: Vec<Arc<Box<dyn EventCommonTrait>>> = vec![Arc::new(Box::new(event))];
+        assert_eq!(
+            format_events(&events),
+            "2026-03-02 09:30 +08:00  2026-03-02 10:30 +08:00  Event     Design review  #billable\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn query_command_lists_matching_events() {
+        let start: DateTime<FixedOffset> = DateTime::parse_from_rfc3339("2031-07-01T09:00:00Z").unwrap();
+        let mut event = Event::init(None);
+        event.set_title("cli query quillwort");
+        event.set_duration(start, start);
+        let user = UserContext::default_user();
+        dynamic_process(&user, move |mut cache| cache.insert_events(vec![Box::new(event)]))
+            .await
+            .unwrap();
+
+        let output = run(vec!["query".to_string(), "quillwort".to_string()]).await.unwrap();
+        assert_eq!(output.lines().count(), 1);
+        assert!(output.contains("cli query quillwort"));
+        assert_eq!(run(vec![]).await.unwrap(), USAGE);
+        assert!(run(vec!["query".to_string(), "kind:task".to_string()]).await.is_err());
+        assert!(run(vec!["unknown".to_string()]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn sync_command_rejects_unknown_policies() {
+        let args = ["sync", "http://127.0.0.1:9/cal/", "keep-mine"];
+        let error = run(args.iter().map(|arg| arg.to_string()).collect()).await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<InternalError>(),
+            Some(InternalError::InvalidConflictPolicyError { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn breaks_command_rejects_policies_without_length() {
+        let args = ["breaks", "2031-07-02T00:00:00Z", "0"];
+        let error = run(args.iter().map(|arg| arg.to_string()).collect()).await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<InternalError>(),
+            Some(InternalError::InvalidBreakPolicyError { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn tagged_command_rejects_unreadable_times() {
+        let args = ["tagged", "clientA,billable", "2031-07-01T00:00:00Z", "march"];
+        let error = run(args.iter().map(|arg| arg.to_string()).collect()).await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<InternalError>(),
+            Some(InternalError::InvalidTimeError { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn search_command_rejects_unreadable_times() {
+        let args = ["search", "standup", "yesterday"];
+        let error = run(args.iter().map(|arg| arg.to_string()).collect()).await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<InternalError>(),
+            Some(InternalError::InvalidTimeError { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn tracking_command_rejects_unknown_groups() {
+        let args = ["tracking", "2031-07-01T00:00:00Z", "2031-08-01T00:00:00Z", "week"];
+        let error = run(args.iter().map(|arg| arg.to_string()).collect()).await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<InternalError>(),
+            Some(InternalError::InvalidTrackingGroupError { .. })
+        ));
+    }
+
+    #[test]
+    fn tracking_reports_show_the_difference_in_minutes() {
+        let report = TrackingReport {
+            key: "2031-07-01".to_string(),
+            planned_seconds: 3600,
+            actual_seconds: 5400,
+            unplanned_seconds: 0,
+        };
+        assert_eq!(
+            format_tracking_reports(&[report]),
+            "2031-07-01  planned 1.00h  actual 1.50h  +30m\n"
+        );
+    }
+
+    #[test]
+    fn trusted_proxies_are_none_unless_listed() {
+        assert!(parse_trusted_proxies("").unwrap().0.is_empty());
+        let proxies = parse_trusted_proxies("127.0.0.1, ::1").unwrap();
+        assert_eq!(proxies.0.len(), 2);
+        let error = parse_trusted_proxies("proxy.example.com").unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<InternalError>(),
+            Some(InternalError::InvalidTrustedProxyError { .. })
+        ));
+    }
+}